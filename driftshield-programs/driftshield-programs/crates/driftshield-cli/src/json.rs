@@ -0,0 +1,308 @@
+//! JSON views of decoded accounts for `driftshield account`.
+
+use driftshield_sdk::accounts::*;
+use driftshield_sdk::agent_registry::AgentStatus;
+use driftshield_sdk::insurance::PolicyStatus;
+use driftshield_sdk::model_registry::ModelStatus;
+use driftshield_sdk::prediction_market::{Comparator, DriftCondition, DriftMetric, MarketStatus, PriceObservation};
+use serde_json::{json, Value};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn market_status(status: MarketStatus) -> &'static str {
+    match status {
+        MarketStatus::Open => "Open",
+        MarketStatus::Resolved => "Resolved",
+        MarketStatus::Cancelled => "Cancelled",
+        MarketStatus::Closed => "Closed",
+    }
+}
+
+fn model_status(status: ModelStatus) -> &'static str {
+    match status {
+        ModelStatus::Active => "Active",
+        ModelStatus::DriftDetected => "DriftDetected",
+        ModelStatus::Paused => "Paused",
+    }
+}
+
+fn policy_status(status: PolicyStatus) -> &'static str {
+    match status {
+        PolicyStatus::Active => "Active",
+        PolicyStatus::Claimed => "Claimed",
+        PolicyStatus::Expired => "Expired",
+        PolicyStatus::Cancelled => "Cancelled",
+    }
+}
+
+fn agent_status(status: AgentStatus) -> &'static str {
+    match status {
+        AgentStatus::Active => "Active",
+        AgentStatus::Suspended => "Suspended",
+    }
+}
+
+fn drift_condition(condition: &DriftCondition) -> Value {
+    let metric = match condition.metric {
+        DriftMetric::Accuracy => "Accuracy",
+        DriftMetric::Precision => "Precision",
+        DriftMetric::Recall => "Recall",
+        DriftMetric::F1Score => "F1Score",
+        DriftMetric::DriftScore => "DriftScore",
+    };
+    let comparator = match condition.comparator {
+        Comparator::Below => "Below",
+        Comparator::AtOrBelow => "AtOrBelow",
+        Comparator::Above => "Above",
+        Comparator::AtOrAbove => "AtOrAbove",
+    };
+    json!({ "metric": metric, "comparator": comparator, "threshold": condition.threshold })
+}
+
+fn observation(obs: &PriceObservation) -> Value {
+    json!({
+        "timestamp": obs.timestamp,
+        "yes_price_cumulative": obs.yes_price_cumulative.to_string(),
+        "no_price_cumulative": obs.no_price_cumulative.to_string(),
+    })
+}
+
+fn market(m: &Market) -> Value {
+    json!({
+        "creator": m.creator.to_string(),
+        "model": m.model.to_string(),
+        "question": m.question,
+        "yes_pool": m.yes_pool,
+        "no_pool": m.no_pool,
+        "total_volume": m.total_volume,
+        "status": market_status(m.status),
+        "resolution_time": m.resolution_time,
+        "resolved_at": m.resolved_at,
+        "winning_outcome": m.winning_outcome,
+        "min_stake": m.min_stake,
+        "created_at": m.created_at,
+        "bump": m.bump,
+        "amm_enabled": m.amm_enabled,
+        "yes_reserve": m.yes_reserve,
+        "no_reserve": m.no_reserve,
+        "k_constant": m.k_constant.to_string(),
+        "total_yes_shares": m.total_yes_shares,
+        "total_no_shares": m.total_no_shares,
+        "yes_price_cumulative": m.yes_price_cumulative.to_string(),
+        "no_price_cumulative": m.no_price_cumulative.to_string(),
+        "last_price_update": m.last_price_update,
+        "max_position_stake": m.max_position_stake,
+        "max_wallet_oi_bps": m.max_wallet_oi_bps,
+        "max_price_impact_bps": m.max_price_impact_bps,
+        "wallet_oi_floor": m.wallet_oi_floor,
+        "allowlist_root": m.allowlist_root.map(|root| hex(&root)),
+        "creator_bond": m.creator_bond,
+        "bond_vault": m.bond_vault.to_string(),
+        "bond_settled": m.bond_settled,
+        "vault": m.vault.to_string(),
+        "total_collateral": m.total_collateral,
+        "open_stake": m.open_stake,
+        "refund_pool": m.refund_pool,
+        "lp_claimable": m.lp_claimable,
+        "lp_fees": m.lp_fees,
+        "parent_market": m.parent_market.map(|key| key.to_string()),
+        "parent_outcome": m.parent_outcome,
+        "drift_condition": m.drift_condition.as_ref().map(drift_condition),
+        "reward_pool": m.reward_pool.map(|key| key.to_string()),
+        "fee_ramp_bps": m.fee_ramp_bps,
+        "fee_ramp_window": m.fee_ramp_window,
+        "volatility_fee_multiplier_bps": m.volatility_fee_multiplier_bps,
+        "volatility_window": m.volatility_window,
+        "volatility_ref_old": observation(&m.volatility_ref_old),
+        "volatility_ref_new": observation(&m.volatility_ref_new),
+        "max_dynamic_fee_bps": m.max_dynamic_fee_bps,
+        "breaker_move_bps": m.breaker_move_bps,
+        "breaker_window": m.breaker_window,
+        "breaker_cooldown": m.breaker_cooldown,
+        "breaker_ref_price": m.breaker_ref_price,
+        "breaker_ref_time": m.breaker_ref_time,
+        "halted_until": m.halted_until,
+        "legacy": m.legacy,
+        "version": m.version,
+    })
+}
+
+/// JSON for any decoded DriftShield account, tagged with its type
+pub fn account(decoded: &DecodedAccount) -> Value {
+    let data = match decoded {
+        DecodedAccount::Model(m) => json!({
+            "owner": m.owner.to_string(),
+            "model_id": m.model_id,
+            "name": m.name,
+            "model_type": m.model_type,
+            "framework": m.framework,
+            "baseline_accuracy": m.baseline_accuracy,
+            "current_accuracy": m.current_accuracy,
+            "total_checks": m.total_checks,
+            "drift_alerts": m.drift_alerts,
+            "status": model_status(m.status),
+            "created_at": m.created_at,
+            "last_check_at": m.last_check_at,
+            "is_insured": m.is_insured,
+            "has_active_market": m.has_active_market,
+            "version": m.version,
+        }),
+        DecodedAccount::Receipt(r) => json!({
+            "model": r.model.to_string(),
+            "checker": r.checker.to_string(),
+            "accuracy": r.accuracy,
+            "precision": r.precision,
+            "recall": r.recall,
+            "f1_score": r.f1_score,
+            "drift_score": r.drift_score,
+            "metadata_uri": r.metadata_uri,
+            "timestamp": r.timestamp,
+            "version": r.version,
+        }),
+        DecodedAccount::Policy(p) => json!({
+            "owner": p.owner.to_string(),
+            "model": p.model.to_string(),
+            "coverage_amount": p.coverage_amount,
+            "premium_paid": p.premium_paid,
+            "accuracy_threshold": p.accuracy_threshold,
+            "status": policy_status(p.status),
+            "start_time": p.start_time,
+            "expiry_time": p.expiry_time,
+            "claim_paid": p.claim_paid,
+            "bump": p.bump,
+            "version": p.version,
+        }),
+        DecodedAccount::Market(m) => market(m),
+        DecodedAccount::Position(p) => json!({
+            "market": p.market.to_string(),
+            "user": p.user.to_string(),
+            "yes_stake": p.yes_stake,
+            "no_stake": p.no_stake,
+            "total_stake": p.total_stake,
+            "yes_shares": p.yes_shares,
+            "no_shares": p.no_shares,
+            "claimed": p.claimed,
+            "version": p.version,
+        }),
+        DecodedAccount::Config(c) => json!({
+            "admin": c.admin.to_string(),
+            "treasury": c.treasury.to_string(),
+            "creation_bond": c.creation_bond,
+            "creation_fee": c.creation_fee,
+            "bump": c.bump,
+            "trading_fee_bps": c.trading_fee_bps,
+            "referral_fee_bps": c.referral_fee_bps,
+            "referral_vault": c.referral_vault.to_string(),
+            "version": c.version,
+            "crank_tip": c.crank_tip,
+            "crank_vault": c.crank_vault.to_string(),
+        }),
+        DecodedAccount::Referral(r) => json!({
+            "referrer": r.referrer.to_string(),
+            "referred_volume": r.referred_volume,
+            "referred_trades": r.referred_trades,
+            "total_earned": r.total_earned,
+            "unclaimed": r.unclaimed,
+            "total_claimed": r.total_claimed,
+            "created_at": r.created_at,
+            "bump": r.bump,
+            "version": r.version,
+        }),
+        DecodedAccount::RewardPool(p) => json!({
+            "sponsor": p.sponsor.to_string(),
+            "pool_id": p.pool_id,
+            "reward_vault": p.reward_vault.to_string(),
+            "emission_rate": p.emission_rate,
+            "start_time": p.start_time,
+            "end_time": p.end_time,
+            "last_update": p.last_update,
+            "reward_per_share": p.reward_per_share.to_string(),
+            "total_staked": p.total_staked,
+            "total_funded": p.total_funded,
+            "unallocated": p.unallocated,
+            "bump": p.bump,
+            "version": p.version,
+        }),
+        DecodedAccount::RewardStake(s) => json!({
+            "pool": s.pool.to_string(),
+            "user": s.user.to_string(),
+            "staked": s.staked,
+            "reward_debt": s.reward_debt.to_string(),
+            "pending": s.pending,
+            "total_claimed": s.total_claimed,
+            "bump": s.bump,
+            "version": s.version,
+        }),
+        DecodedAccount::UserStats(s) => json!({
+            "user": s.user.to_string(),
+            "total_volume": s.total_volume,
+            "trade_count": s.trade_count,
+            "markets_traded": s.markets_traded,
+            "realized_pnl": s.realized_pnl,
+            "winning_resolutions": s.winning_resolutions,
+            "losing_resolutions": s.losing_resolutions,
+            "fees_paid": s.fees_paid,
+            "last_trade_at": s.last_trade_at,
+            "bump": s.bump,
+            "version": s.version,
+        }),
+        DecodedAccount::SessionKey(s) => json!({
+            "owner": s.owner.to_string(),
+            "session_key": s.session_key.to_string(),
+            "vault": s.vault.to_string(),
+            "spend_cap": s.spend_cap,
+            "spent": s.spent,
+            "allowed_markets": s.allowed_markets.iter().map(|key| key.to_string()).collect::<Vec<_>>(),
+            "expires_at": s.expires_at,
+            "created_at": s.created_at,
+            "bump": s.bump,
+            "version": s.version,
+        }),
+        DecodedAccount::PriceHistory(h) => {
+            // Oldest sample first
+            let capacity = h.samples.len();
+            let start = (h.head as usize + capacity - h.len as usize) % capacity;
+            let samples: Vec<Value> = (0..h.len as usize)
+                .map(|i| &h.samples[(start + i) % capacity])
+                .map(|s| json!({
+                    "timestamp": s.timestamp,
+                    "yes_price": s.yes_price,
+                    "no_price": s.no_price,
+                    "volume": s.volume,
+                }))
+                .collect();
+            json!({
+                "market": h.market.to_string(),
+                "sample_interval": h.sample_interval,
+                "last_sample_at": h.last_sample_at,
+                "pending_volume": h.pending_volume,
+                "samples": samples,
+                "version": h.version,
+            })
+        }
+        DecodedAccount::RegistryConfig(c) => json!({
+            "admin": c.admin.to_string(),
+            "total_agents": c.total_agents,
+            "bump": c.bump,
+            "version": c.version,
+        }),
+        DecodedAccount::Agent(a) => json!({
+            "owner": a.owner.to_string(),
+            "agent_id": a.agent_id,
+            "display_name": a.display_name,
+            "strategy_hash": hex(&a.strategy_hash),
+            "trading_wallet": a.trading_wallet.to_string(),
+            "verified": a.verified,
+            "status": agent_status(a.status),
+            "suspended_by_admin": a.suspended_by_admin,
+            "created_at": a.created_at,
+            "updated_at": a.updated_at,
+            "bump": a.bump,
+            "version": a.version,
+        }),
+    };
+
+    json!({ "type": decoded.name(), "data": data })
+}
@@ -0,0 +1,447 @@
+//! `driftshield` - operate DriftShield models, markets and policies from the command line.
+
+#![recursion_limit = "256"]
+
+mod json;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use driftshield_sdk::accounts::{DecodedAccount, InsurancePolicy, Market, Position, ProtocolConfig};
+use driftshield_sdk::client::{load_keypair, parse_commitment, Client, TxOutcome};
+use driftshield_sdk::instructions::{self, CreateMarketAccounts, MarketTerms, ReceiptMetrics, Trader};
+use driftshield_sdk::prediction_market::MarketStatus;
+use driftshield_sdk::{pda, pricing};
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+
+#[derive(Parser)]
+#[command(name = "driftshield", version, about = "Operate DriftShield models, markets and policies")]
+struct Cli {
+    /// RPC URL or moniker (localnet, devnet, testnet, mainnet-beta)
+    #[arg(short = 'u', long, global = true, env = "DRIFTSHIELD_RPC_URL", default_value = "localnet")]
+    url: String,
+
+    /// Keypair file used to sign and pay for transactions
+    #[arg(short = 'k', long, global = true, env = "DRIFTSHIELD_KEYPAIR")]
+    keypair: Option<String>,
+
+    /// Commitment level (processed, confirmed, finalized)
+    #[arg(long, global = true, default_value = "confirmed")]
+    commitment: String,
+
+    /// Simulate transactions and print the logs instead of sending them
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Model registry
+    #[command(subcommand)]
+    Model(ModelCommand),
+    /// Prediction markets
+    #[command(subcommand)]
+    Market(MarketCommand),
+    /// Insurance policies
+    #[command(subcommand)]
+    Policy(PolicyCommand),
+    /// Print any DriftShield account as JSON
+    Account {
+        address: Pubkey,
+    },
+}
+
+#[derive(Subcommand)]
+enum ModelCommand {
+    /// Register a model for monitoring
+    Register {
+        #[arg(long)]
+        model_id: String,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        model_type: String,
+        #[arg(long)]
+        framework: String,
+        /// Basis points (9420 = 94.20%)
+        #[arg(long)]
+        baseline_accuracy: u64,
+    },
+    /// Submit a monitoring receipt for a model you own
+    SubmitReceipt {
+        #[arg(long)]
+        model: Pubkey,
+        #[arg(long)]
+        accuracy: u64,
+        #[arg(long, default_value_t = 0)]
+        precision: u64,
+        #[arg(long, default_value_t = 0)]
+        recall: u64,
+        #[arg(long, default_value_t = 0)]
+        f1_score: u64,
+        #[arg(long, default_value_t = 0)]
+        drift_score: u64,
+        #[arg(long, default_value = "")]
+        metadata_uri: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Outcome {
+    Yes,
+    No,
+}
+
+impl Outcome {
+    fn as_bool(self) -> bool {
+        matches!(self, Outcome::Yes)
+    }
+}
+
+#[derive(Args)]
+struct TradeArgs {
+    #[arg(long)]
+    market: Pubkey,
+    #[arg(long, value_enum)]
+    outcome: Outcome,
+    /// Collateral token account (defaults to the signer's associated token account)
+    #[arg(long)]
+    token_account: Option<Pubkey>,
+    /// Wallet that referred this trade
+    #[arg(long)]
+    referrer: Option<Pubkey>,
+}
+
+#[derive(Subcommand)]
+enum MarketCommand {
+    /// Create a market on a model, seeding the AMM with initial liquidity
+    Create {
+        #[arg(long)]
+        model: Pubkey,
+        #[arg(long)]
+        question: String,
+        /// Unix timestamp
+        #[arg(long)]
+        resolution_time: i64,
+        #[arg(long)]
+        min_stake: u64,
+        #[arg(long)]
+        initial_liquidity: u64,
+        /// Collateral token account (defaults to the signer's associated token account)
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+    },
+    /// Buy outcome shares
+    Bet {
+        #[command(flatten)]
+        trade: TradeArgs,
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Sell outcome shares back to the AMM
+    Sell {
+        #[command(flatten)]
+        trade: TradeArgs,
+        #[arg(long)]
+        shares: u64,
+    },
+    /// Quote a buy or sell for your wallet off-chain with the on-chain pricing math
+    Quote {
+        #[arg(long)]
+        market: Pubkey,
+        #[arg(long, value_enum)]
+        outcome: Outcome,
+        /// Collateral to spend
+        #[arg(long, conflicts_with = "sell")]
+        buy: Option<u64>,
+        /// Shares to sell
+        #[arg(long)]
+        sell: Option<u64>,
+    },
+    /// Resolve a market you created
+    Resolve {
+        #[arg(long)]
+        market: Pubkey,
+        #[arg(long, value_enum)]
+        outcome: Outcome,
+    },
+    /// Claim winnings (resolved) or a refund (cancelled)
+    Claim {
+        #[arg(long)]
+        market: Pubkey,
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+    },
+}
+
+#[derive(Args)]
+struct PolicyArgs {
+    #[arg(long)]
+    model: Pubkey,
+    /// Insurance vault token account
+    #[arg(long)]
+    vault: Pubkey,
+    /// Token account paying or receiving funds (defaults to the signer's associated token account)
+    #[arg(long)]
+    token_account: Option<Pubkey>,
+}
+
+#[derive(Subcommand)]
+enum PolicyCommand {
+    /// Buy drift insurance for a model
+    Purchase {
+        #[command(flatten)]
+        policy: PolicyArgs,
+        #[arg(long)]
+        coverage: u64,
+        #[arg(long)]
+        premium: u64,
+        /// Basis points; claims pay out below this accuracy
+        #[arg(long)]
+        accuracy_threshold: u64,
+        #[arg(long)]
+        duration_days: i64,
+    },
+    /// Claim a policy payout
+    Claim {
+        #[command(flatten)]
+        policy: PolicyArgs,
+        /// Basis points
+        #[arg(long)]
+        current_accuracy: u64,
+    },
+    /// Cancel a policy for a pro-rated premium refund
+    Cancel {
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let payer = load_keypair(cli.keypair.as_deref())?;
+    let commitment = parse_commitment(&cli.commitment)?;
+    let client = Client::new(&cli.url, payer, commitment, cli.dry_run);
+
+    match cli.command {
+        Command::Model(command) => model(&client, command),
+        Command::Market(command) => market(&client, command),
+        Command::Policy(command) => policy(&client, command),
+        Command::Account { address } => {
+            let account = client
+                .account(&address)?
+                .with_context(|| format!("account {} not found", address))?;
+            let decoded = DecodedAccount::decode(&account.owner, &account.data)?;
+            println!("{}", serde_json::to_string_pretty(&json::account(&decoded))?);
+            Ok(())
+        }
+    }
+}
+
+fn report(outcome: TxOutcome) -> Result<()> {
+    match outcome.signature {
+        Some(signature) => println!("Signature: {}", signature),
+        None => {
+            println!("Simulation logs:");
+            for line in &outcome.logs {
+                println!("  {}", line);
+            }
+        }
+    }
+    if let Some(units) = outcome.units_consumed {
+        println!("Compute units: {}", units);
+    }
+    for event in &outcome.events {
+        println!("Event: {}", event.name());
+    }
+    match outcome.err {
+        Some(err) => bail!("transaction failed: {}", err),
+        None => Ok(()),
+    }
+}
+
+fn token_account_for(client: &Client, explicit: Option<Pubkey>, mint: &Pubkey) -> Pubkey {
+    explicit.unwrap_or_else(|| get_associated_token_address(&client.payer(), mint))
+}
+
+fn model(client: &Client, command: ModelCommand) -> Result<()> {
+    match command {
+        ModelCommand::Register { model_id, name, model_type, framework, baseline_accuracy } => {
+            let (model, _) = pda::model(&client.payer(), &model_id);
+            println!("Model: {}", model);
+            let ix = instructions::register_model(client.payer(), model_id, name, model_type, framework, baseline_accuracy);
+            report(client.send(&[ix], &[])?)
+        }
+        ModelCommand::SubmitReceipt { model, accuracy, precision, recall, f1_score, drift_score, metadata_uri } => {
+            let receipt = Keypair::new();
+            println!("Receipt: {}", receipt.pubkey());
+            let metrics = ReceiptMetrics { accuracy, precision, recall, f1_score, drift_score, metadata_uri };
+            let ix = instructions::submit_monitoring_receipt(client.payer(), model, receipt.pubkey(), metrics);
+            report(client.send(&[ix], &[&receipt])?)
+        }
+    }
+}
+
+/// Trader for the signer
+fn trader(client: &Client, market: &Market, trade: &TradeArgs) -> Result<Trader> {
+    let mint = client.token_mint(&market.vault)?;
+    let mut trader = Trader::new(client.payer(), token_account_for(client, trade.token_account, &mint));
+    trader.referrer = trade.referrer;
+    Ok(trader)
+}
+
+fn market(client: &Client, command: MarketCommand) -> Result<()> {
+    match command {
+        MarketCommand::Create { model, question, resolution_time, min_stake, initial_liquidity, token_account } => {
+            let config: ProtocolConfig = client.fetch(&pda::config().0)?;
+            let mint = client.token_mint(&config.treasury)?;
+            let (market, _) = pda::market(&client.payer(), &model);
+
+            // Collateral and bond vaults are token accounts owned by the market PDA
+            let market_vault = Keypair::new();
+            let bond_vault = Keypair::new();
+            let rent = client.rpc.get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?;
+            let mut ixs = Vec::new();
+            for vault in [&market_vault, &bond_vault] {
+                ixs.push(system_instruction::create_account(
+                    &client.payer(),
+                    &vault.pubkey(),
+                    rent,
+                    spl_token::state::Account::LEN as u64,
+                    &spl_token::ID,
+                ));
+                ixs.push(spl_token::instruction::initialize_account3(&spl_token::ID, &vault.pubkey(), &mint, &market)?);
+            }
+
+            let accounts = CreateMarketAccounts {
+                creator: client.payer(),
+                model,
+                creator_token_account: token_account_for(client, token_account, &mint),
+                market_vault: market_vault.pubkey(),
+                bond_vault: bond_vault.pubkey(),
+            };
+            let terms = MarketTerms { resolution_time, min_stake, initial_liquidity, allowlist_root: None };
+            ixs.push(instructions::create_market(&config, &accounts, question, terms));
+
+            println!("Market: {}", market);
+            report(client.send(&ixs, &[&market_vault, &bond_vault])?)
+        }
+        MarketCommand::Bet { trade, amount } => {
+            let config: ProtocolConfig = client.fetch(&pda::config().0)?;
+            let market: Market = client.fetch(&trade.market)?;
+            let trader = trader(client, &market, &trade)?;
+            let ix = instructions::place_bet(&config, trade.market, &market, &trader, trade.outcome.as_bool(), amount, Vec::new());
+            report(client.send(&[ix], &[])?)
+        }
+        MarketCommand::Sell { trade, shares } => {
+            let config: ProtocolConfig = client.fetch(&pda::config().0)?;
+            let market: Market = client.fetch(&trade.market)?;
+            let trader = trader(client, &market, &trade)?;
+            let ix = instructions::sell_shares(&config, trade.market, &market, &trader, trade.outcome.as_bool(), shares);
+            report(client.send(&[ix], &[])?)
+        }
+        MarketCommand::Quote { market: market_key, outcome, buy, sell } => {
+            let config: ProtocolConfig = client.fetch(&pda::config().0)?;
+            let market: Market = client.fetch(&market_key)?;
+            // Quoted for the signer, so their position's limits and shares apply
+            let (position_key, _) = pda::position(&market_key, &client.payer());
+            let position: Option<Position> =
+                if client.exists(&position_key)? { Some(client.fetch(&position_key)?) } else { None };
+            let now = client.now()?;
+            let quote = match (buy, sell) {
+                (Some(amount), None) => {
+                    let q = pricing::quote_buy(&config, &market, position.as_ref(), outcome.as_bool(), amount, now)?;
+                    serde_json::json!({
+                        "amount": q.amount,
+                        "fee": q.fee,
+                        "fee_bps": q.fee_bps,
+                        "net_amount": q.net_amount,
+                        "shares": q.shares,
+                        "average_price": q.average_price,
+                        "price_impact": q.price_impact,
+                        "yes_price_after": q.yes_price_after,
+                        "no_price_after": q.no_price_after,
+                    })
+                }
+                (None, Some(shares)) => {
+                    let q = pricing::quote_sell(&config, &market, position.as_ref(), outcome.as_bool(), shares, now)?;
+                    serde_json::json!({
+                        "shares": q.shares,
+                        "gross_proceeds": q.gross_proceeds,
+                        "fee": q.fee,
+                        "fee_bps": q.fee_bps,
+                        "proceeds": q.proceeds,
+                        "average_price": q.average_price,
+                        "price_impact": q.price_impact,
+                        "yes_price_after": q.yes_price_after,
+                        "no_price_after": q.no_price_after,
+                    })
+                }
+                _ => bail!("pass exactly one of --buy or --sell"),
+            };
+            println!("{}", serde_json::to_string_pretty(&quote)?);
+            Ok(())
+        }
+        MarketCommand::Resolve { market: market_key, outcome } => {
+            let market: Market = client.fetch(&market_key)?;
+            let ix = instructions::resolve_market(client.payer(), market_key, &market, outcome.as_bool());
+            report(client.send(&[ix], &[])?)
+        }
+        MarketCommand::Claim { market: market_key, token_account } => {
+            let market: Market = client.fetch(&market_key)?;
+            let mint = client.token_mint(&market.vault)?;
+            let token_account = token_account_for(client, token_account, &mint);
+            let ix = match market.status {
+                MarketStatus::Resolved => {
+                    instructions::claim_winnings(market_key, &market, &Trader::new(client.payer(), token_account))
+                }
+                MarketStatus::Cancelled => instructions::claim_refund(market_key, &market, client.payer(), token_account),
+                MarketStatus::Open | MarketStatus::Closed => bail!("market {} is not resolved yet", market_key),
+            };
+            report(client.send(&[ix], &[])?)
+        }
+    }
+}
+
+fn policy(client: &Client, command: PolicyCommand) -> Result<()> {
+    let (args, ix) = match command {
+        PolicyCommand::Purchase { policy, coverage, premium, accuracy_threshold, duration_days } => {
+            let token_account = token_account_for(client, policy.token_account, &client.token_mint(&policy.vault)?);
+            let ix = instructions::purchase_policy(
+                client.payer(),
+                policy.model,
+                token_account,
+                policy.vault,
+                coverage,
+                premium,
+                accuracy_threshold,
+                duration_days,
+            );
+            (policy, ix)
+        }
+        PolicyCommand::Claim { policy, current_accuracy } => {
+            let token_account = token_account_for(client, policy.token_account, &client.token_mint(&policy.vault)?);
+            let ix = instructions::file_claim(client.payer(), policy.model, token_account, policy.vault, current_accuracy);
+            (policy, ix)
+        }
+        PolicyCommand::Cancel { policy } => {
+            let token_account = token_account_for(client, policy.token_account, &client.token_mint(&policy.vault)?);
+            let (policy_key, _) = pda::policy(&client.payer(), &policy.model);
+            let existing: InsurancePolicy = client.fetch(&policy_key)?;
+            println!("Expected refund: {}", pricing::policy_refund(&existing, client.now()?)?);
+            let ix = instructions::cancel_policy(client.payer(), policy.model, token_account, policy.vault);
+            (policy, ix)
+        }
+    };
+
+    println!("Policy: {}", pda::policy(&client.payer(), &args.model).0);
+    report(client.send(&[ix], &[])?)
+}
@@ -0,0 +1,241 @@
+//! SQLite storage.
+//!
+//! Transactions are indexed at `confirmed` commitment and flagged `finalized`
+//! once their slot is rooted. Provisional (non-finalized) rows are dropped and
+//! rebuilt on every poll, so a transaction lost to a fork disappears with its
+//! events. Cursors only ever advance to finalized signatures.
+
+use anyhow::Result;
+use driftshield_sdk::events::ProgramEvent;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+
+use crate::tables;
+
+const CORE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS cursors (
+    program_id TEXT PRIMARY KEY,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS transactions (
+    signature TEXT PRIMARY KEY,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    finalized INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    signature TEXT NOT NULL REFERENCES transactions(signature) ON DELETE CASCADE,
+    event_index INTEGER NOT NULL,
+    program_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    UNIQUE (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS events_name ON events(name);
+CREATE INDEX IF NOT EXISTS events_slot ON events(slot);
+CREATE INDEX IF NOT EXISTS transactions_finalized ON transactions(finalized);
+";
+
+/// A transaction to store along with its decoded events
+pub struct IndexedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub finalized: bool,
+    pub events: Vec<ProgramEvent>,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+        conn.execute_batch(CORE_SCHEMA)?;
+        for statement in tables::schema() {
+            conn.execute(&statement, [])?;
+        }
+        Ok(Self { conn })
+    }
+
+    /// Newest finalized signature indexed for `program_id`
+    pub fn cursor(&self, program_id: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row("SELECT signature FROM cursors WHERE program_id = ?1", [program_id], |row| row.get(0))
+            .optional()?)
+    }
+
+    pub fn is_finalized(&self, signature: &str) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT finalized FROM transactions WHERE signature = ?1",
+                [signature],
+                |row| row.get::<_, bool>(0),
+            )
+            .optional()?
+            .unwrap_or(false))
+    }
+
+    /// Atomically replace provisional rows with `transactions` and advance cursors
+    pub fn commit_poll(&mut self, transactions: &[IndexedTransaction], cursors: &[(String, String, u64)]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM transactions WHERE finalized = 0", [])?;
+
+        for indexed in transactions {
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO transactions (signature, slot, block_time, finalized) VALUES (?1, ?2, ?3, ?4)",
+                params![indexed.signature, indexed.slot as i64, indexed.block_time, indexed.finalized],
+            )?;
+            if inserted == 0 {
+                continue; // Already stored as finalized
+            }
+
+            for (index, event) in indexed.events.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO events (signature, event_index, program_id, name, slot, block_time) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        indexed.signature,
+                        index as i64,
+                        event.program_id().to_string(),
+                        event.name(),
+                        indexed.slot as i64,
+                        indexed.block_time,
+                    ],
+                )?;
+                let event_id = tx.last_insert_rowid();
+
+                let row = tables::row(event);
+                let names: Vec<String> = row.columns.iter().map(|(name, _)| format!("\"{}\"", name)).collect();
+                let placeholders: Vec<String> = (2..=row.columns.len() + 1).map(|i| format!("?{}", i)).collect();
+                let sql = format!(
+                    "INSERT INTO {} (event_id, {}) VALUES (?1, {})",
+                    row.table,
+                    names.join(", "),
+                    placeholders.join(", ")
+                );
+                let values = std::iter::once(rusqlite::types::Value::Integer(event_id))
+                    .chain(row.columns.into_iter().map(|(_, value)| value));
+                tx.prepare_cached(&sql)?.execute(params_from_iter(values))?;
+            }
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        for (program_id, signature, slot) in cursors {
+            tx.execute(
+                "INSERT INTO cursors (program_id, signature, slot, updated_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(program_id) DO UPDATE SET signature = excluded.signature, slot = excluded.slot, updated_at = excluded.updated_at",
+                params![program_id, signature, *slot as i64, now],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use driftshield_sdk::events::PredictionMarketEvent;
+    use driftshield_sdk::prediction_market::BetPlaced;
+    use driftshield_sdk::Pubkey;
+
+    fn bet(amount: u64) -> ProgramEvent {
+        ProgramEvent::PredictionMarket(PredictionMarketEvent::BetPlaced(BetPlaced {
+            market_key: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            outcome: true,
+            amount,
+            shares: amount,
+            fee: 0,
+            fee_bps: 100,
+            referrer: None,
+            yes_price: 5_000,
+            no_price: 5_000,
+            timestamp: 1_700_000_000,
+        }))
+    }
+
+    fn transaction(signature: &str, slot: u64, finalized: bool, events: Vec<ProgramEvent>) -> IndexedTransaction {
+        IndexedTransaction { signature: signature.to_string(), slot, block_time: Some(1_700_000_000), finalized, events }
+    }
+
+    fn count(store: &Store, table: &str) -> i64 {
+        store.conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn provisional_transactions_are_rebuilt_each_poll() {
+        let mut store = Store::open(":memory:").unwrap();
+        store
+            .commit_poll(
+                &[transaction("final", 10, true, vec![bet(1)]), transaction("forked", 11, false, vec![bet(2), bet(3)])],
+                &[],
+            )
+            .unwrap();
+        assert_eq!(count(&store, "events"), 3);
+        assert_eq!(count(&store, "bet_placed"), 3);
+        assert!(store.is_finalized("final").unwrap());
+        assert!(!store.is_finalized("forked").unwrap());
+
+        // The fork is abandoned: the next poll no longer returns it
+        store.commit_poll(&[transaction("next", 12, false, vec![bet(4)])], &[]).unwrap();
+        let signatures: Vec<String> = store
+            .conn
+            .prepare("SELECT signature FROM events ORDER BY slot")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(signatures, ["final", "next"]);
+        assert_eq!(count(&store, "bet_placed"), 2);
+        assert_eq!(count(&store, "transactions"), 2);
+
+        // Re-polling a finalized transaction does not duplicate its events
+        store.commit_poll(&[transaction("final", 10, true, vec![bet(1)])], &[]).unwrap();
+        assert_eq!(count(&store, "events"), 1);
+    }
+
+    #[test]
+    fn stores_amounts_above_i64_as_text() {
+        let mut store = Store::open(":memory:").unwrap();
+        store.commit_poll(&[transaction("big", 1, true, vec![bet(u64::MAX)])], &[]).unwrap();
+        let (amount, larger): (String, bool) = store
+            .conn
+            .query_row(
+                "SELECT amount, CAST(amount AS NUMERIC) > CAST(fee AS NUMERIC) FROM bet_placed",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(amount, u64::MAX.to_string());
+        assert!(larger);
+    }
+
+    #[test]
+    fn cursors_advance_per_program() {
+        let mut store = Store::open(":memory:").unwrap();
+        assert_eq!(store.cursor("pm").unwrap(), None);
+
+        let cursors = [("pm".to_string(), "a".to_string(), 10), ("registry".to_string(), "b".to_string(), 11)];
+        store.commit_poll(&[], &cursors).unwrap();
+        assert_eq!(store.cursor("pm").unwrap().as_deref(), Some("a"));
+        assert_eq!(store.cursor("registry").unwrap().as_deref(), Some("b"));
+
+        store.commit_poll(&[], &[("pm".to_string(), "c".to_string(), 20)]).unwrap();
+        assert_eq!(store.cursor("pm").unwrap().as_deref(), Some("c"));
+        assert_eq!(store.cursor("registry").unwrap().as_deref(), Some("b"));
+        let slot: i64 = store.conn.query_row("SELECT slot FROM cursors WHERE program_id = 'pm'", [], |row| row.get(0)).unwrap();
+        assert_eq!(slot, 20);
+    }
+}
@@ -0,0 +1,193 @@
+//! `driftshield-indexer` - follow DriftShield program transactions and store
+//! every decoded event in SQLite.
+//!
+//! Each poll walks `getSignaturesForAddress` back to the last finalized
+//! cursor of each program, fetches the transactions and writes their events
+//! in one SQLite transaction. Query `transactions.finalized = 1` for rows
+//! that can no longer be rolled back.
+
+mod db;
+mod tables;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::Parser;
+use db::{IndexedTransaction, Store};
+use driftshield_sdk::client::resolve_url;
+use driftshield_sdk::events::parse_logs;
+use driftshield_sdk::{AGENT_REGISTRY_ID, INSURANCE_ID, MODEL_REGISTRY_ID, PREDICTION_MARKET_ID};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::{TransactionConfirmationStatus, UiTransactionEncoding};
+
+#[derive(Parser)]
+#[command(name = "driftshield-indexer", version, about = "Index DriftShield program events into SQLite")]
+struct Args {
+    /// RPC URL or moniker (localnet, devnet, testnet, mainnet-beta)
+    #[arg(short = 'u', long, env = "DRIFTSHIELD_RPC_URL", default_value = "localnet")]
+    url: String,
+
+    /// SQLite database path
+    #[arg(long, default_value = "driftshield-events.db")]
+    db: String,
+
+    /// Programs to follow (defaults to all DriftShield programs)
+    #[arg(long = "program")]
+    programs: Vec<Pubkey>,
+
+    /// Seconds between polls
+    #[arg(long, default_value_t = 5)]
+    poll_interval: u64,
+
+    /// Signatures requested per page
+    #[arg(long, default_value_t = 1000)]
+    page_size: usize,
+
+    /// Run a single poll and exit
+    #[arg(long)]
+    once: bool,
+}
+
+/// A signature seen in this poll, oldest first
+struct SignatureRef {
+    signature: String,
+    slot: u64,
+    block_time: Option<i64>,
+    finalized: bool,
+}
+
+/// Newest finalized (signature, slot) of a program
+type Cursor = (String, u64);
+
+/// Signatures for `program` newer than its finalized cursor (oldest first),
+/// and the newest finalized signature among them
+fn new_signatures(
+    rpc: &RpcClient,
+    store: &Store,
+    program: &Pubkey,
+    page_size: usize,
+) -> Result<(Vec<SignatureRef>, Option<Cursor>)> {
+    let until = store
+        .cursor(&program.to_string())?
+        .map(|signature| Signature::from_str(&signature))
+        .transpose()?;
+
+    let mut signatures = Vec::new();
+    let mut newest_finalized = None;
+    let mut before = None;
+
+    loop {
+        let page = rpc.get_signatures_for_address_with_config(
+            program,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: Some(page_size),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )?;
+
+        for status in &page {
+            let finalized = matches!(status.confirmation_status, Some(TransactionConfirmationStatus::Finalized));
+            if finalized && newest_finalized.is_none() {
+                newest_finalized = Some((status.signature.clone(), status.slot));
+            }
+            // Failed transactions roll back, so their events never happened
+            if status.err.is_none() {
+                signatures.push(SignatureRef {
+                    signature: status.signature.clone(),
+                    slot: status.slot,
+                    block_time: status.block_time,
+                    finalized,
+                });
+            }
+        }
+
+        match page.last() {
+            Some(last) if page.len() >= page_size => before = Some(Signature::from_str(&last.signature)?),
+            _ => break,
+        }
+    }
+
+    signatures.reverse();
+    Ok((signatures, newest_finalized))
+}
+
+fn poll(rpc: &RpcClient, store: &mut Store, programs: &[Pubkey], page_size: usize) -> Result<usize> {
+    let mut seen = HashSet::new();
+    let mut pending = Vec::new();
+    let mut cursors = Vec::new();
+
+    for program in programs {
+        let (signatures, newest_finalized) = new_signatures(rpc, store, program, page_size)?;
+        pending.extend(signatures.into_iter().filter(|s| seen.insert(s.signature.clone())));
+        if let Some((signature, slot)) = newest_finalized {
+            cursors.push((program.to_string(), signature, slot));
+        }
+    }
+    pending.sort_by_key(|s| s.slot);
+
+    let mut transactions = Vec::new();
+    for sig in pending {
+        if store.is_finalized(&sig.signature)? {
+            continue;
+        }
+        let confirmed = rpc.get_transaction_with_config(
+            &Signature::from_str(&sig.signature)?,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+        let logs: Vec<String> = confirmed
+            .transaction
+            .meta
+            .and_then(|meta| meta.log_messages.into())
+            .unwrap_or_default();
+
+        transactions.push(IndexedTransaction {
+            signature: sig.signature,
+            slot: sig.slot,
+            block_time: sig.block_time.or(confirmed.block_time),
+            finalized: sig.finalized,
+            events: parse_logs(&logs)?,
+        });
+    }
+
+    let events = transactions.iter().map(|tx| tx.events.len()).sum();
+    store.commit_poll(&transactions, &cursors)?;
+    Ok(events)
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let programs = if args.programs.is_empty() {
+        vec![MODEL_REGISTRY_ID, INSURANCE_ID, PREDICTION_MARKET_ID, AGENT_REGISTRY_ID]
+    } else {
+        args.programs.clone()
+    };
+
+    let rpc = RpcClient::new_with_commitment(resolve_url(&args.url), CommitmentConfig::confirmed());
+    let mut store = Store::open(&args.db)?;
+
+    loop {
+        match poll(&rpc, &mut store, &programs, args.page_size) {
+            Ok(events) if events > 0 => println!("Indexed {} events", events),
+            Ok(_) => {}
+            Err(err) if !args.once => eprintln!("Poll failed: {:#}", err),
+            Err(err) => return Err(err),
+        }
+        if args.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(args.poll_interval));
+    }
+}
@@ -0,0 +1,192 @@
+//! One table per event type. Each row references `events(id)`, so rows for
+//! a transaction are removed together when it is rolled back.
+
+use driftshield_sdk::events::{
+    AgentRegistryEvent, InsuranceEvent, ModelRegistryEvent, PredictionMarketEvent, ProgramEvent,
+};
+use driftshield_sdk::prediction_market::{Comparator, DriftMetric};
+use driftshield_sdk::Pubkey;
+use rusqlite::types::Value;
+
+// Column kinds: SQL type and conversion from the event field
+
+macro_rules! sql_type {
+    (pubkey) => { "TEXT NOT NULL" };
+    (opt_pubkey) => { "TEXT" };
+    (int) => { "INTEGER NOT NULL" };
+    (uint) => { "TEXT NOT NULL" };
+    (boolean) => { "INTEGER NOT NULL" };
+    (text) => { "TEXT NOT NULL" };
+    (bytes32) => { "TEXT NOT NULL" };
+    (opt_bytes32) => { "TEXT" };
+    (pubkey_list) => { "TEXT NOT NULL" };
+    (drift_metric) => { "TEXT NOT NULL" };
+    (comparator) => { "TEXT NOT NULL" };
+}
+
+fn pubkey(key: &Pubkey) -> Value {
+    Value::Text(key.to_string())
+}
+
+fn opt_pubkey(key: &Option<Pubkey>) -> Value {
+    key.as_ref().map_or(Value::Null, pubkey)
+}
+
+fn int<T: Copy + Into<i64>>(value: &T) -> Value {
+    Value::Integer((*value).into())
+}
+
+/// u64 and u128 amounts are stored as decimal text: SQLite integers are
+/// signed 64-bit, and an INTEGER column would turn larger values into a
+/// lossy REAL. Compare them numerically with `CAST(col AS NUMERIC)`.
+fn uint<T: Copy + Into<u128>>(value: &T) -> Value {
+    Value::Text((*value).into().to_string())
+}
+
+fn boolean(value: &bool) -> Value {
+    Value::Integer(*value as i64)
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+fn bytes32(bytes: &[u8; 32]) -> Value {
+    Value::Text(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn opt_bytes32(bytes: &Option<[u8; 32]>) -> Value {
+    bytes.as_ref().map_or(Value::Null, bytes32)
+}
+
+/// Comma-separated base58 keys
+fn pubkey_list(keys: &[Pubkey]) -> Value {
+    Value::Text(keys.iter().map(|k| k.to_string()).collect::<Vec<_>>().join(","))
+}
+
+fn drift_metric(metric: &DriftMetric) -> Value {
+    Value::Text(
+        match metric {
+            DriftMetric::Accuracy => "Accuracy",
+            DriftMetric::Precision => "Precision",
+            DriftMetric::Recall => "Recall",
+            DriftMetric::F1Score => "F1Score",
+            DriftMetric::DriftScore => "DriftScore",
+        }
+        .to_string(),
+    )
+}
+
+fn comparator(comparator: &Comparator) -> Value {
+    Value::Text(
+        match comparator {
+            Comparator::Below => "Below",
+            Comparator::AtOrBelow => "AtOrBelow",
+            Comparator::Above => "Above",
+            Comparator::AtOrAbove => "AtOrAbove",
+        }
+        .to_string(),
+    )
+}
+
+/// A decoded event flattened into one row of its table
+pub struct EventRow {
+    pub table: &'static str,
+    pub columns: Vec<(&'static str, Value)>,
+}
+
+macro_rules! event_tables {
+    ($($program:ident($enum:ident) {
+        $($event:ident => $table:literal { $($field:ident: $kind:ident),* $(,)? }),* $(,)?
+    }),* $(,)?) => {
+        /// CREATE TABLE statements for every event table
+        pub fn schema() -> Vec<String> {
+            let mut statements = Vec::new();
+            $($(
+                let columns: Vec<String> = vec![$(format!("\"{}\" {}", stringify!($field), sql_type!($kind))),*];
+                statements.push(format!(
+                    "CREATE TABLE IF NOT EXISTS {} (event_id INTEGER PRIMARY KEY REFERENCES events(id) ON DELETE CASCADE, {})",
+                    $table,
+                    columns.join(", ")
+                ));
+            )*)*
+            statements
+        }
+
+        pub fn row(event: &ProgramEvent) -> EventRow {
+            match event {
+                $($(
+                    ProgramEvent::$program($enum::$event(e)) => EventRow {
+                        table: $table,
+                        columns: vec![$((stringify!($field), $kind(&e.$field))),*],
+                    },
+                )*)*
+            }
+        }
+    };
+}
+
+event_tables! {
+    ModelRegistry(ModelRegistryEvent) {
+        AccountMigrated => "model_registry_account_migrated" { account: pubkey, from_version: int, to_version: int, timestamp: int },
+        ModelRegistered => "model_registered" { model_key: pubkey, owner: pubkey, model_id: text, name: text, timestamp: int },
+        ReceiptSubmitted => "receipt_submitted" { model_key: pubkey, receipt_key: pubkey, accuracy: uint, drift_score: uint, timestamp: int },
+        DriftAlert => "drift_alert" { model_key: pubkey, accuracy_drop: uint, current_accuracy: uint, timestamp: int },
+    },
+    Insurance(InsuranceEvent) {
+        AccountMigrated => "insurance_account_migrated" { account: pubkey, from_version: int, to_version: int, timestamp: int },
+        PolicyPurchased => "policy_purchased" { policy_key: pubkey, owner: pubkey, model: pubkey, coverage_amount: uint, premium: uint, timestamp: int },
+        ClaimPaid => "claim_paid" { policy_key: pubkey, owner: pubkey, payout: uint, accuracy_at_claim: uint, timestamp: int },
+        PolicyCancelled => "policy_cancelled" { policy_key: pubkey, refund_amount: uint, timestamp: int },
+        PolicyExpired => "policy_expired" { policy_key: pubkey, cranker: pubkey, tip: uint, timestamp: int },
+    },
+    PredictionMarket(PredictionMarketEvent) {
+        AccountMigrated => "prediction_market_account_migrated" { account: pubkey, from_version: int, to_version: int, timestamp: int },
+        MarketCreated => "market_created" { market_key: pubkey, creator: pubkey, model: pubkey, question: text, initial_liquidity: uint, creation_bond: uint, creation_fee: uint, timestamp: int },
+        DriftMarketCreated => "drift_market_created" { market_key: pubkey, model: pubkey, metric: drift_metric, comparator: comparator, threshold: uint, resolution_time: int, timestamp: int },
+        BetPlaced => "bet_placed" { market_key: pubkey, user: pubkey, outcome: boolean, amount: uint, shares: uint, fee: uint, fee_bps: int, referrer: opt_pubkey, yes_price: uint, no_price: uint, timestamp: int },
+        SharesSold => "shares_sold" { market_key: pubkey, user: pubkey, outcome: boolean, shares: uint, sol_received: uint, fee: uint, fee_bps: int, referrer: opt_pubkey, yes_price: uint, no_price: uint, timestamp: int },
+        MarketResolved => "market_resolved" { market_key: pubkey, winning_outcome: boolean, yes_pool: uint, no_pool: uint, total_yes_shares: uint, total_no_shares: uint, timestamp: int },
+        LosingPositionSettled => "losing_position_settled" { market_key: pubkey, user: pubkey, stake_lost: uint, timestamp: int },
+        BatchClaimed => "batch_claimed" { user: pubkey, claimed_count: int, skipped_count: int, total_payout: uint, timestamp: int },
+        LiquidityWithdrawn => "liquidity_withdrawn" { market_key: pubkey, creator: pubkey, amount: uint, timestamp: int },
+        ConfigUpdated => "config_updated" { admin: pubkey, treasury: pubkey, creation_bond: uint, creation_fee: uint, trading_fee_bps: int, referral_fee_bps: int, timestamp: int },
+        CrankTipUpdated => "crank_tip_updated" { crank_tip: uint, crank_vault: pubkey, timestamp: int },
+        CrankTipPaid => "crank_tip_paid" { cranker: pubkey, target: pubkey, tip: uint, timestamp: int },
+        DriftAttesterUpdated => "drift_attester_updated" { drift_attester: pubkey, timestamp: int },
+        ExposureFloorsUpdated => "exposure_floors_updated" { max_position_stake: uint, max_wallet_oi_bps: int, max_price_impact_bps: int, wallet_oi_floor: uint, timestamp: int },
+        MarketClosed => "market_closed" { market_key: pubkey, yes_price: uint, no_price: uint, timestamp: int },
+        SessionKeyCreated => "session_key_created" { owner: pubkey, session_key: pubkey, vault: pubkey, spend_cap: uint, allowed_markets: pubkey_list, expires_at: int, timestamp: int },
+        SessionKeyRevoked => "session_key_revoked" { owner: pubkey, session_key: pubkey, spent: uint, refund: uint, timestamp: int },
+        RewardPoolCreated => "reward_pool_created" { market_key: pubkey, reward_pool: pubkey, sponsor: pubkey, emission_rate: uint, start_time: int, end_time: int, total_rewards: uint },
+        RewardMarketAdded => "reward_market_added" { reward_pool: pubkey, market_key: pubkey, timestamp: int },
+        RewardsClaimed => "rewards_claimed" { market_key: pubkey, user: pubkey, amount: uint, timestamp: int },
+        UnallocatedRewardsWithdrawn => "unallocated_rewards_withdrawn" { reward_pool: pubkey, sponsor: pubkey, amount: uint, timestamp: int },
+        ReferrerRegistered => "referrer_registered" { referrer: pubkey, referral_key: pubkey, timestamp: int },
+        ReferralRewardAccrued => "referral_reward_accrued" { referrer: pubkey, market_key: pubkey, user: pubkey, volume: uint, reward: uint, timestamp: int },
+        ReferralRewardsClaimed => "referral_rewards_claimed" { referrer: pubkey, amount: uint, total_claimed: uint, timestamp: int },
+        CreatorBondReturned => "creator_bond_returned" { market_key: pubkey, creator: pubkey, amount: uint, timestamp: int },
+        MarketCancelled => "market_cancelled" { market_key: pubkey, bond_slashed: uint, timestamp: int },
+        ParentMarketLinked => "parent_market_linked" { market_key: pubkey, parent_market: pubkey, parent_outcome: boolean, timestamp: int },
+        ConditionalMarketVoided => "conditional_market_voided" { market_key: pubkey, parent_market: pubkey, refund_pool: uint, timestamp: int },
+        AbandonedMarketVoided => "abandoned_market_voided" { market_key: pubkey, bond_slashed: uint, refund_pool: uint, timestamp: int },
+        RefundClaimed => "refund_claimed" { market_key: pubkey, user: pubkey, refund: uint, timestamp: int },
+        AllowlistRootUpdated => "allowlist_root_updated" { market_key: pubkey, previous_root: opt_bytes32, new_root: opt_bytes32, timestamp: int },
+        DynamicFeesUpdated => "dynamic_fees_updated" { market_key: pubkey, fee_ramp_bps: int, fee_ramp_window: int, volatility_fee_multiplier_bps: int, volatility_window: int, max_dynamic_fee_bps: int, timestamp: int },
+        CircuitBreakerUpdated => "circuit_breaker_updated" { market_key: pubkey, move_bps: int, window: int, cooldown: int, timestamp: int },
+        MarketHalted => "market_halted" { market_key: pubkey, reference_price: uint, yes_price: uint, halted_until: int, timestamp: int },
+        MarketResumed => "market_resumed" { market_key: pubkey, authority: opt_pubkey, timestamp: int },
+        ExposureLimitsUpdated => "exposure_limits_updated" { market_key: pubkey, max_position_stake: uint, max_wallet_oi_bps: int, max_price_impact_bps: int, wallet_oi_floor: uint, timestamp: int },
+        PriceHistoryInitialized => "price_history_initialized" { market_key: pubkey, price_history: pubkey, sample_interval: int, timestamp: int },
+        WinningsClaimed => "winnings_claimed" { market_key: pubkey, user: pubkey, shares: uint, payout: uint, timestamp: int },
+    },
+    AgentRegistry(AgentRegistryEvent) {
+        AccountMigrated => "agent_registry_account_migrated" { account: pubkey, from_version: int, to_version: int, timestamp: int },
+        AgentRegistered => "agent_registered" { agent_key: pubkey, owner: pubkey, agent_id: text, display_name: text, strategy_hash: bytes32, trading_wallet: pubkey, timestamp: int },
+        AgentUpdated => "agent_updated" { agent_key: pubkey, display_name: text, strategy_hash: bytes32, trading_wallet: pubkey, verified: boolean, timestamp: int },
+        AgentSuspended => "agent_suspended" { agent_key: pubkey, authority: pubkey, timestamp: int },
+        AgentReinstated => "agent_reinstated" { agent_key: pubkey, authority: pubkey, timestamp: int },
+        AgentVerificationChanged => "agent_verification_changed" { agent_key: pubkey, verified: boolean, timestamp: int },
+        AgentDeregistered => "agent_deregistered" { agent_key: pubkey, owner: pubkey, timestamp: int },
+    },
+}
@@ -0,0 +1,235 @@
+//! `driftshield-keeper` - crank time-based DriftShield state transitions.
+//!
+//! Every tick scans program accounts and submits, one transaction each:
+//! - `close_market` for open markets past `resolution_time`
+//! - `finalize_drift_market` for drift markets nobody settled YES within
+//!   `DRIFT_SETTLEMENT_GRACE` of `resolution_time`
+//! - `void_abandoned_market` for other markets their creator left unresolved
+//!   `ABANDONED_MARKET_GRACE` after `resolution_time`
+//! - `expire_policy` for active policies past `expiry_time`
+//!
+//! Tips are paid to the keeper's associated token accounts when they exist.
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token;
+use anyhow::{Context, Result};
+use clap::Parser;
+use driftshield_sdk::accounts::{InsurancePolicy, Market, ProtocolConfig};
+use driftshield_sdk::client::{load_keypair, parse_commitment, Client};
+use driftshield_sdk::instructions;
+use driftshield_sdk::insurance::PolicyStatus;
+use driftshield_sdk::pda;
+use driftshield_sdk::prediction_market::{MarketStatus, ABANDONED_MARKET_GRACE, DRIFT_SETTLEMENT_GRACE};
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Parser)]
+#[command(name = "driftshield-keeper", version, about = "Crank time-based DriftShield state transitions")]
+struct Args {
+    /// RPC URL or moniker (localnet, devnet, testnet, mainnet-beta)
+    #[arg(short = 'u', long, env = "DRIFTSHIELD_RPC_URL", default_value = "localnet")]
+    url: String,
+
+    /// Keypair file that signs, pays fees and receives tips
+    #[arg(short = 'k', long, env = "DRIFTSHIELD_KEYPAIR")]
+    keypair: Option<String>,
+
+    /// Commitment level (processed, confirmed, finalized)
+    #[arg(long, default_value = "confirmed")]
+    commitment: String,
+
+    /// Seconds between scans
+    #[arg(long, default_value_t = 30)]
+    interval: u64,
+
+    /// Run a single scan and exit
+    #[arg(long)]
+    once: bool,
+
+    /// Simulate cranks instead of sending them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Skip prediction market cranks
+    #[arg(long)]
+    no_markets: bool,
+
+    /// Skip insurance policy cranks
+    #[arg(long)]
+    no_policies: bool,
+}
+
+/// A crank to submit
+struct Crank {
+    name: &'static str,
+    target: Pubkey,
+    instruction: Instruction,
+}
+
+/// Caches the keeper's associated token account per mint, if it exists
+struct TipAccounts<'a> {
+    client: &'a Client,
+    by_mint: HashMap<Pubkey, Option<Pubkey>>,
+}
+
+impl<'a> TipAccounts<'a> {
+    fn new(client: &'a Client) -> Self {
+        Self { client, by_mint: HashMap::new() }
+    }
+
+    fn for_mint(&mut self, mint: Pubkey) -> Result<Option<Pubkey>> {
+        if let Some(account) = self.by_mint.get(&mint) {
+            return Ok(*account);
+        }
+        let address = get_associated_token_address(&self.client.payer(), &mint);
+        let account = self.client.exists(&address)?.then_some(address);
+        if account.is_none() {
+            eprintln!("No token account {} for mint {}; cranking without tips", address, mint);
+        }
+        self.by_mint.insert(mint, account);
+        Ok(account)
+    }
+}
+
+/// Whether `finalize_drift_market` would pass its parent check
+fn parent_allows_resolution(market: &Market, markets: &HashMap<Pubkey, Market>) -> bool {
+    match market.parent_market {
+        None => true,
+        Some(parent) => markets.get(&parent).is_some_and(|parent| {
+            parent.status == MarketStatus::Resolved && parent.winning_outcome == Some(market.parent_outcome)
+        }),
+    }
+}
+
+fn market_cranks(client: &Client, tips: &mut TipAccounts, now: i64) -> Result<Vec<Crank>> {
+    let config_key = pda::config().0;
+    let config: ProtocolConfig = client
+        .fetch(&config_key)
+        .with_context(|| format!("failed to load protocol config {}", config_key))?;
+    let tip_account = if config.crank_tip > 0 {
+        tips.for_mint(client.token_mint(&config.crank_vault)?)?
+    } else {
+        None
+    };
+
+    let markets: HashMap<Pubkey, Market> = client.program_accounts::<Market>(vec![])?.into_iter().collect();
+    let mut cranks = Vec::new();
+    for (key, market) in &markets {
+        let finalizable = market.drift_condition.is_some()
+            && market.status.is_unresolved()
+            && now >= market.resolution_time.saturating_add(DRIFT_SETTLEMENT_GRACE)
+            && parent_allows_resolution(market, &markets);
+        let abandoned = market.drift_condition.is_none()
+            && market.status.is_unresolved()
+            && now >= market.resolution_time.saturating_add(ABANDONED_MARKET_GRACE);
+
+        if finalizable {
+            cranks.push(Crank {
+                name: "finalize_drift_market",
+                target: *key,
+                instruction: instructions::finalize_drift_market(&config, client.payer(), tip_account, *key, market),
+            });
+        } else if abandoned {
+            cranks.push(Crank {
+                name: "void_abandoned_market",
+                target: *key,
+                instruction: instructions::void_abandoned_market(&config, client.payer(), tip_account, *key, market),
+            });
+        } else if market.status == MarketStatus::Open && now >= market.resolution_time {
+            cranks.push(Crank {
+                name: "close_market",
+                target: *key,
+                instruction: instructions::close_market(&config, client.payer(), tip_account, *key),
+            });
+        }
+    }
+    Ok(cranks)
+}
+
+/// The policy's vault and the keeper's token account for its mint
+fn policy_tip(client: &Client, tips: &mut TipAccounts, policy: &Pubkey) -> Result<Option<(Pubkey, Pubkey)>> {
+    let vaults = client
+        .rpc
+        .get_token_accounts_by_owner(policy, TokenAccountsFilter::ProgramId(token::ID))?;
+    let vault = match vaults.first() {
+        Some(keyed) => keyed.pubkey.parse::<Pubkey>()?,
+        None => return Ok(None),
+    };
+    let mint = client.token_mint(&vault)?;
+    Ok(tips.for_mint(mint)?.map(|account| (vault, account)))
+}
+
+fn policy_cranks(client: &Client, tips: &mut TipAccounts, now: i64) -> Result<Vec<Crank>> {
+    let mut cranks = Vec::new();
+    for (key, policy) in client.program_accounts::<InsurancePolicy>(vec![])? {
+        if policy.status != PolicyStatus::Active || now <= policy.expiry_time {
+            continue;
+        }
+        let tip = policy_tip(client, tips, &key)?;
+        cranks.push(Crank {
+            name: "expire_policy",
+            target: key,
+            instruction: instructions::expire_policy(client.payer(), key, tip),
+        });
+    }
+    Ok(cranks)
+}
+
+/// Scan and submit every due crank. Returns (submitted, failed).
+fn tick(client: &Client, args: &Args) -> Result<(usize, usize)> {
+    let now = client.now()?;
+    let mut tips = TipAccounts::new(client);
+
+    let mut cranks = Vec::new();
+    if !args.no_markets {
+        cranks.extend(market_cranks(client, &mut tips, now)?);
+    }
+    if !args.no_policies {
+        cranks.extend(policy_cranks(client, &mut tips, now)?);
+    }
+
+    let mut failed = 0;
+    for crank in &cranks {
+        match client.send(std::slice::from_ref(&crank.instruction), &[]) {
+            Ok(outcome) if outcome.err.is_none() => {
+                let signature = outcome.signature.map_or("simulated".to_string(), |s| s.to_string());
+                println!("{} {}: {}", crank.name, crank.target, signature);
+            }
+            Ok(outcome) => {
+                failed += 1;
+                eprintln!("{} {} failed: {}", crank.name, crank.target, outcome.err.unwrap());
+            }
+            Err(err) => {
+                failed += 1;
+                eprintln!("{} {} failed: {:#}", crank.name, crank.target, err);
+            }
+        }
+    }
+    Ok((cranks.len(), failed))
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let payer = load_keypair(args.keypair.as_deref())?;
+    let commitment = parse_commitment(&args.commitment)?;
+    let client = Client::new(&args.url, payer, commitment, args.dry_run);
+
+    loop {
+        match tick(&client, &args) {
+            Ok((0, _)) => {}
+            Ok((submitted, failed)) => println!("Submitted {} cranks, {} failed", submitted, failed),
+            Err(err) if !args.once => eprintln!("Scan failed: {:#}", err),
+            Err(err) => return Err(err),
+        }
+        if args.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
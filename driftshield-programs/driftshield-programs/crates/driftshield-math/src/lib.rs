@@ -0,0 +1,123 @@
+#![no_std]
+
+//! Checked integer math shared by the DriftShield programs.
+//!
+//! Every division takes a [`Rounding`] so call sites state which side keeps
+//! the remainder. The convention is that rounding favors the vault: amounts
+//! paid out round down, amounts owed to the protocol round up.
+//!
+//! Functions return `None` on overflow, on a zero divisor, or when a result
+//! does not fit the return type, like the standard `checked_*` methods, so
+//! programs map them with `.ok_or(ErrorCode::MathOverflow)?`.
+//!
+//! ```
+//! use driftshield_math::{bps, Rounding};
+//!
+//! // A 1.5% fee on 999 units is 14.985
+//! assert_eq!(bps::apply(999, 150, Rounding::Up), Some(15));
+//! assert_eq!(bps::apply(999, 150, Rounding::Down), Some(14));
+//! ```
+
+/// Which way to round an inexact quotient
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// Toward zero: use for amounts leaving the vault
+    Down,
+    /// Away from zero: use for amounts owed to the vault or protocol
+    Up,
+}
+
+/// `a / b` rounded as chosen
+pub fn div(a: u128, b: u128, rounding: Rounding) -> Option<u128> {
+    let q = a.checked_div(b)?;
+    Some(match rounding {
+        Rounding::Up if q * b < a => q + 1,
+        _ => q,
+    })
+}
+
+/// `a * b / c` with a 128-bit intermediate, rounded as chosen
+pub fn mul_div(a: u64, b: u64, c: u64, rounding: Rounding) -> Option<u64> {
+    let q = div(a as u128 * b as u128, c as u128, rounding)?;
+    u64::try_from(q).ok()
+}
+
+/// `a * b / c` in 128 bits, rounded as chosen
+pub fn mul_div_u128(a: u128, b: u128, c: u128, rounding: Rounding) -> Option<u128> {
+    div(a.checked_mul(b)?, c, rounding)
+}
+
+/// Integer square root rounded as chosen
+pub fn sqrt(n: u128, rounding: Rounding) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's method for floor(sqrt(n)), from n / 2 + 1 >= sqrt(n) so the
+    // first step cannot overflow at u128::MAX
+    let mut x = n / 2 + 1;
+    let mut y = (x + n / x) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    match rounding {
+        Rounding::Up if x * x != n => x + 1,
+        _ => x,
+    }
+}
+
+/// Basis-point helpers (1 bps = 1 / 10,000)
+pub mod bps {
+    use super::{mul_div, Rounding};
+
+    /// 100% in basis points
+    pub const DENOMINATOR: u64 = 10_000;
+
+    /// `amount * bps / 10,000`
+    pub fn apply(amount: u64, bps: u64, rounding: Rounding) -> Option<u64> {
+        mul_div(amount, bps, DENOMINATOR, rounding)
+    }
+
+    /// `part / whole` in basis points
+    pub fn ratio(part: u64, whole: u64, rounding: Rounding) -> Option<u64> {
+        mul_div(part, DENOMINATOR, whole, rounding)
+    }
+}
+
+/// Unsigned fixed-point number with 12 decimal places, stored as a raw `u128`
+/// so it can live in account fields unchanged
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed(u128);
+
+impl Fixed {
+    /// Raw value of 1.0
+    pub const SCALE: u128 = 1_000_000_000_000;
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(Self::SCALE);
+
+    pub const fn from_raw(raw: u128) -> Self {
+        Fixed(raw)
+    }
+
+    pub const fn raw(self) -> u128 {
+        self.0
+    }
+
+    /// `numerator / denominator`
+    pub fn ratio(numerator: u128, denominator: u128, rounding: Rounding) -> Option<Self> {
+        mul_div_u128(numerator, Self::SCALE, denominator, rounding).map(Fixed)
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Fixed)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Fixed)
+    }
+
+    /// `self * value`, as an integer
+    pub fn mul_int(self, value: u128, rounding: Rounding) -> Option<u128> {
+        mul_div_u128(self.0, value, Self::SCALE, rounding)
+    }
+}
@@ -0,0 +1,88 @@
+use driftshield_math::{bps, div, mul_div, mul_div_u128, sqrt, Fixed, Rounding};
+
+#[test]
+fn div_rounds_only_inexact_quotients() {
+    for rounding in [Rounding::Down, Rounding::Up] {
+        assert_eq!(div(12, 4, rounding), Some(3));
+        assert_eq!(div(0, 7, rounding), Some(0));
+        assert_eq!(div(u128::MAX, 1, rounding), Some(u128::MAX));
+        assert_eq!(div(7, 0, rounding), None);
+    }
+    assert_eq!(div(13, 4, Rounding::Down), Some(3));
+    assert_eq!(div(13, 4, Rounding::Up), Some(4));
+    assert_eq!(div(1, 3, Rounding::Down), Some(0));
+    assert_eq!(div(1, 3, Rounding::Up), Some(1));
+    assert_eq!(div(u128::MAX, 2, Rounding::Down), Some(u128::MAX / 2));
+    assert_eq!(div(u128::MAX, 2, Rounding::Up), Some(u128::MAX / 2 + 1));
+}
+
+#[test]
+fn mul_div_uses_a_wide_intermediate() {
+    assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX, Rounding::Down), Some(u64::MAX));
+    assert_eq!(mul_div(1_000, 3, 4, Rounding::Down), Some(750));
+    assert_eq!(mul_div(1_000, 3, 4, Rounding::Up), Some(750));
+    assert_eq!(mul_div(1_001, 3, 4, Rounding::Down), Some(750));
+    assert_eq!(mul_div(1_001, 3, 4, Rounding::Up), Some(751));
+    assert_eq!(mul_div(u64::MAX, 2, 3, Rounding::Down), Some(12_297_829_382_473_034_410));
+    assert_eq!(mul_div(u64::MAX, 2, 3, Rounding::Up), Some(12_297_829_382_473_034_410));
+    assert_eq!(mul_div(u64::MAX, 2, 4, Rounding::Up), Some(u64::MAX / 2 + 1));
+}
+
+#[test]
+fn mul_div_rejects_zero_divisors_and_overflow() {
+    for rounding in [Rounding::Down, Rounding::Up] {
+        assert_eq!(mul_div(5, 5, 0, rounding), None);
+        assert_eq!(mul_div(u64::MAX, 2, 1, rounding), None);
+        assert_eq!(mul_div_u128(u128::MAX, 2, 2, rounding), None);
+        assert_eq!(mul_div_u128(5, 5, 0, rounding), None);
+    }
+    // Rounding up the largest representable quotient still fits
+    assert_eq!(mul_div(u64::MAX, u64::MAX - 1, u64::MAX, Rounding::Up), Some(u64::MAX - 1));
+    // 31 * 1190112520884487201 = 2^65 - 1, so the quotient only overflows once rounded up
+    assert_eq!(mul_div(31, 1_190_112_520_884_487_201, 2, Rounding::Down), Some(u64::MAX));
+    assert_eq!(mul_div(31, 1_190_112_520_884_487_201, 2, Rounding::Up), None);
+    assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX - 1, Rounding::Down), None);
+}
+
+#[test]
+fn sqrt_of_perfect_squares_and_neighbours() {
+    for root in [0u128, 1, 2, 3, 10, 1_000, 65_535, 1 << 40, u64::MAX as u128] {
+        let n = root * root;
+        assert_eq!(sqrt(n, Rounding::Down), root);
+        assert_eq!(sqrt(n, Rounding::Up), root);
+        if root > 1 {
+            assert_eq!(sqrt(n - 1, Rounding::Down), root - 1);
+            assert_eq!(sqrt(n - 1, Rounding::Up), root);
+        }
+        assert_eq!(sqrt(n + 1, Rounding::Down), root.max(1));
+        assert_eq!(sqrt(n + 1, Rounding::Up), root + 1);
+    }
+    assert_eq!(sqrt(2, Rounding::Down), 1);
+    assert_eq!(sqrt(2, Rounding::Up), 2);
+}
+
+#[test]
+fn sqrt_of_large_n() {
+    let max_root = u64::MAX as u128;
+    assert_eq!(sqrt(u128::MAX, Rounding::Down), max_root);
+    assert_eq!(sqrt(u128::MAX, Rounding::Up), max_root + 1);
+    assert_eq!(sqrt(u128::MAX - 1, Rounding::Down), max_root);
+    assert_eq!(sqrt(1 << 127, Rounding::Down), 13_043_817_825_332_782_212);
+    assert_eq!(sqrt(1 << 126, Rounding::Down), 1 << 63);
+}
+
+#[test]
+fn bps_and_fixed_round_as_chosen() {
+    assert_eq!(bps::apply(999, 150, Rounding::Up), Some(15));
+    assert_eq!(bps::apply(999, 150, Rounding::Down), Some(14));
+    assert_eq!(bps::ratio(1, 3, Rounding::Down), Some(3_333));
+    assert_eq!(bps::ratio(1, 3, Rounding::Up), Some(3_334));
+    assert_eq!(bps::ratio(1, 0, Rounding::Down), None);
+
+    let third = Fixed::ratio(1, 3, Rounding::Down).unwrap();
+    assert_eq!(third.raw(), 333_333_333_333);
+    assert_eq!(third.mul_int(3, Rounding::Down), Some(0));
+    assert_eq!(third.mul_int(3, Rounding::Up), Some(1));
+    assert_eq!(Fixed::ONE.checked_sub(third.checked_add(third).unwrap()).unwrap().raw(), 333_333_333_334);
+    assert_eq!(Fixed::ZERO.checked_sub(Fixed::ONE), None);
+}
@@ -0,0 +1,62 @@
+//! Append-only decision log, one JSON object per line.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::consensus::{Decision, Vote};
+use crate::provider::Query;
+
+/// What was done with a decision
+pub enum Submission {
+    /// No outcome yet
+    None,
+    /// `resolve_market` was simulated (`--dry-run`)
+    Simulated,
+    Sent { signature: String },
+    Failed { error: String },
+}
+
+pub struct AuditLog {
+    file: File,
+}
+
+impl AuditLog {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open audit log {}", path.display()))?;
+        Ok(Self { file })
+    }
+
+    /// Append one record and flush it
+    pub fn record(&mut self, query: &Query, decision: &Decision, submission: &Submission) -> Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let (status, detail) = match submission {
+            Submission::None => ("pending", Value::Null),
+            Submission::Simulated => ("simulated", Value::Null),
+            Submission::Sent { signature } => ("sent", json!(signature)),
+            Submission::Failed { error } => ("failed", json!(error)),
+        };
+        let record = json!({
+            "timestamp": timestamp,
+            "market": query.market.to_string(),
+            "question": query.question,
+            "resolution_time": query.resolution_time,
+            "votes": decision.votes.iter().map(Vote::to_json).collect::<Vec<_>>(),
+            "outcome": decision.outcome.map(|o| if o { "YES" } else { "NO" }),
+            "reason": decision.reason,
+            "submission": status,
+            "detail": detail,
+        });
+        writeln!(self.file, "{}", record)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! Combine several providers into one decision.
+
+use serde_json::{json, Value};
+
+use crate::provider::{Provider, Query, Resolution};
+
+/// One provider's answer. Errors are kept as text for the audit log.
+pub struct Vote {
+    pub provider: String,
+    pub answer: Result<Option<Resolution>, String>,
+}
+
+impl Vote {
+    pub fn to_json(&self) -> Value {
+        match &self.answer {
+            Ok(Some(r)) => json!({
+                "provider": self.provider,
+                "outcome": if r.outcome { "YES" } else { "NO" },
+                "confidence": r.confidence,
+                "proof": r.proof,
+            }),
+            Ok(None) => json!({ "provider": self.provider, "outcome": null }),
+            Err(err) => json!({ "provider": self.provider, "error": err }),
+        }
+    }
+
+    /// The answer, if it counts towards a decision at `min_confidence`
+    fn counted(&self, min_confidence: u8) -> Option<&Resolution> {
+        self.answer.as_ref().ok().and_then(Option::as_ref).filter(|r| r.confidence >= min_confidence)
+    }
+}
+
+/// Every vote plus the combined outcome, if any
+pub struct Decision {
+    pub votes: Vec<Vote>,
+    /// `Some` once the outcome may be submitted
+    pub outcome: Option<bool>,
+    /// Why, in one line
+    pub reason: String,
+}
+
+/// Decides an outcome once at least `min_agreement` providers answer it with
+/// at least `min_confidence` and no such answer dissents. Any counted
+/// disagreement blocks resolution until the sources converge, since a
+/// resolved market cannot be corrected.
+pub struct Consensus {
+    providers: Vec<Box<dyn Provider>>,
+    min_agreement: usize,
+    min_confidence: u8,
+}
+
+impl Consensus {
+    pub fn new(providers: Vec<Box<dyn Provider>>, min_agreement: usize) -> Self {
+        Self { providers, min_agreement: min_agreement.max(1), min_confidence: 0 }
+    }
+
+    pub fn with_min_confidence(mut self, min_confidence: u8) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    pub fn decide(&self, query: &Query) -> Decision {
+        let votes: Vec<Vote> = self
+            .providers
+            .iter()
+            .map(|provider| Vote {
+                provider: provider.name().to_string(),
+                answer: provider.resolve(query).map_err(|err| format!("{:#}", err)),
+            })
+            .collect();
+
+        let counted = votes.iter().filter_map(|vote| vote.counted(self.min_confidence));
+        let (yes, no) = counted.fold((0, 0), |(yes, no), r| if r.outcome { (yes + 1, no) } else { (yes, no + 1) });
+
+        let (outcome, reason) = if yes > 0 && no > 0 {
+            (None, format!("providers disagree: {} YES, {} NO", yes, no))
+        } else if yes.max(no) >= self.min_agreement {
+            let outcome = yes > 0;
+            let label = if outcome { "YES" } else { "NO" };
+            (Some(outcome), format!("{} with {}/{} providers", label, yes.max(no), votes.len()))
+        } else {
+            (None, format!("waiting: {}/{} agreeing answers", yes.max(no), self.min_agreement))
+        };
+
+        Decision { votes, outcome, reason }
+    }
+}
+
+/// A consensus can itself be one provider of a larger consensus
+impl Provider for Consensus {
+    fn name(&self) -> &str {
+        "consensus"
+    }
+
+    fn resolve(&self, query: &Query) -> anyhow::Result<Option<Resolution>> {
+        let decision = self.decide(query);
+        Ok(decision.outcome.map(|outcome| {
+            // Only answers that counted towards the decision
+            let counted: Vec<&Resolution> =
+                decision.votes.iter().filter_map(|vote| vote.counted(self.min_confidence)).collect();
+            let answered = counted.len();
+            let agreeing = counted.iter().filter(|r| r.outcome == outcome).count();
+            let votes: Vec<Value> = decision.votes.iter().map(Vote::to_json).collect();
+            Resolution {
+                outcome,
+                confidence: (agreeing * 100 / answered.max(1)) as u8,
+                source: format!("consensus ({}/{})", agreeing, decision.votes.len()),
+                proof: Some(Value::Array(votes).to_string()),
+            }
+        }))
+    }
+}
@@ -0,0 +1,52 @@
+//! Answers read from a JSON file.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::provider::{parse_answer, Provider, Query, Resolution};
+
+/// Answers keyed by market address or by exact question text:
+///
+/// ```json
+/// {
+///   "7xKX...": { "outcome": "no", "proof": "eval run 42" },
+///   "Will model X drop below 90% accuracy?": { "outcome": true, "confidence": 90 }
+/// }
+/// ```
+///
+/// The file is re-read on every query, so answers can be added while the
+/// relayer runs. Markets with no entry are unsettled.
+pub struct FileProvider {
+    name: String,
+    path: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self { name: name.into(), path: path.into() }
+    }
+}
+
+impl Provider for FileProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn resolve(&self, query: &Query) -> Result<Option<Resolution>> {
+        let text = fs::read_to_string(&self.path)
+            .with_context(|| format!("failed to read {}", self.path.display()))?;
+        let answers: Value = serde_json::from_str(&text)
+            .with_context(|| format!("failed to parse {}", self.path.display()))?;
+
+        match answers
+            .get(query.market.to_string())
+            .or_else(|| answers.get(&query.question))
+        {
+            Some(answer) => parse_answer(answer, &self.name),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Answers from an HTTP endpoint.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::provider::{parse_answer, Provider, Query, Resolution};
+
+/// POSTs `{ "marketId", "question", "outcomes": ["Yes", "No"], "resolutionTime" }`
+/// to `endpoint`, the same request the web app's `APIOracle` sends, and
+/// parses the response body with [`parse_answer`]. A 404 means unsettled.
+pub struct HttpProvider {
+    name: String,
+    endpoint: String,
+    client: Client,
+}
+
+impl HttpProvider {
+    pub fn new(name: impl Into<String>, endpoint: impl Into<String>, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            endpoint: endpoint.into(),
+            client: Client::builder().timeout(timeout).build()?,
+        })
+    }
+}
+
+impl Provider for HttpProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn resolve(&self, query: &Query) -> Result<Option<Resolution>> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .json(&json!({
+                "marketId": query.market.to_string(),
+                "question": query.question,
+                "outcomes": ["Yes", "No"],
+                "resolutionTime": query.resolution_time,
+            }))
+            .send()
+            .with_context(|| format!("{}: request to {} failed", self.name, self.endpoint))?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => return Ok(None),
+            status if !status.is_success() => bail!("{}: {} returned {}", self.name, self.endpoint, status),
+            _ => {}
+        }
+
+        let body: Value = response
+            .json()
+            .with_context(|| format!("{}: invalid JSON from {}", self.name, self.endpoint))?;
+        parse_answer(&body, &self.name)
+    }
+}
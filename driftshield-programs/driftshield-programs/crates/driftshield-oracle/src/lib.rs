@@ -0,0 +1,32 @@
+//! Oracle relayer for DriftShield prediction markets.
+//!
+//! A [`Provider`] answers whether a market's question has settled, and how.
+//! [`Consensus`] combines several providers and only decides an outcome once
+//! enough of them agree and none dissent. Every decision is appended to an
+//! [`AuditLog`], one JSON object per line.
+//!
+//! - `provider`: the trait, plus the answer format shared by the built-ins
+//! - `file`: answers read from a JSON file, for fixtures and manual overrides
+//! - `http`: answers from an HTTP endpoint, compatible with the web app's `APIOracle`
+//! - `consensus`: the combinator
+//! - `audit`: the decision log
+//!
+//! The `driftshield-oracle` binary scans open markets created by its keypair
+//! and submits `resolve_market` once consensus is reached. Against a local
+//! validator, point it at a fixture instead of live endpoints:
+//!
+//! ```text
+//! driftshield-oracle --file crates/driftshield-oracle/fixtures/resolutions.json --once
+//! ```
+
+pub mod audit;
+pub mod consensus;
+pub mod file;
+pub mod http;
+pub mod provider;
+
+pub use audit::{AuditLog, Submission};
+pub use consensus::{Consensus, Decision, Vote};
+pub use file::FileProvider;
+pub use http::HttpProvider;
+pub use provider::{parse_answer, Provider, Query, Resolution};
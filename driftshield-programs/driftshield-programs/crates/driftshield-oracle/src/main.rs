@@ -0,0 +1,211 @@
+//! `driftshield-oracle` - resolve DriftShield markets from oracle providers.
+//!
+//! Every tick scans unresolved markets created by the relayer's keypair
+//! (`resolve_market` only accepts the creator) that are past
+//! `resolution_time`, asks the providers, and submits `resolve_market` once
+//! they reach consensus. Drift markets are skipped: they settle from the
+//! model registry through `settle_drift_market`, and `resolve_market`
+//! rejects them. Decisions go to the audit log; pending ones only
+//! when their reason changes, so a waiting market does not flood it.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use driftshield_oracle::{AuditLog, Consensus, FileProvider, HttpProvider, Provider, Query, Submission};
+use driftshield_sdk::accounts::Market;
+use driftshield_sdk::client::{load_keypair, parse_commitment, Client};
+use driftshield_sdk::instructions;
+use driftshield_sdk::prediction_market::MarketStatus;
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Parser)]
+#[command(name = "driftshield-oracle", version, about = "Resolve DriftShield markets from oracle providers")]
+struct Args {
+    /// RPC URL or moniker (localnet, devnet, testnet, mainnet-beta)
+    #[arg(short = 'u', long, env = "DRIFTSHIELD_RPC_URL", default_value = "localnet")]
+    url: String,
+
+    /// Keypair of the market creator that signs resolutions
+    #[arg(short = 'k', long, env = "DRIFTSHIELD_KEYPAIR")]
+    keypair: Option<String>,
+
+    /// Commitment level (processed, confirmed, finalized)
+    #[arg(long, default_value = "confirmed")]
+    commitment: String,
+
+    /// JSON answer file provider (repeatable)
+    #[arg(long = "file", value_name = "PATH")]
+    files: Vec<PathBuf>,
+
+    /// HTTP endpoint provider (repeatable)
+    #[arg(long = "http", value_name = "URL")]
+    endpoints: Vec<String>,
+
+    /// HTTP provider timeout in seconds
+    #[arg(long, default_value_t = 10)]
+    http_timeout: u64,
+
+    /// Agreeing answers required to resolve [default: every provider]
+    #[arg(long)]
+    min_agreement: Option<usize>,
+
+    /// Answers below this confidence (0-100) are ignored
+    #[arg(long, default_value_t = 0)]
+    min_confidence: u8,
+
+    /// Only consider these markets (repeatable)
+    #[arg(long = "market")]
+    markets: Vec<Pubkey>,
+
+    /// Decision log, appended as JSON lines
+    #[arg(long, default_value = "oracle-audit.jsonl")]
+    audit_log: PathBuf,
+
+    /// Seconds between scans
+    #[arg(long, default_value_t = 60)]
+    interval: u64,
+
+    /// Run a single scan and exit
+    #[arg(long)]
+    once: bool,
+
+    /// Simulate resolutions instead of sending them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Name a provider after its source, e.g. `file:fixtures.json`
+fn file_name(path: &Path) -> String {
+    format!("file:{}", path.file_name().map_or(path.display().to_string(), |n| n.to_string_lossy().into_owned()))
+}
+
+fn build_consensus(args: &Args) -> Result<Consensus> {
+    let mut providers: Vec<Box<dyn Provider>> = Vec::new();
+    for path in &args.files {
+        providers.push(Box::new(FileProvider::new(file_name(path), path)));
+    }
+    for endpoint in &args.endpoints {
+        providers.push(Box::new(HttpProvider::new(
+            format!("http:{}", endpoint),
+            endpoint,
+            Duration::from_secs(args.http_timeout),
+        )?));
+    }
+    if providers.is_empty() {
+        bail!("no providers; pass --file or --http");
+    }
+
+    let min_agreement = args.min_agreement.unwrap_or(providers.len());
+    if min_agreement > providers.len() {
+        bail!("--min-agreement {} exceeds the {} providers", min_agreement, providers.len());
+    }
+    Ok(Consensus::new(providers, min_agreement).with_min_confidence(args.min_confidence))
+}
+
+/// Whether `resolve_market` would pass its parent check
+fn parent_allows_resolution(client: &Client, market: &Market) -> Result<bool> {
+    let Some(parent_key) = market.parent_market else {
+        return Ok(true);
+    };
+    let parent: Market = client.fetch(&parent_key)?;
+    Ok(parent.status == MarketStatus::Resolved && parent.winning_outcome == Some(market.parent_outcome))
+}
+
+struct Relayer {
+    client: Client,
+    consensus: Consensus,
+    audit: AuditLog,
+    /// Last audited reason per market
+    last_reason: HashMap<Pubkey, String>,
+}
+
+impl Relayer {
+    fn tick(&mut self, args: &Args) -> Result<()> {
+        let now = self.client.now()?;
+        // Market.creator follows the 8-byte discriminator
+        let creator = RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8, self.client.payer().to_bytes().to_vec()));
+
+        for (key, market) in self.client.program_accounts::<Market>(vec![creator])? {
+            if !market.status.is_unresolved() || market.drift_condition.is_some() || now < market.resolution_time {
+                continue;
+            }
+            if !args.markets.is_empty() && !args.markets.contains(&key) {
+                continue;
+            }
+            match parent_allows_resolution(&self.client, &market) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    eprintln!("{} skipped: parent market unavailable: {:#}", key, err);
+                    continue;
+                }
+            }
+
+            let query = Query { market: key, question: market.question.clone(), resolution_time: market.resolution_time };
+            let decision = self.consensus.decide(&query);
+
+            let submission = match decision.outcome {
+                None => {
+                    if self.last_reason.get(&key) == Some(&decision.reason) {
+                        continue;
+                    }
+                    println!("{} pending: {}", key, decision.reason);
+                    Submission::None
+                }
+                Some(outcome) => {
+                    let ix = instructions::resolve_market(self.client.payer(), key, &market, outcome);
+                    match self.client.send(&[ix], &[]) {
+                        Ok(tx) => match (tx.err, tx.signature) {
+                            (Some(err), _) => Submission::Failed { error: err.to_string() },
+                            (None, Some(signature)) => Submission::Sent { signature: signature.to_string() },
+                            (None, None) => Submission::Simulated,
+                        },
+                        Err(err) => Submission::Failed { error: format!("{:#}", err) },
+                    }
+                }
+            };
+
+            match &submission {
+                Submission::Sent { signature } => println!("{} resolved {}: {}", key, decision.reason, signature),
+                Submission::Simulated => println!("{} would resolve {}", key, decision.reason),
+                Submission::Failed { error } => eprintln!("{} failed to resolve {}: {}", key, decision.reason, error),
+                Submission::None => {}
+            }
+
+            self.audit.record(&query, &decision, &submission)?;
+            self.last_reason.insert(key, decision.reason);
+        }
+        Ok(())
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let payer = load_keypair(args.keypair.as_deref())?;
+    let commitment = parse_commitment(&args.commitment)?;
+
+    let mut relayer = Relayer {
+        consensus: build_consensus(&args)?,
+        audit: AuditLog::open(&args.audit_log)?,
+        client: Client::new(&args.url, payer, commitment, args.dry_run),
+        last_reason: HashMap::new(),
+    };
+
+    loop {
+        match relayer.tick(&args) {
+            Ok(()) => {}
+            Err(err) if !args.once => eprintln!("Scan failed: {:#}", err),
+            Err(err) => return Err(err),
+        }
+        if args.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
@@ -0,0 +1,72 @@
+//! The provider trait and the answer format shared by the built-in providers.
+
+use anyhow::{bail, Result};
+use driftshield_sdk::Pubkey;
+use serde_json::Value;
+
+/// A market awaiting resolution
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query {
+    pub market: Pubkey,
+    pub question: String,
+    pub resolution_time: i64,
+}
+
+/// A provider's answer for a settled question
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    /// true = YES
+    pub outcome: bool,
+    /// 0-100
+    pub confidence: u8,
+    pub source: String,
+    pub proof: Option<String>,
+}
+
+pub trait Provider {
+    fn name(&self) -> &str;
+
+    /// `Ok(None)` while the question has not settled at the source
+    fn resolve(&self, query: &Query) -> Result<Option<Resolution>>;
+}
+
+/// Parse an answer object:
+///
+/// ```json
+/// { "outcome": "yes", "confidence": 95, "proof": "https://..." }
+/// ```
+///
+/// `outcome` may be a bool (true = YES), `"yes"`/`"no"`, or an index into
+/// `["Yes", "No"]` as the web app's `APIOracle` returns. A missing or null
+/// `outcome` means unsettled. `confidence` defaults to 100; a non-string
+/// `proof` is kept as JSON text.
+pub fn parse_answer(value: &Value, source: &str) -> Result<Option<Resolution>> {
+    let outcome = match value.get("outcome").unwrap_or(&Value::Null) {
+        Value::Null => return Ok(None),
+        Value::Bool(outcome) => *outcome,
+        Value::Number(n) => match n.as_u64() {
+            Some(0) => true,
+            Some(1) => false,
+            _ => bail!("{}: outcome index {} is not 0 (YES) or 1 (NO)", source, n),
+        },
+        Value::String(s) if s.eq_ignore_ascii_case("yes") => true,
+        Value::String(s) if s.eq_ignore_ascii_case("no") => false,
+        other => bail!("{}: unrecognized outcome {}", source, other),
+    };
+
+    let confidence = match value.get("confidence").unwrap_or(&Value::Null) {
+        Value::Null => 100,
+        other => match other.as_u64() {
+            Some(c) if c <= 100 => c as u8,
+            _ => bail!("{}: confidence {} is not 0-100", source, other),
+        },
+    };
+
+    let proof = match value.get("proof").unwrap_or(&Value::Null) {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    };
+
+    Ok(Some(Resolution { outcome, confidence, source: source.to_string(), proof }))
+}
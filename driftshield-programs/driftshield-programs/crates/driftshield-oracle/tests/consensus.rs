@@ -0,0 +1,179 @@
+use anyhow::{anyhow, Result};
+use driftshield_oracle::{parse_answer, Consensus, FileProvider, Provider, Query, Resolution};
+use driftshield_sdk::Pubkey;
+use serde_json::json;
+
+/// Answers the same way for every query
+struct Fixed {
+    name: &'static str,
+    answer: Option<(bool, u8)>,
+    error: bool,
+}
+
+impl Provider for Fixed {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn resolve(&self, _query: &Query) -> Result<Option<Resolution>> {
+        if self.error {
+            return Err(anyhow!("connection refused"));
+        }
+        Ok(self.answer.map(|(outcome, confidence)| Resolution {
+            outcome,
+            confidence,
+            source: self.name.to_string(),
+            proof: None,
+        }))
+    }
+}
+
+fn answers(name: &'static str, outcome: bool, confidence: u8) -> Box<dyn Provider> {
+    Box::new(Fixed { name, answer: Some((outcome, confidence)), error: false })
+}
+
+fn unsettled(name: &'static str) -> Box<dyn Provider> {
+    Box::new(Fixed { name, answer: None, error: false })
+}
+
+fn failing(name: &'static str) -> Box<dyn Provider> {
+    Box::new(Fixed { name, answer: None, error: true })
+}
+
+fn query(question: &str) -> Query {
+    Query { market: Pubkey::new_unique(), question: question.to_string(), resolution_time: 1_700_000_000 }
+}
+
+#[test]
+fn agreeing_providers_decide() {
+    let consensus = Consensus::new(vec![answers("a", false, 90), answers("b", false, 100)], 2);
+    let decision = consensus.decide(&query("q"));
+    assert_eq!(decision.outcome, Some(false));
+    assert_eq!(decision.reason, "NO with 2/2 providers");
+    assert_eq!(decision.votes.len(), 2);
+
+    let waiting = Consensus::new(vec![answers("a", true, 90), unsettled("b")], 2).decide(&query("q"));
+    assert_eq!(waiting.outcome, None);
+    assert_eq!(waiting.reason, "waiting: 1/2 agreeing answers");
+}
+
+#[test]
+fn any_dissent_blocks_resolution() {
+    let consensus = Consensus::new(vec![answers("a", true, 90), answers("b", true, 90), answers("c", false, 60)], 2);
+    let decision = consensus.decide(&query("q"));
+    assert_eq!(decision.outcome, None);
+    assert_eq!(decision.reason, "providers disagree: 2 YES, 1 NO");
+}
+
+#[test]
+fn errored_providers_vote_but_are_not_counted() {
+    let consensus = Consensus::new(vec![answers("a", true, 90), failing("b")], 1);
+    let decision = consensus.decide(&query("q"));
+    assert_eq!(decision.outcome, Some(true));
+    assert_eq!(decision.reason, "YES with 1/2 providers");
+    assert_eq!(decision.votes[1].answer, Err("connection refused".to_string()));
+    assert_eq!(decision.votes[1].to_json(), json!({ "provider": "b", "error": "connection refused" }));
+
+    let none = Consensus::new(vec![failing("a"), failing("b")], 1).decide(&query("q"));
+    assert_eq!(none.outcome, None);
+}
+
+#[test]
+fn low_confidence_answers_are_ignored() {
+    let providers = || vec![answers("a", true, 95), answers("b", false, 40)];
+
+    let strict = Consensus::new(providers(), 1).with_min_confidence(50).decide(&query("q"));
+    assert_eq!(strict.outcome, Some(true));
+
+    let lax = Consensus::new(providers(), 1).decide(&query("q"));
+    assert_eq!(lax.outcome, None);
+
+    let unconfident = Consensus::new(vec![answers("a", true, 40)], 1).with_min_confidence(50).decide(&query("q"));
+    assert_eq!(unconfident.outcome, None);
+    assert_eq!(unconfident.reason, "waiting: 0/1 agreeing answers");
+}
+
+#[test]
+fn nested_consensus_reports_agreement() {
+    let inner = Consensus::new(vec![answers("a", false, 90), answers("b", false, 90), unsettled("c")], 2);
+    let resolution = inner.resolve(&query("q")).unwrap().unwrap();
+    assert!(!resolution.outcome);
+    assert_eq!(resolution.confidence, 100);
+    assert_eq!(resolution.source, "consensus (2/3)");
+
+    // Answers below the confidence floor count neither as agreement nor dissent
+    let inner = Consensus::new(vec![answers("a", true, 90), answers("b", true, 90), answers("c", false, 30)], 2)
+        .with_min_confidence(50);
+    let resolution = inner.resolve(&query("q")).unwrap().unwrap();
+    assert!(resolution.outcome);
+    assert_eq!(resolution.confidence, 100);
+    assert_eq!(resolution.source, "consensus (2/3)");
+
+    let unconfident = Consensus::new(vec![answers("a", true, 90), answers("b", true, 40)], 2).with_min_confidence(50);
+    assert_eq!(unconfident.resolve(&query("q")).unwrap(), None);
+}
+
+#[test]
+fn parses_answer_formats() {
+    let parse = |value| parse_answer(&value, "test").unwrap().map(|r| (r.outcome, r.confidence, r.proof));
+
+    assert_eq!(parse(json!({ "outcome": true })), Some((true, 100, None)));
+    assert_eq!(parse(json!({ "outcome": "No", "confidence": 75 })), Some((false, 75, None)));
+    assert_eq!(parse(json!({ "outcome": "YES", "proof": "run 42" })), Some((true, 100, Some("run 42".to_string()))));
+    assert_eq!(parse(json!({ "outcome": 0 })), Some((true, 100, None)));
+    assert_eq!(
+        parse(json!({ "outcome": 1, "proof": { "run": 42 } })),
+        Some((false, 100, Some(r#"{"run":42}"#.to_string())))
+    );
+    assert_eq!(parse(json!({ "outcome": null, "confidence": 90 })), None);
+    assert_eq!(parse(json!({})), None);
+}
+
+#[test]
+fn rejects_malformed_answers() {
+    for value in [
+        json!({ "outcome": 2 }),
+        json!({ "outcome": -1 }),
+        json!({ "outcome": "maybe" }),
+        json!({ "outcome": [true] }),
+        json!({ "outcome": true, "confidence": 101 }),
+        json!({ "outcome": true, "confidence": "high" }),
+    ] {
+        let err = parse_answer(&value, "test").unwrap_err();
+        assert!(err.to_string().starts_with("test: "), "{}", err);
+    }
+}
+
+#[test]
+fn resolves_from_fixture() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/resolutions.json");
+    let provider = FileProvider::new("file:resolutions.json", path);
+
+    let accuracy =
+        provider.resolve(&query("Will the fraud model's accuracy drop below 90% this month?")).unwrap().unwrap();
+    assert_eq!(
+        accuracy,
+        Resolution {
+            outcome: false,
+            confidence: 100,
+            source: "file:resolutions.json".to_string(),
+            proof: Some("fixture".to_string()),
+        }
+    );
+
+    let retrained =
+        provider.resolve(&query("Will the pricing model be retrained before the deadline?")).unwrap().unwrap();
+    assert!(retrained.outcome);
+    assert_eq!(retrained.confidence, 80);
+    assert_eq!(retrained.proof.as_deref(), Some(r#"{"report":"fixture","run":42}"#));
+
+    assert_eq!(provider.resolve(&query("Will anything else happen?")).unwrap(), None);
+
+    let consensus = Consensus::new(vec![Box::new(provider), answers("b", true, 90)], 2).with_min_confidence(80);
+    assert_eq!(
+        consensus.decide(&query("Will the pricing model be retrained before the deadline?")).outcome,
+        Some(true)
+    );
+    let split = consensus.decide(&query("Will the fraud model's accuracy drop below 90% this month?"));
+    assert_eq!(split.reason, "providers disagree: 1 YES, 1 NO");
+}
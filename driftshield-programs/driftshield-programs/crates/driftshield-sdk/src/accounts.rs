@@ -0,0 +1,191 @@
+//! Account decoders.
+//!
+//! Accounts created under an older, append-only layout are shorter than the
+//! current one. Decoding zero-pads them to the current size, so fields appended
+//! since read as zero (and `version` as 0), exactly as they will after
+//! `migrate_*`. Layouts that need an explicit translation, such as v0 markets,
+//! fail with [`SdkError::AccountDecode`] until they have been migrated.
+
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::Discriminator;
+use prediction_market::Versioned;
+
+use crate::error::{Result, SdkError};
+use crate::{AGENT_REGISTRY_ID, INSURANCE_ID, MODEL_REGISTRY_ID, PREDICTION_MARKET_ID};
+
+pub use agent_registry::{AgentAccount, RegistryConfig};
+pub use driftshield_programs::{ModelAccount, MonitoringReceipt};
+pub use insurance::InsurancePolicy;
+pub use prediction_market::{
+    Market, Position, PriceHistory, ProtocolConfig, ReferralAccount, RewardPool, RewardStake,
+    SessionKey, UserStats,
+};
+
+/// An account type owned by one of the DriftShield programs
+pub trait ProgramAccount: Sized {
+    const PROGRAM_ID: Pubkey;
+    const DISCRIMINATOR: [u8; 8];
+
+    /// Decode raw account data, discriminator included
+    fn decode(data: &[u8]) -> Result<Self>;
+}
+
+fn check_discriminator(data: &[u8], discriminator: [u8; 8]) -> Result<()> {
+    if data.len() < 8 {
+        return Err(SdkError::AccountTooShort);
+    }
+    if data[..8] != discriminator {
+        return Err(SdkError::AccountDecode("discriminator mismatch".to_string()));
+    }
+    Ok(())
+}
+
+/// Zero-pad `data` to the current layout and decode it, rejecting versions the
+/// padding can't stand in for a migration of
+fn decode_padded<T: Versioned>(data: &[u8]) -> Result<T> {
+    let mut buf = data.to_vec();
+    if buf.len() < T::SPACE {
+        buf.resize(T::SPACE, 0);
+    }
+    let account = T::try_deserialize(&mut buf.as_slice()).map_err(|e| SdkError::AccountDecode(e.to_string()))?;
+
+    // A current account is never shorter than the current layout
+    let version = account.version();
+    let upgradable = (T::APPEND_ONLY_SINCE..T::VERSION).contains(&version);
+    let current = version == T::VERSION && data.len() >= T::SPACE;
+    if !upgradable && !current {
+        return Err(SdkError::AccountDecode(format!(
+            "unsupported layout: version {} in {} bytes",
+            version,
+            data.len()
+        )));
+    }
+    Ok(account)
+}
+
+macro_rules! borsh_accounts {
+    ($($ty:ty => $program_id:expr;)*) => {
+        $(
+            impl ProgramAccount for $ty {
+                const PROGRAM_ID: Pubkey = $program_id;
+                const DISCRIMINATOR: [u8; 8] = <$ty as Discriminator>::DISCRIMINATOR;
+
+                fn decode(data: &[u8]) -> Result<Self> {
+                    check_discriminator(data, <Self as ProgramAccount>::DISCRIMINATOR)?;
+                    decode_padded(data)
+                }
+            }
+        )*
+    };
+}
+
+borsh_accounts! {
+    ModelAccount => MODEL_REGISTRY_ID;
+    MonitoringReceipt => MODEL_REGISTRY_ID;
+    InsurancePolicy => INSURANCE_ID;
+    Market => PREDICTION_MARKET_ID;
+    Position => PREDICTION_MARKET_ID;
+    ProtocolConfig => PREDICTION_MARKET_ID;
+    ReferralAccount => PREDICTION_MARKET_ID;
+    RewardPool => PREDICTION_MARKET_ID;
+    RewardStake => PREDICTION_MARKET_ID;
+    UserStats => PREDICTION_MARKET_ID;
+    SessionKey => PREDICTION_MARKET_ID;
+    RegistryConfig => AGENT_REGISTRY_ID;
+    AgentAccount => AGENT_REGISTRY_ID;
+}
+
+impl ProgramAccount for PriceHistory {
+    const PROGRAM_ID: Pubkey = PREDICTION_MARKET_ID;
+    const DISCRIMINATOR: [u8; 8] = <PriceHistory as Discriminator>::DISCRIMINATOR;
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        check_discriminator(data, <Self as ProgramAccount>::DISCRIMINATOR)?;
+        let size = std::mem::size_of::<PriceHistory>();
+        let mut buf = vec![0u8; size];
+        let body = &data[8..];
+        let len = body.len().min(size);
+        buf[..len].copy_from_slice(&body[..len]);
+        bytemuck::try_pod_read_unaligned(&buf).map_err(|e| SdkError::AccountDecode(e.to_string()))
+    }
+}
+
+/// Decode an account of a known type
+pub fn decode<T: ProgramAccount>(data: &[u8]) -> Result<T> {
+    T::decode(data)
+}
+
+/// Any DriftShield account, identified by owner and discriminator
+pub enum DecodedAccount {
+    Model(Box<ModelAccount>),
+    Receipt(Box<MonitoringReceipt>),
+    Policy(Box<InsurancePolicy>),
+    Market(Box<Market>),
+    Position(Box<Position>),
+    Config(Box<ProtocolConfig>),
+    Referral(Box<ReferralAccount>),
+    RewardPool(Box<RewardPool>),
+    RewardStake(Box<RewardStake>),
+    UserStats(Box<UserStats>),
+    SessionKey(Box<SessionKey>),
+    PriceHistory(Box<PriceHistory>),
+    RegistryConfig(Box<RegistryConfig>),
+    Agent(Box<AgentAccount>),
+}
+
+impl DecodedAccount {
+    pub fn decode(owner: &Pubkey, data: &[u8]) -> Result<Self> {
+        if data.len() < 8 {
+            return Err(SdkError::AccountTooShort);
+        }
+
+        macro_rules! try_decode {
+            ($($variant:ident($ty:ty)),*) => {
+                $(
+                    if *owner == <$ty>::PROGRAM_ID && data[..8] == <$ty as ProgramAccount>::DISCRIMINATOR {
+                        return Ok(DecodedAccount::$variant(Box::new(<$ty as ProgramAccount>::decode(data)?)));
+                    }
+                )*
+            };
+        }
+
+        try_decode!(
+            Model(ModelAccount),
+            Receipt(MonitoringReceipt),
+            Policy(InsurancePolicy),
+            Market(Market),
+            Position(Position),
+            Config(ProtocolConfig),
+            Referral(ReferralAccount),
+            RewardPool(RewardPool),
+            RewardStake(RewardStake),
+            UserStats(UserStats),
+            SessionKey(SessionKey),
+            PriceHistory(PriceHistory),
+            RegistryConfig(RegistryConfig),
+            Agent(AgentAccount)
+        );
+
+        Err(SdkError::UnknownAccount(*owner))
+    }
+
+    /// Account type name as declared in the program
+    pub fn name(&self) -> &'static str {
+        match self {
+            DecodedAccount::Model(_) => "ModelAccount",
+            DecodedAccount::Receipt(_) => "MonitoringReceipt",
+            DecodedAccount::Policy(_) => "InsurancePolicy",
+            DecodedAccount::Market(_) => "Market",
+            DecodedAccount::Position(_) => "Position",
+            DecodedAccount::Config(_) => "ProtocolConfig",
+            DecodedAccount::Referral(_) => "ReferralAccount",
+            DecodedAccount::RewardPool(_) => "RewardPool",
+            DecodedAccount::RewardStake(_) => "RewardStake",
+            DecodedAccount::UserStats(_) => "UserStats",
+            DecodedAccount::SessionKey(_) => "SessionKey",
+            DecodedAccount::PriceHistory(_) => "PriceHistory",
+            DecodedAccount::RegistryConfig(_) => "RegistryConfig",
+            DecodedAccount::Agent(_) => "AgentAccount",
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Blocking RPC helpers shared by the command-line tools (`client` feature).
+
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionConfig, RpcTransactionConfig,
+};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::accounts::ProgramAccount;
+use crate::events::{parse_logs, ProgramEvent};
+
+/// Resolve the usual cluster monikers to an RPC URL
+pub fn resolve_url(url: &str) -> String {
+    match url {
+        "localnet" | "l" => "http://127.0.0.1:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "testnet" | "t" => "https://api.testnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        other => other,
+    }
+    .to_string()
+}
+
+/// Read a keypair file, defaulting to the Solana CLI's `~/.config/solana/id.json`
+pub fn load_keypair(path: Option<&str>) -> anyhow::Result<Keypair> {
+    let path = match path {
+        Some(path) => path.to_string(),
+        None => {
+            let home = std::env::var("HOME").map_err(|_| anyhow::anyhow!("HOME is not set; pass --keypair"))?;
+            format!("{}/.config/solana/id.json", home)
+        }
+    };
+    read_keypair_file(&path).map_err(|e| anyhow::anyhow!("failed to read keypair {}: {}", path, e))
+}
+
+/// Parse a commitment level (processed, confirmed, finalized)
+pub fn parse_commitment(level: &str) -> anyhow::Result<CommitmentConfig> {
+    Ok(match level {
+        "processed" => CommitmentConfig::processed(),
+        "confirmed" => CommitmentConfig::confirmed(),
+        "finalized" => CommitmentConfig::finalized(),
+        other => anyhow::bail!("unknown commitment {}", other),
+    })
+}
+
+/// Outcome of submitting (or simulating) a transaction
+pub struct TxOutcome {
+    pub signature: Option<Signature>,
+    pub err: Option<TransactionError>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    pub events: Vec<ProgramEvent>,
+}
+
+pub struct Client {
+    pub rpc: RpcClient,
+    pub payer: Keypair,
+    /// Simulate transactions instead of sending them
+    pub dry_run: bool,
+}
+
+impl Client {
+    pub fn new(url: &str, payer: Keypair, commitment: CommitmentConfig, dry_run: bool) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(resolve_url(url), commitment),
+            payer,
+            dry_run,
+        }
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// Raw account, or `None` if it does not exist
+    pub fn account(&self, address: &Pubkey) -> anyhow::Result<Option<solana_sdk::account::Account>> {
+        Ok(self.rpc.get_account_with_commitment(address, self.rpc.commitment())?.value)
+    }
+
+    pub fn exists(&self, address: &Pubkey) -> anyhow::Result<bool> {
+        Ok(self.account(address)?.is_some())
+    }
+
+    /// Fetch and decode a program account
+    pub fn fetch<T: ProgramAccount>(&self, address: &Pubkey) -> anyhow::Result<T> {
+        let account = self
+            .account(address)?
+            .ok_or_else(|| anyhow::anyhow!("account {} not found", address))?;
+        anyhow::ensure!(
+            account.owner == T::PROGRAM_ID,
+            "account {} is owned by {}, expected {}",
+            address,
+            account.owner,
+            T::PROGRAM_ID
+        );
+        Ok(T::decode(&account.data)?)
+    }
+
+    /// Every account of type `T` matching `filters`, decoded. Accounts that
+    /// fail to decode are skipped with a warning.
+    pub fn program_accounts<T: ProgramAccount>(&self, filters: Vec<RpcFilterType>) -> anyhow::Result<Vec<(Pubkey, T)>> {
+        let mut all_filters = vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, T::DISCRIMINATOR.to_vec()))];
+        all_filters.extend(filters);
+        let accounts = self.rpc.get_program_accounts_with_config(
+            &T::PROGRAM_ID,
+            RpcProgramAccountsConfig {
+                filters: Some(all_filters),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(self.rpc.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )?;
+
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(address, account)| match T::decode(&account.data) {
+                Ok(decoded) => Some((address, decoded)),
+                Err(err) => {
+                    eprintln!("Skipping {}: {}", address, err);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Mint of an SPL token account
+    pub fn token_mint(&self, token_account: &Pubkey) -> anyhow::Result<Pubkey> {
+        let account = self
+            .account(token_account)?
+            .ok_or_else(|| anyhow::anyhow!("token account {} not found", token_account))?;
+        Ok(anchor_spl::token::spl_token::state::Account::unpack(&account.data)?.mint)
+    }
+
+    /// Cluster unix time, as `Clock::get()` would see it in the next block
+    pub fn now(&self) -> anyhow::Result<i64> {
+        let slot = self.rpc.get_slot()?;
+        Ok(self.rpc.get_block_time(slot)?)
+    }
+
+    /// Sign with the payer and `signers`, then send (or simulate when `dry_run`)
+    pub fn send(&self, instructions: &[Instruction], signers: &[&Keypair]) -> anyhow::Result<TxOutcome> {
+        let mut all_signers: Vec<&dyn Signer> = vec![&self.payer];
+        all_signers.extend(signers.iter().map(|s| *s as &dyn Signer));
+
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(instructions, Some(&self.payer()), &all_signers, blockhash);
+
+        if self.dry_run {
+            return self.simulate(&tx);
+        }
+
+        let signature = self.rpc.send_and_confirm_transaction_with_spinner(&tx)?;
+        let confirmed = self.rpc.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(self.rpc.commitment()),
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+        let meta = confirmed.transaction.meta;
+        let logs: Vec<String> = meta
+            .as_ref()
+            .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages.clone()))
+            .unwrap_or_default();
+
+        Ok(TxOutcome {
+            signature: Some(signature),
+            err: meta.as_ref().and_then(|meta| meta.err.clone()),
+            units_consumed: meta.and_then(|meta| meta.compute_units_consumed.into()),
+            events: parse_logs(&logs)?,
+            logs,
+        })
+    }
+
+    fn simulate(&self, tx: &Transaction) -> anyhow::Result<TxOutcome> {
+        let result = self
+            .rpc
+            .simulate_transaction_with_config(
+                tx,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    commitment: Some(self.rpc.commitment()),
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )?
+            .value;
+        let logs = result.logs.unwrap_or_default();
+
+        Ok(TxOutcome {
+            signature: None,
+            err: result.err,
+            units_consumed: result.units_consumed,
+            events: parse_logs(&logs)?,
+            logs,
+        })
+    }
+}
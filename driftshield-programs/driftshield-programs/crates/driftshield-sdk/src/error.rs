@@ -0,0 +1,23 @@
+use anchor_lang::solana_program::pubkey::Pubkey;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SdkError {
+    #[error("account data is too short")]
+    AccountTooShort,
+    #[error("unknown account discriminator for program {0}")]
+    UnknownAccount(Pubkey),
+    #[error("failed to decode account: {0}")]
+    AccountDecode(String),
+    #[error("failed to decode event log: {0}")]
+    EventDecode(String),
+    #[error("program error: {0}")]
+    Program(String),
+}
+
+impl From<anchor_lang::error::Error> for SdkError {
+    fn from(err: anchor_lang::error::Error) -> Self {
+        SdkError::Program(err.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, SdkError>;
@@ -0,0 +1,212 @@
+//! Anchor event log parsers.
+//!
+//! `emit!` writes `Program data: <base64(discriminator || borsh)>` to the
+//! transaction logs. Events are attributed to the program on top of the
+//! invoke stack, so data logged by other programs is never misdecoded.
+
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use std::str::FromStr;
+
+use crate::error::{Result, SdkError};
+use crate::{AGENT_REGISTRY_ID, INSURANCE_ID, MODEL_REGISTRY_ID, PREDICTION_MARKET_ID};
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+macro_rules! program_events {
+    ($(#[$meta:meta])* $name:ident, $program:ident { $($event:ident),* $(,)? }) => {
+        $(#[$meta])*
+        pub enum $name {
+            $($event($program::$event),)*
+        }
+
+        impl $name {
+            /// Decode event data (discriminator included); `None` if the discriminator is unknown
+            pub fn decode(data: &[u8]) -> Result<Option<Self>> {
+                if data.len() < 8 {
+                    return Err(SdkError::EventDecode("event data is too short".to_string()));
+                }
+                let (discriminator, mut body) = data.split_at(8);
+                $(
+                    if discriminator == <$program::$event as Discriminator>::DISCRIMINATOR {
+                        let event = <$program::$event as AnchorDeserialize>::deserialize(&mut body)
+                            .map_err(|e| SdkError::EventDecode(e.to_string()))?;
+                        return Ok(Some($name::$event(event)));
+                    }
+                )*
+                Ok(None)
+            }
+
+            /// Event name as declared in the program
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($name::$event(_) => stringify!($event),)*
+                }
+            }
+        }
+    };
+}
+
+use driftshield_programs as model_registry;
+
+program_events!(
+    /// Events emitted by the model registry
+    ModelRegistryEvent, model_registry {
+        AccountMigrated,
+        ModelRegistered,
+        ReceiptSubmitted,
+        DriftAlert,
+    }
+);
+
+program_events!(
+    /// Events emitted by the insurance program
+    InsuranceEvent, insurance {
+        AccountMigrated,
+        PolicyPurchased,
+        ClaimPaid,
+        PolicyCancelled,
+        PolicyExpired,
+    }
+);
+
+program_events!(
+    /// Events emitted by the prediction market
+    PredictionMarketEvent, prediction_market {
+        AccountMigrated,
+        MarketCreated,
+        DriftMarketCreated,
+        BetPlaced,
+        SharesSold,
+        MarketResolved,
+        LosingPositionSettled,
+        BatchClaimed,
+        LiquidityWithdrawn,
+        ConfigUpdated,
+        CrankTipUpdated,
+        CrankTipPaid,
+        DriftAttesterUpdated,
+        ExposureFloorsUpdated,
+        MarketClosed,
+        SessionKeyCreated,
+        SessionKeyRevoked,
+        RewardPoolCreated,
+        RewardMarketAdded,
+        RewardsClaimed,
+        UnallocatedRewardsWithdrawn,
+        ReferrerRegistered,
+        ReferralRewardAccrued,
+        ReferralRewardsClaimed,
+        CreatorBondReturned,
+        MarketCancelled,
+        ParentMarketLinked,
+        ConditionalMarketVoided,
+        AbandonedMarketVoided,
+        RefundClaimed,
+        AllowlistRootUpdated,
+        DynamicFeesUpdated,
+        CircuitBreakerUpdated,
+        MarketHalted,
+        MarketResumed,
+        ExposureLimitsUpdated,
+        PriceHistoryInitialized,
+        WinningsClaimed,
+    }
+);
+
+program_events!(
+    /// Events emitted by the agent registry
+    AgentRegistryEvent, agent_registry {
+        AccountMigrated,
+        AgentRegistered,
+        AgentUpdated,
+        AgentSuspended,
+        AgentReinstated,
+        AgentVerificationChanged,
+        AgentDeregistered,
+    }
+);
+
+/// An event from any DriftShield program
+pub enum ProgramEvent {
+    ModelRegistry(ModelRegistryEvent),
+    Insurance(InsuranceEvent),
+    PredictionMarket(PredictionMarketEvent),
+    AgentRegistry(AgentRegistryEvent),
+}
+
+impl ProgramEvent {
+    /// Decode event data emitted by `program_id`; `None` for other programs or unknown events
+    pub fn decode(program_id: &Pubkey, data: &[u8]) -> Result<Option<Self>> {
+        Ok(if *program_id == MODEL_REGISTRY_ID {
+            ModelRegistryEvent::decode(data)?.map(ProgramEvent::ModelRegistry)
+        } else if *program_id == INSURANCE_ID {
+            InsuranceEvent::decode(data)?.map(ProgramEvent::Insurance)
+        } else if *program_id == PREDICTION_MARKET_ID {
+            PredictionMarketEvent::decode(data)?.map(ProgramEvent::PredictionMarket)
+        } else if *program_id == AGENT_REGISTRY_ID {
+            AgentRegistryEvent::decode(data)?.map(ProgramEvent::AgentRegistry)
+        } else {
+            None
+        })
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        match self {
+            ProgramEvent::ModelRegistry(_) => MODEL_REGISTRY_ID,
+            ProgramEvent::Insurance(_) => INSURANCE_ID,
+            ProgramEvent::PredictionMarket(_) => PREDICTION_MARKET_ID,
+            ProgramEvent::AgentRegistry(_) => AGENT_REGISTRY_ID,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProgramEvent::ModelRegistry(event) => event.name(),
+            ProgramEvent::Insurance(event) => event.name(),
+            ProgramEvent::PredictionMarket(event) => event.name(),
+            ProgramEvent::AgentRegistry(event) => event.name(),
+        }
+    }
+}
+
+/// Parse every DriftShield event out of a transaction's log messages, in order
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Result<Vec<ProgramEvent>> {
+    let mut stack: Vec<Pubkey> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let line = line.as_ref();
+
+        if let Some(data) = line.strip_prefix(PROGRAM_DATA) {
+            let program_id = match stack.last() {
+                Some(program_id) => program_id,
+                None => continue,
+            };
+            let bytes = base64::decode(data.trim()).map_err(|e| SdkError::EventDecode(e.to_string()))?;
+            if let Some(event) = ProgramEvent::decode(program_id, &bytes)? {
+                events.push(event);
+            }
+            continue;
+        }
+
+        // "Program <id> invoke [depth]" / "Program <id> success" / "Program <id> failed: ..."
+        let mut words = line.split_whitespace();
+        if words.next() != Some("Program") {
+            continue;
+        }
+        let program_id = match words.next().map(Pubkey::from_str) {
+            Some(Ok(program_id)) => program_id,
+            _ => continue,
+        };
+        match words.next() {
+            Some("invoke") => stack.push(program_id),
+            Some("success") | Some("failed:") => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(events)
+}
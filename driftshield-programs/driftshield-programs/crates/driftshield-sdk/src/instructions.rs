@@ -0,0 +1,564 @@
+//! Typed instruction builders.
+//!
+//! Instruction data and account lists come from the Anchor-generated
+//! `instruction::*` and `accounts::*` types of each program, so they stay in
+//! sync with the on-chain argument order and discriminators. Builders below
+//! cover the common flows and derive PDAs; anything else can be built with
+//! [`build`].
+
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use anchor_spl::token;
+
+use crate::pda;
+use crate::{INSURANCE_ID, MODEL_REGISTRY_ID, PREDICTION_MARKET_ID};
+
+use driftshield_programs as model_registry;
+use prediction_market::{DriftCondition, Market, ProtocolConfig};
+
+/// Build any instruction from a program's generated accounts and args structs
+pub fn build<A: ToAccountMetas, D: InstructionData>(program_id: Pubkey, accounts: A, args: D) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+// Model registry
+
+pub fn register_model(
+    owner: Pubkey,
+    model_id: String,
+    name: String,
+    model_type: String,
+    framework: String,
+    baseline_accuracy: u64,
+) -> Instruction {
+    let (model, _) = pda::model(&owner, &model_id);
+    build(
+        MODEL_REGISTRY_ID,
+        model_registry::accounts::RegisterModel {
+            model,
+            owner,
+            system_program: system_program::ID,
+        },
+        model_registry::instruction::RegisterModel {
+            model_id,
+            name,
+            model_type,
+            framework,
+            baseline_accuracy,
+        },
+    )
+}
+
+/// Metrics reported in a monitoring receipt (basis points)
+#[derive(Clone, Debug, Default)]
+pub struct ReceiptMetrics {
+    pub accuracy: u64,
+    pub precision: u64,
+    pub recall: u64,
+    pub f1_score: u64,
+    pub drift_score: u64,
+    pub metadata_uri: String,
+}
+
+/// `receipt` is a fresh keypair that must also sign the transaction
+pub fn submit_monitoring_receipt(owner: Pubkey, model: Pubkey, receipt: Pubkey, metrics: ReceiptMetrics) -> Instruction {
+    build(
+        MODEL_REGISTRY_ID,
+        model_registry::accounts::SubmitReceipt {
+            model,
+            receipt,
+            owner,
+            system_program: system_program::ID,
+        },
+        model_registry::instruction::SubmitMonitoringReceipt {
+            accuracy: metrics.accuracy,
+            precision: metrics.precision,
+            recall: metrics.recall,
+            f1_score: metrics.f1_score,
+            drift_score: metrics.drift_score,
+            metadata_uri: metrics.metadata_uri,
+        },
+    )
+}
+
+// Insurance
+
+#[allow(clippy::too_many_arguments)]
+pub fn purchase_policy(
+    owner: Pubkey,
+    model: Pubkey,
+    user_token_account: Pubkey,
+    insurance_vault: Pubkey,
+    coverage_amount: u64,
+    premium: u64,
+    accuracy_threshold: u64,
+    duration_days: i64,
+) -> Instruction {
+    let (policy, _) = pda::policy(&owner, &model);
+    build(
+        INSURANCE_ID,
+        insurance::accounts::PurchasePolicy {
+            policy,
+            owner,
+            user_token_account,
+            insurance_vault,
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        insurance::instruction::PurchasePolicy {
+            model_pubkey: model,
+            coverage_amount,
+            premium,
+            accuracy_threshold,
+            duration_days,
+        },
+    )
+}
+
+pub fn file_claim(
+    owner: Pubkey,
+    model: Pubkey,
+    owner_token_account: Pubkey,
+    insurance_vault: Pubkey,
+    current_accuracy: u64,
+) -> Instruction {
+    let (policy, _) = pda::policy(&owner, &model);
+    build(
+        INSURANCE_ID,
+        insurance::accounts::FileClaim {
+            policy,
+            owner,
+            owner_token_account,
+            insurance_vault,
+            token_program: token::ID,
+        },
+        insurance::instruction::FileClaim { current_accuracy },
+    )
+}
+
+pub fn cancel_policy(owner: Pubkey, model: Pubkey, owner_token_account: Pubkey, insurance_vault: Pubkey) -> Instruction {
+    let (policy, _) = pda::policy(&owner, &model);
+    build(
+        INSURANCE_ID,
+        insurance::accounts::CancelPolicy {
+            policy,
+            owner,
+            owner_token_account,
+            insurance_vault,
+            token_program: token::ID,
+        },
+        insurance::instruction::CancelPolicy {},
+    )
+}
+
+/// Permissionless crank. `tip` is (policy vault, cranker token account);
+/// `None` expires the policy without collecting the tip.
+pub fn expire_policy(cranker: Pubkey, policy: Pubkey, tip: Option<(Pubkey, Pubkey)>) -> Instruction {
+    build(
+        INSURANCE_ID,
+        insurance::accounts::ExpirePolicy {
+            policy,
+            cranker,
+            insurance_vault: tip.map(|(vault, _)| vault),
+            cranker_token_account: tip.map(|(_, account)| account),
+            token_program: token::ID,
+        },
+        insurance::instruction::ExpirePolicy {},
+    )
+}
+
+// Prediction market
+
+/// Accounts for creating a market. `market_vault` and `bond_vault` are token
+/// accounts of the collateral mint owned by the market PDA.
+#[derive(Clone, Copy, Debug)]
+pub struct CreateMarketAccounts {
+    pub creator: Pubkey,
+    pub model: Pubkey,
+    pub creator_token_account: Pubkey,
+    pub market_vault: Pubkey,
+    pub bond_vault: Pubkey,
+}
+
+/// Market parameters shared by `create_market` and `create_drift_market`
+#[derive(Clone, Debug)]
+pub struct MarketTerms {
+    pub resolution_time: i64,
+    pub min_stake: u64,
+    pub initial_liquidity: u64,
+    pub allowlist_root: Option<[u8; 32]>,
+}
+
+fn create_market_accounts(config: &ProtocolConfig, accounts: &CreateMarketAccounts) -> prediction_market::accounts::CreateMarket {
+    let (market, _) = pda::market(&accounts.creator, &accounts.model);
+    prediction_market::accounts::CreateMarket {
+        market,
+        creator: accounts.creator,
+        market_vault: accounts.market_vault,
+        config: pda::config().0,
+        creator_token_account: accounts.creator_token_account,
+        bond_vault: accounts.bond_vault,
+        treasury: config.treasury,
+        token_program: token::ID,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn create_market(config: &ProtocolConfig, accounts: &CreateMarketAccounts, question: String, terms: MarketTerms) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        create_market_accounts(config, accounts),
+        prediction_market::instruction::CreateMarket {
+            model_pubkey: accounts.model,
+            question,
+            resolution_time: terms.resolution_time,
+            min_stake: terms.min_stake,
+            initial_liquidity: terms.initial_liquidity,
+            allowlist_root: terms.allowlist_root,
+        },
+    )
+}
+
+pub fn create_drift_market(
+    config: &ProtocolConfig,
+    accounts: &CreateMarketAccounts,
+    condition: DriftCondition,
+    terms: MarketTerms,
+) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::CreateDriftMarket {
+            model: accounts.model,
+            create: create_market_accounts(config, accounts),
+        },
+        prediction_market::instruction::CreateDriftMarket {
+            model_pubkey: accounts.model,
+            condition,
+            resolution_time: terms.resolution_time,
+            min_stake: terms.min_stake,
+            initial_liquidity: terms.initial_liquidity,
+            allowlist_root: terms.allowlist_root,
+        },
+    )
+}
+
+/// Who is trading and how. `user` signs; when `session_owner` is set, `user`
+/// is that owner's session key and the position belongs to the owner. Session
+/// bets are paid from `session_vault`; proceeds still go to `user_token_account`,
+/// which must then belong to the owner.
+#[derive(Clone, Copy, Debug)]
+pub struct Trader {
+    pub user: Pubkey,
+    pub session_owner: Option<Pubkey>,
+    pub session_vault: Option<Pubkey>,
+    pub user_token_account: Pubkey,
+    pub referrer: Option<Pubkey>,
+}
+
+impl Trader {
+    pub fn new(user: Pubkey, user_token_account: Pubkey) -> Self {
+        Self {
+            user,
+            session_owner: None,
+            session_vault: None,
+            user_token_account,
+            referrer: None,
+        }
+    }
+
+    /// Wallet that owns the position, stats and reward stake
+    pub fn key(&self) -> Pubkey {
+        self.session_owner.unwrap_or(self.user)
+    }
+
+    fn session(&self) -> Option<Pubkey> {
+        self.session_owner.map(|owner| pda::session(&owner, &self.user).0)
+    }
+}
+
+pub fn place_bet(
+    config: &ProtocolConfig,
+    market_key: Pubkey,
+    market: &Market,
+    trader: &Trader,
+    outcome: bool,
+    amount: u64,
+    allowlist_proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let trader_key = trader.key();
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::PlaceBet {
+            market: market_key,
+            position: pda::position(&market_key, &trader_key).0,
+            user: trader.user,
+            session: trader.session(),
+            user_stats: pda::user_stats(&trader_key).0,
+            user_token_account: trader.session_vault.unwrap_or(trader.user_token_account),
+            market_vault: market.vault,
+            price_history: market.price_history,
+            config: pda::config().0,
+            treasury: config.treasury,
+            referral: trader.referrer.map(|referrer| pda::referral(&referrer).0),
+            referral_vault: trader.referrer.map(|_| config.referral_vault),
+            parent_market: market.parent_market,
+            reward_pool: market.reward_pool,
+            reward_stake: market.reward_pool.map(|_| pda::reward_stake(&market_key, &trader_key).0),
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        prediction_market::instruction::PlaceBet {
+            outcome,
+            amount,
+            allowlist_proof,
+        },
+    )
+}
+
+pub fn sell_shares(
+    config: &ProtocolConfig,
+    market_key: Pubkey,
+    market: &Market,
+    trader: &Trader,
+    outcome: bool,
+    shares: u64,
+) -> Instruction {
+    let trader_key = trader.key();
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::SellShares {
+            market: market_key,
+            position: pda::position(&market_key, &trader_key).0,
+            user: trader.user,
+            session: trader.session(),
+            user_stats: pda::user_stats(&trader_key).0,
+            user_token_account: trader.user_token_account,
+            market_vault: market.vault,
+            price_history: market.price_history,
+            config: pda::config().0,
+            treasury: config.treasury,
+            referral: trader.referrer.map(|referrer| pda::referral(&referrer).0),
+            referral_vault: trader.referrer.map(|_| config.referral_vault),
+            parent_market: market.parent_market,
+            reward_pool: market.reward_pool,
+            reward_stake: market.reward_pool.map(|_| pda::reward_stake(&market_key, &trader_key).0),
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        prediction_market::instruction::SellShares { outcome, shares },
+    )
+}
+
+pub fn resolve_market(authority: Pubkey, market_key: Pubkey, market: &Market, outcome: bool) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::ResolveMarket {
+            market: market_key,
+            authority,
+            parent_market: market.parent_market,
+        },
+        prediction_market::instruction::ResolveMarket { outcome },
+    )
+}
+
+/// `attester` must be the config's `drift_attester` and sign
+pub fn settle_drift_market(attester: Pubkey, market_key: Pubkey, market: &Market, receipt: Pubkey) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::SettleDriftMarket {
+            market: market_key,
+            receipt,
+            parent_market: market.parent_market,
+            config: pda::config().0,
+            attester,
+        },
+        prediction_market::instruction::SettleDriftMarket {},
+    )
+}
+
+pub fn claim_winnings(market_key: Pubkey, market: &Market, trader: &Trader) -> Instruction {
+    let trader_key = trader.key();
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::ClaimWinnings {
+            market: market_key,
+            position: pda::position(&market_key, &trader_key).0,
+            user: trader.user,
+            session: trader.session(),
+            user_stats: pda::user_stats(&trader_key).0,
+            user_token_account: trader.user_token_account,
+            market_vault: market.vault,
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        prediction_market::instruction::ClaimWinnings {},
+    )
+}
+
+pub fn claim_refund(market_key: Pubkey, market: &Market, user: Pubkey, user_token_account: Pubkey) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::ClaimRefund {
+            market: market_key,
+            position: pda::position(&market_key, &user).0,
+            user,
+            user_token_account,
+            market_vault: market.vault,
+            token_program: token::ID,
+        },
+        prediction_market::instruction::ClaimRefund {},
+    )
+}
+
+/// `crank_vault` must be a token account owned by the config PDA
+pub fn set_crank_tip(admin: Pubkey, crank_vault: Pubkey, crank_tip: u64) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::SetCrankTip {
+            config: pda::config().0,
+            admin,
+            crank_vault,
+        },
+        prediction_market::instruction::SetCrankTip { crank_tip },
+    )
+}
+
+pub fn set_drift_attester(admin: Pubkey, drift_attester: Pubkey) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::SetDriftAttester {
+            config: pda::config().0,
+            admin,
+        },
+        prediction_market::instruction::SetDriftAttester { drift_attester },
+    )
+}
+
+pub fn set_exposure_floors(
+    admin: Pubkey,
+    max_position_stake: u64,
+    max_wallet_oi_bps: u16,
+    max_price_impact_bps: u16,
+    wallet_oi_floor: u64,
+) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::SetExposureFloors {
+            config: pda::config().0,
+            admin,
+        },
+        prediction_market::instruction::SetExposureFloors {
+            max_position_stake,
+            max_wallet_oi_bps,
+            max_price_impact_bps,
+            wallet_oi_floor,
+        },
+    )
+}
+
+/// Tip accounts for the crank instructions. The tip is only requested when
+/// tips are enabled and the cranker has a token account to receive it.
+fn crank_tip_accounts(
+    config: &ProtocolConfig,
+    cranker: Pubkey,
+    cranker_token_account: Option<Pubkey>,
+) -> prediction_market::accounts::CrankTip {
+    let cranker_token_account = cranker_token_account.filter(|_| config.crank_tip > 0);
+    prediction_market::accounts::CrankTip {
+        config: pda::config().0,
+        cranker,
+        crank_vault: cranker_token_account.map(|_| config.crank_vault),
+        cranker_token_account,
+        token_program: token::ID,
+    }
+}
+
+/// Permissionless crank: close trading on a market past `resolution_time`
+pub fn close_market(
+    config: &ProtocolConfig,
+    cranker: Pubkey,
+    cranker_token_account: Option<Pubkey>,
+    market_key: Pubkey,
+) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::CloseMarket {
+            market: market_key,
+            tip: crank_tip_accounts(config, cranker, cranker_token_account),
+        },
+        prediction_market::instruction::CloseMarket {},
+    )
+}
+
+/// Permissionless crank: resolve an unsettled drift market NO after the grace period
+pub fn finalize_drift_market(
+    config: &ProtocolConfig,
+    cranker: Pubkey,
+    cranker_token_account: Option<Pubkey>,
+    market_key: Pubkey,
+    market: &Market,
+) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::FinalizeDriftMarket {
+            market: market_key,
+            parent_market: market.parent_market,
+            tip: crank_tip_accounts(config, cranker, cranker_token_account),
+        },
+        prediction_market::instruction::FinalizeDriftMarket {},
+    )
+}
+
+/// Permissionless crank: void a non-drift market its creator left unresolved
+/// past `ABANDONED_MARKET_GRACE`, slashing the creator bond
+pub fn void_abandoned_market(
+    config: &ProtocolConfig,
+    cranker: Pubkey,
+    cranker_token_account: Option<Pubkey>,
+    market_key: Pubkey,
+    market: &Market,
+) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::VoidAbandonedMarket {
+            market: market_key,
+            tip: crank_tip_accounts(config, cranker, cranker_token_account),
+            bond_vault: market.bond_vault,
+            treasury: config.treasury,
+        },
+        prediction_market::instruction::VoidAbandonedMarket {},
+    )
+}
+
+/// View instruction; simulate it and read the Borsh-encoded `BuyQuote` return data.
+/// Pass the trader's position, if it exists, so its limits apply.
+pub fn quote_buy(market: Pubkey, position: Option<Pubkey>, outcome: bool, amount: u64) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::QuoteTrade {
+            market,
+            position,
+            config: pda::config().0,
+        },
+        prediction_market::instruction::QuoteBuy { outcome, amount },
+    )
+}
+
+/// View instruction; simulate it and read the Borsh-encoded `SellQuote` return data.
+/// Pass the trader's position, if it exists, so its limits apply.
+pub fn quote_sell(market: Pubkey, position: Option<Pubkey>, outcome: bool, shares: u64) -> Instruction {
+    build(
+        PREDICTION_MARKET_ID,
+        prediction_market::accounts::QuoteTrade {
+            market,
+            position,
+            config: pda::config().0,
+        },
+        prediction_market::instruction::QuoteSell { outcome, shares },
+    )
+}
@@ -0,0 +1,32 @@
+//! Rust client SDK for the DriftShield programs.
+//!
+//! - `pda`: program-derived address helpers
+//! - `instructions`: typed instruction builders
+//! - `accounts`: account decoders
+//! - `events`: Anchor event log parsers
+//! - `pricing`: off-chain quotes computed with the programs' own math
+//! - `client`: blocking RPC helpers (`client` feature)
+
+pub mod accounts;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod error;
+pub mod events;
+pub mod instructions;
+pub mod pda;
+pub mod pricing;
+
+pub use agent_registry;
+pub use driftshield_programs as model_registry;
+pub use insurance;
+pub use prediction_market;
+
+pub use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+pub use anchor_lang::solana_program::pubkey::Pubkey;
+pub use error::{Result, SdkError};
+
+/// Program IDs
+pub const MODEL_REGISTRY_ID: Pubkey = driftshield_programs::ID;
+pub const INSURANCE_ID: Pubkey = insurance::ID;
+pub const PREDICTION_MARKET_ID: Pubkey = prediction_market::ID;
+pub const AGENT_REGISTRY_ID: Pubkey = agent_registry::ID;
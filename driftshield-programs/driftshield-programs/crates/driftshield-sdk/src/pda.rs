@@ -0,0 +1,87 @@
+//! Program-derived addresses, mirroring the `seeds` constraints in each program.
+
+use anchor_lang::solana_program::pubkey::Pubkey;
+
+use crate::{AGENT_REGISTRY_ID, INSURANCE_ID, MODEL_REGISTRY_ID, PREDICTION_MARKET_ID};
+
+// Model registry
+
+pub fn model(owner: &Pubkey, model_id: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"model", owner.as_ref(), model_id.as_bytes()],
+        &MODEL_REGISTRY_ID,
+    )
+}
+
+// Insurance
+
+pub fn policy(owner: &Pubkey, model: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"policy", owner.as_ref(), model.as_ref()], &INSURANCE_ID)
+}
+
+// Prediction market
+
+pub fn config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &PREDICTION_MARKET_ID)
+}
+
+pub fn market(creator: &Pubkey, model: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"market", creator.as_ref(), model.as_ref()],
+        &PREDICTION_MARKET_ID,
+    )
+}
+
+/// Position of `trader` (the session owner when trading through a session key)
+pub fn position(market: &Pubkey, trader: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"position", market.as_ref(), trader.as_ref()],
+        &PREDICTION_MARKET_ID,
+    )
+}
+
+pub fn price_history(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"price_history", market.as_ref()], &PREDICTION_MARKET_ID)
+}
+
+pub fn referral(referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referral", referrer.as_ref()], &PREDICTION_MARKET_ID)
+}
+
+pub fn session(owner: &Pubkey, session_key: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"session", owner.as_ref(), session_key.as_ref()],
+        &PREDICTION_MARKET_ID,
+    )
+}
+
+pub fn user_stats(trader: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"user_stats", trader.as_ref()], &PREDICTION_MARKET_ID)
+}
+
+pub fn reward_pool(sponsor: &Pubkey, pool_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"reward_pool", sponsor.as_ref(), &pool_id.to_le_bytes()],
+        &PREDICTION_MARKET_ID,
+    )
+}
+
+pub fn reward_stake(market: &Pubkey, trader: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"reward_stake", market.as_ref(), trader.as_ref()],
+        &PREDICTION_MARKET_ID,
+    )
+}
+
+// Agent registry
+
+pub fn registry_config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"registry_config"], &AGENT_REGISTRY_ID)
+}
+
+pub fn agent(owner: &Pubkey, agent_id: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"agent", owner.as_ref(), agent_id.as_bytes()],
+        &AGENT_REGISTRY_ID,
+    )
+}
@@ -0,0 +1,67 @@
+//! Off-chain pricing.
+//!
+//! These call the programs' own pricing functions (the same code `quote_buy`,
+//! `quote_sell`, `quote_claim`, `place_bet`, `sell_shares` and `cancel_policy`
+//! run on-chain), so results match on-chain execution for the same account
+//! state and clock.
+
+use crate::error::Result;
+
+pub use prediction_market::{BuyQuote, ClaimQuote, SellQuote};
+
+use insurance::InsurancePolicy;
+use prediction_market::{Market, MarketStatus, Position, ProtocolConfig};
+
+/// Quote a `place_bet` of `amount` at unix time `now`, for the trader's position if they have one.
+/// Fails with the error `place_bet` would return.
+pub fn quote_buy(
+    config: &ProtocolConfig,
+    market: &Market,
+    position: Option<&Position>,
+    outcome: bool,
+    amount: u64,
+    now: i64,
+) -> Result<BuyQuote> {
+    Ok(prediction_market::quote_buy_amount(config, market, position, outcome, amount, now)?)
+}
+
+/// Quote a `sell_shares` of `shares` from `position` at unix time `now`.
+/// Fails with the error `sell_shares` would return.
+pub fn quote_sell(
+    config: &ProtocolConfig,
+    market: &Market,
+    position: Option<&Position>,
+    outcome: bool,
+    shares: u64,
+    now: i64,
+) -> Result<SellQuote> {
+    Ok(prediction_market::quote_sell_shares(config, market, position, outcome, shares, now)?)
+}
+
+/// Quote `claim_winnings` for a position in a resolved market
+pub fn quote_claim(market: &Market, position: &Position) -> Result<ClaimQuote> {
+    Ok(prediction_market::quote_claim_payout(market, position)?)
+}
+
+/// Refund `claim_refund` pays a position in a cancelled market
+pub fn quote_refund(market: &Market, position: &Position) -> Result<u64> {
+    if market.status != MarketStatus::Cancelled {
+        return Ok(0);
+    }
+    Ok(market.refund_for(position.total_stake)?)
+}
+
+/// (YES, NO) spot prices in basis points
+pub fn prices(market: &Market) -> Result<(u64, u64)> {
+    Ok(prediction_market::prices_from_reserves(market.yes_reserve, market.no_reserve)?)
+}
+
+/// Trading fee rate (protocol plus dynamic fee) in basis points at unix time `now`
+pub fn fee_bps(config: &ProtocolConfig, market: &Market, now: i64) -> Result<u16> {
+    Ok(prediction_market::trading_fee_bps(config, market, now)?)
+}
+
+/// Premium refunded by `cancel_policy` at unix time `now`
+pub fn policy_refund(policy: &InsurancePolicy, now: i64) -> Result<u64> {
+    Ok(policy.refund_at(now)?)
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use anchor_lang::{AccountSerialize, AnchorSerialize, Discriminator};
+use driftshield_sdk::accounts::{self, DecodedAccount};
+use driftshield_sdk::agent_registry::{AgentAccount, AgentStatus};
+use driftshield_sdk::prediction_market::{Market, MarketStatus, MarketV0, Position, PriceHistory, Versioned};
+use driftshield_sdk::{Pubkey, SdkError, AGENT_REGISTRY_ID, INSURANCE_ID, PREDICTION_MARKET_ID};
+
+/// Account data as the program leaves it: serialized by `exit` into a buffer of `SPACE` bytes
+fn account_data<T: AccountSerialize + Versioned>(account: &T) -> Vec<u8> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    assert!(data.len() <= T::SPACE, "serialized account overflows its allocation");
+    data.resize(T::SPACE, 0);
+    data
+}
+
+fn traded_market() -> Market {
+    let mut market = common::market(1_000_000);
+    market.yes_reserve = 810_000;
+    market.no_reserve = 1_234_568;
+    market.total_volume = 250_000;
+    market.yes_pool = 250_000;
+    market.total_yes_shares = 424_568;
+    market.winning_outcome = Some(true);
+    market.allowlist_root = Some([7; 32]);
+    market.price_history = Some(Pubkey::new_unique());
+    market.lp_fees = 1_250;
+    market
+}
+
+#[test]
+fn decodes_market_written_by_program() {
+    let market = traded_market();
+    let data = account_data(&market);
+
+    let decoded: Market = accounts::decode(&data).unwrap();
+    assert_eq!(decoded.creator, market.creator);
+    assert_eq!(decoded.question, market.question);
+    assert_eq!(decoded.yes_reserve, 810_000);
+    assert_eq!(decoded.no_reserve, 1_234_568);
+    assert_eq!(decoded.k_constant, market.k_constant);
+    assert_eq!(decoded.winning_outcome, Some(true));
+    assert_eq!(decoded.allowlist_root, Some([7; 32]));
+    assert_eq!(decoded.price_history, market.price_history);
+    assert_eq!(decoded.lp_fees, 1_250);
+    assert_eq!(decoded.version, Market::VERSION);
+
+    match DecodedAccount::decode(&PREDICTION_MARKET_ID, &data).unwrap() {
+        DecodedAccount::Market(decoded) => assert_eq!(decoded.vault, market.vault),
+        other => panic!("decoded as {}", other.name()),
+    }
+}
+
+#[test]
+fn decodes_position_written_before_versioning() {
+    let mut position: Position = common::zeroed();
+    position.market = Pubkey::new_unique();
+    position.user = Pubkey::new_unique();
+    position.yes_stake = 250_000;
+    position.total_stake = 250_000;
+    position.yes_shares = 400_000;
+    let mut data = Vec::new();
+    position.try_serialize(&mut data).unwrap();
+
+    // The unversioned program allocated the account up to `claimed`
+    data.truncate(8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1);
+
+    let decoded: Position = accounts::decode(&data).unwrap();
+    assert_eq!(decoded.version, 0);
+    assert_eq!(decoded.user, position.user);
+    assert_eq!((decoded.yes_stake, decoded.yes_shares), (250_000, 400_000));
+}
+
+#[test]
+fn rejects_layouts_that_need_a_translation() {
+    let v0 = MarketV0 {
+        creator: Pubkey::new_unique(),
+        model: Pubkey::new_unique(),
+        question: "Will the fraud model's accuracy drop below 90% this month?".to_string(),
+        yes_pool: 600,
+        no_pool: 400,
+        total_volume: 1_000,
+        status: MarketStatus::Open,
+        resolution_time: common::NOW,
+        resolved_at: 0,
+        winning_outcome: None,
+        min_stake: 10,
+        created_at: common::NOW - 86_400,
+        bump: 253,
+        amm_enabled: true,
+        virtual_yes_reserve: 3_000,
+        virtual_no_reserve: 1_000,
+        k_constant: 3_000_000,
+        total_yes_shares: 900,
+        total_no_shares: 350,
+    };
+    let mut data = <Market as Discriminator>::DISCRIMINATOR.to_vec();
+    v0.serialize(&mut data).unwrap();
+    data.resize(MarketV0::SPACE, 0);
+
+    assert!(matches!(accounts::decode::<Market>(&data), Err(SdkError::AccountDecode(_))));
+    assert!(matches!(DecodedAccount::decode(&PREDICTION_MARKET_ID, &data), Err(SdkError::AccountDecode(_))));
+
+    // Nor can a current account be shorter than the current layout
+    let data = account_data(&traded_market());
+    let truncated = &data[..data.len() - 100];
+    assert!(matches!(accounts::decode::<Market>(truncated), Err(SdkError::AccountDecode(_))));
+}
+
+#[test]
+fn decodes_agent_account() {
+    let agent = AgentAccount {
+        owner: Pubkey::new_unique(),
+        agent_id: "momentum-7".to_string(),
+        display_name: "Momentum Seven".to_string(),
+        strategy_hash: [3; 32],
+        trading_wallet: Pubkey::new_unique(),
+        verified: true,
+        status: AgentStatus::Suspended,
+        created_at: common::NOW,
+        updated_at: common::NOW + 60,
+        bump: 254,
+        suspended_by_admin: true,
+        version: AgentAccount::VERSION,
+        reserved: [0; 64],
+    };
+    let data = account_data(&agent);
+
+    match DecodedAccount::decode(&AGENT_REGISTRY_ID, &data).unwrap() {
+        DecodedAccount::Agent(decoded) => {
+            assert_eq!(decoded.agent_id, "momentum-7");
+            assert_eq!(decoded.trading_wallet, agent.trading_wallet);
+            assert!(decoded.status == AgentStatus::Suspended);
+            assert!(decoded.suspended_by_admin);
+        }
+        other => panic!("decoded as {}", other.name()),
+    }
+}
+
+#[test]
+fn decodes_price_history() {
+    let mut history: PriceHistory = bytemuck::Zeroable::zeroed();
+    history.market = Pubkey::new_unique();
+    history.sample_interval = 60;
+    history.head = 1;
+    history.len = 1;
+    history.samples[0].timestamp = common::NOW;
+    history.samples[0].yes_price = 6_000;
+    history.samples[0].no_price = 4_000;
+
+    let mut data = <PriceHistory as Discriminator>::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&history));
+
+    let decoded: PriceHistory = accounts::decode(&data).unwrap();
+    assert_eq!(decoded.market, history.market);
+    assert_eq!(decoded.len, 1);
+    assert_eq!(decoded.samples[0].yes_price, 6_000);
+}
+
+#[test]
+fn rejects_foreign_and_short_accounts() {
+    let data = account_data(&traded_market());
+
+    assert!(matches!(
+        DecodedAccount::decode(&INSURANCE_ID, &data),
+        Err(SdkError::UnknownAccount(owner)) if owner == INSURANCE_ID
+    ));
+    assert!(matches!(accounts::decode::<AgentAccount>(&data), Err(SdkError::AccountDecode(_))));
+    assert!(matches!(accounts::decode::<Market>(&data[..4]), Err(SdkError::AccountTooShort)));
+
+    let mut closed = common::market(1_000);
+    closed.status = MarketStatus::Closed;
+    let decoded: Market = accounts::decode(&account_data(&closed)).unwrap();
+    assert!(decoded.status == MarketStatus::Closed);
+}
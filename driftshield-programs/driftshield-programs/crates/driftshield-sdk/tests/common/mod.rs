@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+use anchor_lang::AnchorDeserialize;
+use driftshield_sdk::prediction_market::{Market, MarketStatus, ProtocolConfig, Versioned};
+use driftshield_sdk::Pubkey;
+
+pub const NOW: i64 = 1_700_000_000;
+
+/// A freshly allocated account body: every field zero, as `init` leaves it
+pub fn zeroed<T: Versioned + AnchorDeserialize>() -> T {
+    let data = vec![0u8; T::SPACE - 8];
+    T::deserialize(&mut data.as_slice()).unwrap()
+}
+
+pub fn config() -> ProtocolConfig {
+    let mut config: ProtocolConfig = zeroed();
+    config.admin = Pubkey::new_unique();
+    config.treasury = Pubkey::new_unique();
+    config.referral_vault = Pubkey::new_unique();
+    config.crank_vault = Pubkey::new_unique();
+    config.trading_fee_bps = 100;
+    config.referral_fee_bps = 2000;
+    config.crank_tip = 10_000;
+    config.version = ProtocolConfig::VERSION;
+    config
+}
+
+/// An open AMM market seeded with `liquidity` on each side
+pub fn market(liquidity: u64) -> Market {
+    let mut market: Market = zeroed();
+    market.creator = Pubkey::new_unique();
+    market.model = Pubkey::new_unique();
+    market.question = "Will model accuracy drop below 90.00% by 2024-01-01?".to_string();
+    market.status = MarketStatus::Open;
+    market.amm_enabled = true;
+    market.yes_reserve = liquidity;
+    market.no_reserve = liquidity;
+    market.k_constant = liquidity as u128 * liquidity as u128;
+    market.total_collateral = liquidity;
+    market.created_at = NOW - 86_400;
+    market.resolution_time = NOW + 7 * 86_400;
+    market.last_price_update = NOW - 86_400;
+    market.vault = Pubkey::new_unique();
+    market.version = Market::VERSION;
+    market
+}
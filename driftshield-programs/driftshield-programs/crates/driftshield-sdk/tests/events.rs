@@ -0,0 +1,119 @@
+use anchor_lang::Event;
+use driftshield_sdk::events::{parse_logs, PredictionMarketEvent, ProgramEvent};
+use driftshield_sdk::prediction_market::{BetPlaced, WinningsClaimed};
+use driftshield_sdk::{model_registry, Pubkey, SdkError, MODEL_REGISTRY_ID, PREDICTION_MARKET_ID};
+
+fn program_data<E: Event>(event: &E) -> String {
+    format!("Program data: {}", base64::encode(event.data()))
+}
+
+fn bet(user: Pubkey) -> BetPlaced {
+    BetPlaced {
+        market_key: Pubkey::new_unique(),
+        user,
+        outcome: true,
+        amount: 1_000_000,
+        shares: 1_903_125,
+        fee: 10_000,
+        fee_bps: 100,
+        referrer: None,
+        yes_price: 6_184,
+        no_price: 3_816,
+        timestamp: 1_700_000_000,
+    }
+}
+
+#[test]
+fn parses_events_in_order_across_cpis() {
+    let user = Pubkey::new_unique();
+    let pm = PREDICTION_MARKET_ID.to_string();
+    let registry = MODEL_REGISTRY_ID.to_string();
+    let token = anchor_spl::token::ID.to_string();
+    let aggregator = Pubkey::new_unique().to_string();
+    let claimed = WinningsClaimed {
+        market_key: Pubkey::new_unique(),
+        user,
+        shares: 1_903_125,
+        payout: 1_903_125,
+        timestamp: 1_700_000_100,
+    };
+    let registered = model_registry::ModelRegistered {
+        model_key: Pubkey::new_unique(),
+        owner: user,
+        model_id: "fraud-v3".to_string(),
+        name: "Fraud detector".to_string(),
+        timestamp: 1_700_000_050,
+    };
+
+    // An aggregator invoking the prediction market, which CPIs into the token
+    // program; data logged while another program is on top of the stack must
+    // not be attributed to the prediction market.
+    let logs = vec![
+        "Program ComputeBudget111111111111111111111111111111 invoke [1]".to_string(),
+        "Program ComputeBudget111111111111111111111111111111 success".to_string(),
+        format!("Program {aggregator} invoke [1]"),
+        format!("Program {pm} invoke [2]"),
+        "Program log: Instruction: PlaceBet".to_string(),
+        format!("Program {token} invoke [3]"),
+        "Program log: Instruction: Transfer".to_string(),
+        program_data(&claimed),
+        format!("Program {token} consumed 4645 of 171011 compute units"),
+        format!("Program {token} success"),
+        program_data(&bet(user)),
+        format!("Program {pm} consumed 58210 of 200000 compute units"),
+        format!("Program {pm} success"),
+        program_data(&bet(user)),
+        format!("Program {aggregator} success"),
+        format!("Program {registry} invoke [1]"),
+        "Program log: Instruction: RegisterModel".to_string(),
+        format!("Program {} invoke [2]", anchor_lang::system_program::ID),
+        format!("Program {} success", anchor_lang::system_program::ID),
+        program_data(&registered),
+        format!("Program {registry} success"),
+        format!("Program {pm} invoke [1]"),
+        program_data(&claimed),
+        format!("Program {pm} success"),
+    ];
+
+    let events = parse_logs(&logs).unwrap();
+    let names: Vec<_> = events.iter().map(ProgramEvent::name).collect();
+    assert_eq!(names, ["BetPlaced", "ModelRegistered", "WinningsClaimed"]);
+
+    match &events[0] {
+        ProgramEvent::PredictionMarket(PredictionMarketEvent::BetPlaced(event)) => {
+            assert_eq!(event.user, user);
+            assert_eq!(event.shares, 1_903_125);
+            assert_eq!(event.yes_price, 6_184);
+        }
+        _ => panic!("expected BetPlaced"),
+    }
+    assert_eq!(events[1].program_id(), MODEL_REGISTRY_ID);
+}
+
+#[test]
+fn skips_data_outside_any_invocation_and_unknown_events() {
+    let logs = vec![
+        program_data(&bet(Pubkey::new_unique())),
+        format!("Program {PREDICTION_MARKET_ID} invoke [1]"),
+        format!("Program data: {}", base64::encode([9u8; 16])),
+        format!("Program {PREDICTION_MARKET_ID} success"),
+    ];
+    assert!(parse_logs(&logs).unwrap().is_empty());
+}
+
+#[test]
+fn reports_malformed_event_data() {
+    let logs = vec![
+        format!("Program {PREDICTION_MARKET_ID} invoke [1]"),
+        "Program data: not base64!".to_string(),
+    ];
+    assert!(matches!(parse_logs(&logs), Err(SdkError::EventDecode(_))));
+
+    let mut truncated = bet(Pubkey::new_unique()).data();
+    truncated.truncate(20);
+    let logs = vec![
+        format!("Program {PREDICTION_MARKET_ID} invoke [1]"),
+        format!("Program data: {}", base64::encode(truncated)),
+    ];
+    assert!(matches!(parse_logs(&logs), Err(SdkError::EventDecode(_))));
+}
@@ -0,0 +1,313 @@
+mod common;
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use driftshield_sdk::instructions::{self, CreateMarketAccounts, MarketTerms, ReceiptMetrics, Trader};
+use driftshield_sdk::prediction_market::{self, Comparator, DriftCondition, DriftMetric};
+use driftshield_sdk::{insurance, model_registry, pda};
+use driftshield_sdk::{Instruction, Pubkey, INSURANCE_ID, MODEL_REGISTRY_ID, PREDICTION_MARKET_ID};
+
+/// Decode instruction data back into the program's generated args struct
+fn args<T: Discriminator + AnchorDeserialize>(ix: &Instruction) -> T {
+    assert_eq!(ix.data[..8], T::DISCRIMINATOR, "wrong instruction discriminator");
+    T::try_from_slice(&ix.data[8..]).expect("instruction data does not decode")
+}
+
+fn signers(ix: &Instruction) -> Vec<Pubkey> {
+    ix.accounts.iter().filter(|meta| meta.is_signer).map(|meta| meta.pubkey).collect()
+}
+
+fn writable(ix: &Instruction, key: &Pubkey) -> bool {
+    ix.accounts.iter().any(|meta| meta.pubkey == *key && meta.is_writable)
+}
+
+fn has(ix: &Instruction, key: &Pubkey) -> bool {
+    ix.accounts.iter().any(|meta| meta.pubkey == *key)
+}
+
+#[test]
+fn register_model_round_trips() {
+    let owner = Pubkey::new_unique();
+    let ix = instructions::register_model(
+        owner,
+        "fraud-v3".to_string(),
+        "Fraud detector".to_string(),
+        "classifier".to_string(),
+        "xgboost".to_string(),
+        9_420,
+    );
+    assert_eq!(ix.program_id, MODEL_REGISTRY_ID);
+    let data: model_registry::instruction::RegisterModel = args(&ix);
+    assert_eq!(data.model_id, "fraud-v3");
+    assert_eq!(data.name, "Fraud detector");
+    assert_eq!(data.model_type, "classifier");
+    assert_eq!(data.framework, "xgboost");
+    assert_eq!(data.baseline_accuracy, 9_420);
+    assert!(writable(&ix, &pda::model(&owner, "fraud-v3").0));
+    assert_eq!(signers(&ix), [owner]);
+}
+
+#[test]
+fn submit_monitoring_receipt_round_trips() {
+    let (owner, model, receipt) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let metrics = ReceiptMetrics {
+        accuracy: 9_100,
+        precision: 8_800,
+        recall: 9_300,
+        f1_score: 9_000,
+        drift_score: 1_200,
+        metadata_uri: "shdw://receipts/1".to_string(),
+    };
+    let ix = instructions::submit_monitoring_receipt(owner, model, receipt, metrics);
+    let data: model_registry::instruction::SubmitMonitoringReceipt = args(&ix);
+    assert_eq!(
+        (data.accuracy, data.precision, data.recall, data.f1_score, data.drift_score),
+        (9_100, 8_800, 9_300, 9_000, 1_200)
+    );
+    assert_eq!(data.metadata_uri, "shdw://receipts/1");
+    assert_eq!(signers(&ix), [receipt, owner]);
+}
+
+#[test]
+fn insurance_builders_round_trip() {
+    let (owner, model, token_account, vault) =
+        (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let policy = pda::policy(&owner, &model).0;
+
+    let ix = instructions::purchase_policy(owner, model, token_account, vault, 5_000_000, 250_000, 9_000, 30);
+    assert_eq!(ix.program_id, INSURANCE_ID);
+    let data: insurance::instruction::PurchasePolicy = args(&ix);
+    assert_eq!(data.model_pubkey, model);
+    assert_eq!(
+        (data.coverage_amount, data.premium, data.accuracy_threshold, data.duration_days),
+        (5_000_000, 250_000, 9_000, 30)
+    );
+    assert!(writable(&ix, &policy));
+
+    let ix = instructions::file_claim(owner, model, token_account, vault, 8_500);
+    let data: insurance::instruction::FileClaim = args(&ix);
+    assert_eq!(data.current_accuracy, 8_500);
+    assert_eq!(signers(&ix), [owner]);
+
+    let ix = instructions::cancel_policy(owner, model, token_account, vault);
+    let _: insurance::instruction::CancelPolicy = args(&ix);
+    assert!(writable(&ix, &policy));
+
+    // Without a tip the optional accounts are filled with the program id
+    let cranker = Pubkey::new_unique();
+    let ix = instructions::expire_policy(cranker, policy, None);
+    let _: insurance::instruction::ExpirePolicy = args(&ix);
+    assert!(!has(&ix, &vault));
+    assert_eq!(ix.accounts.iter().filter(|meta| meta.pubkey == INSURANCE_ID).count(), 2);
+
+    let ix = instructions::expire_policy(cranker, policy, Some((vault, token_account)));
+    assert!(writable(&ix, &vault) && writable(&ix, &token_account));
+    assert_eq!(signers(&ix), [cranker]);
+}
+
+fn create_accounts(creator: Pubkey) -> CreateMarketAccounts {
+    CreateMarketAccounts {
+        creator,
+        model: Pubkey::new_unique(),
+        creator_token_account: Pubkey::new_unique(),
+        market_vault: Pubkey::new_unique(),
+        bond_vault: Pubkey::new_unique(),
+    }
+}
+
+fn terms() -> MarketTerms {
+    MarketTerms {
+        resolution_time: common::NOW + 86_400,
+        min_stake: 1_000,
+        initial_liquidity: 1_000_000,
+        allowlist_root: Some([5; 32]),
+    }
+}
+
+#[test]
+fn create_market_round_trips() {
+    let config = common::config();
+    let accounts = create_accounts(Pubkey::new_unique());
+    let market = pda::market(&accounts.creator, &accounts.model).0;
+
+    let ix = instructions::create_market(&config, &accounts, "Will it drift?".to_string(), terms());
+    assert_eq!(ix.program_id, PREDICTION_MARKET_ID);
+    let data: prediction_market::instruction::CreateMarket = args(&ix);
+    assert_eq!(data.model_pubkey, accounts.model);
+    assert_eq!(data.question, "Will it drift?");
+    assert_eq!(data.resolution_time, common::NOW + 86_400);
+    assert_eq!((data.min_stake, data.initial_liquidity), (1_000, 1_000_000));
+    assert_eq!(data.allowlist_root, Some([5; 32]));
+    assert!(writable(&ix, &market));
+    assert!(writable(&ix, &config.treasury));
+    assert_eq!(signers(&ix), [accounts.creator]);
+
+    let condition = DriftCondition {
+        metric: DriftMetric::Accuracy,
+        comparator: Comparator::Below,
+        threshold: 9_000,
+    };
+    let ix = instructions::create_drift_market(&config, &accounts, condition, terms());
+    let data: prediction_market::instruction::CreateDriftMarket = args(&ix);
+    assert_eq!(data.model_pubkey, accounts.model);
+    assert_eq!(data.condition, condition);
+    assert_eq!(data.initial_liquidity, 1_000_000);
+    assert_eq!(ix.accounts[0].pubkey, accounts.model);
+    assert!(writable(&ix, &market));
+}
+
+#[test]
+fn trades_round_trip() {
+    let config = common::config();
+    let market_key = Pubkey::new_unique();
+    let mut market = common::market(1_000_000);
+    let trader = Trader::new(Pubkey::new_unique(), Pubkey::new_unique());
+    let position = pda::position(&market_key, &trader.user).0;
+
+    let ix = instructions::place_bet(&config, market_key, &market, &trader, true, 250_000, vec![[1; 32], [2; 32]]);
+    let data: prediction_market::instruction::PlaceBet = args(&ix);
+    assert!(data.outcome);
+    assert_eq!(data.amount, 250_000);
+    assert_eq!(data.allowlist_proof, vec![[1; 32], [2; 32]]);
+    assert!(writable(&ix, &position));
+    assert!(writable(&ix, &market.vault));
+    assert!(writable(&ix, &config.treasury));
+    assert!(!has(&ix, &config.referral_vault));
+    assert_eq!(signers(&ix), [trader.user]);
+
+    let ix = instructions::sell_shares(&config, market_key, &market, &trader, false, 99_000);
+    let data: prediction_market::instruction::SellShares = args(&ix);
+    assert!(!data.outcome);
+    assert_eq!(data.shares, 99_000);
+    assert!(writable(&ix, &position));
+
+    // Session trades, referrals, price history and reward pools all add accounts
+    let owner = Pubkey::new_unique();
+    let session_vault = Pubkey::new_unique();
+    let referrer = Pubkey::new_unique();
+    let session_trader = Trader {
+        user: Pubkey::new_unique(),
+        session_owner: Some(owner),
+        session_vault: Some(session_vault),
+        user_token_account: Pubkey::new_unique(),
+        referrer: Some(referrer),
+    };
+    market.price_history = Some(Pubkey::new_unique());
+    market.reward_pool = Some(Pubkey::new_unique());
+
+    let ix = instructions::place_bet(&config, market_key, &market, &session_trader, false, 10_000, vec![]);
+    assert!(writable(&ix, &pda::position(&market_key, &owner).0));
+    assert!(has(&ix, &pda::session(&owner, &session_trader.user).0));
+    assert!(writable(&ix, &session_vault));
+    assert!(!has(&ix, &session_trader.user_token_account));
+    assert!(writable(&ix, &pda::referral(&referrer).0));
+    assert!(writable(&ix, &config.referral_vault));
+    assert!(writable(&ix, &market.price_history.unwrap()));
+    assert!(writable(&ix, &market.reward_pool.unwrap()));
+    assert!(writable(&ix, &pda::reward_stake(&market_key, &owner).0));
+    assert_eq!(signers(&ix), [session_trader.user]);
+
+    let ix = instructions::sell_shares(&config, market_key, &market, &session_trader, false, 5_000);
+    assert!(writable(&ix, &session_trader.user_token_account));
+    assert!(!has(&ix, &session_vault));
+}
+
+#[test]
+fn settlement_round_trips() {
+    let market_key = Pubkey::new_unique();
+    let mut market = common::market(1_000_000);
+    market.parent_market = Some(Pubkey::new_unique());
+    let trader = Trader::new(Pubkey::new_unique(), Pubkey::new_unique());
+
+    let authority = Pubkey::new_unique();
+    let ix = instructions::resolve_market(authority, market_key, &market, false);
+    let data: prediction_market::instruction::ResolveMarket = args(&ix);
+    assert!(!data.outcome);
+    assert!(has(&ix, &market.parent_market.unwrap()));
+    assert_eq!(signers(&ix), [authority]);
+
+    let (attester, receipt) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let ix = instructions::settle_drift_market(attester, market_key, &market, receipt);
+    let _: prediction_market::instruction::SettleDriftMarket = args(&ix);
+    assert!(has(&ix, &receipt) && has(&ix, &pda::config().0));
+    assert_eq!(signers(&ix), [attester]);
+
+    let ix = instructions::claim_winnings(market_key, &market, &trader);
+    let _: prediction_market::instruction::ClaimWinnings = args(&ix);
+    assert!(writable(&ix, &pda::position(&market_key, &trader.user).0));
+    assert!(writable(&ix, &trader.user_token_account));
+
+    let ix = instructions::claim_refund(market_key, &market, trader.user, trader.user_token_account);
+    let _: prediction_market::instruction::ClaimRefund = args(&ix);
+    assert!(writable(&ix, &market.vault));
+}
+
+#[test]
+fn admin_builders_round_trip() {
+    let admin = Pubkey::new_unique();
+    let crank_vault = Pubkey::new_unique();
+
+    let ix = instructions::set_crank_tip(admin, crank_vault, 5_000);
+    let data: prediction_market::instruction::SetCrankTip = args(&ix);
+    assert_eq!(data.crank_tip, 5_000);
+    assert!(has(&ix, &crank_vault));
+    assert_eq!(signers(&ix), [admin]);
+
+    let attester = Pubkey::new_unique();
+    let ix = instructions::set_drift_attester(admin, attester);
+    let data: prediction_market::instruction::SetDriftAttester = args(&ix);
+    assert_eq!(data.drift_attester, attester);
+    assert!(writable(&ix, &pda::config().0));
+
+    let ix = instructions::set_exposure_floors(admin, 1_000_000, 2_500, 500, 10_000_000);
+    let data: prediction_market::instruction::SetExposureFloors = args(&ix);
+    assert_eq!(
+        (data.max_position_stake, data.max_wallet_oi_bps, data.max_price_impact_bps, data.wallet_oi_floor),
+        (1_000_000, 2_500, 500, 10_000_000)
+    );
+}
+
+#[test]
+fn cranks_request_tips_only_when_enabled() {
+    let mut config = common::config();
+    let market_key = Pubkey::new_unique();
+    let market = common::market(1_000_000);
+    let (cranker, cranker_token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let ix = instructions::close_market(&config, cranker, Some(cranker_token_account), market_key);
+    let _: prediction_market::instruction::CloseMarket = args(&ix);
+    assert!(writable(&ix, &market_key));
+    assert!(writable(&ix, &config.crank_vault) && writable(&ix, &cranker_token_account));
+    assert_eq!(signers(&ix), [cranker]);
+
+    let ix = instructions::finalize_drift_market(&config, cranker, None, market_key, &market);
+    let _: prediction_market::instruction::FinalizeDriftMarket = args(&ix);
+    assert!(!has(&ix, &config.crank_vault));
+
+    let ix = instructions::void_abandoned_market(&config, cranker, Some(cranker_token_account), market_key, &market);
+    let _: prediction_market::instruction::VoidAbandonedMarket = args(&ix);
+    assert!(writable(&ix, &market.bond_vault) && writable(&ix, &config.treasury));
+    assert!(writable(&ix, &cranker_token_account));
+
+    config.crank_tip = 0;
+    let ix = instructions::close_market(&config, cranker, Some(cranker_token_account), market_key);
+    assert!(!has(&ix, &config.crank_vault) && !has(&ix, &cranker_token_account));
+}
+
+#[test]
+fn quotes_round_trip() {
+    let market = Pubkey::new_unique();
+    let position = pda::position(&market, &Pubkey::new_unique()).0;
+
+    let ix = instructions::quote_buy(market, None, true, 1_000);
+    let data: prediction_market::instruction::QuoteBuy = args(&ix);
+    assert!(data.outcome);
+    assert_eq!(data.amount, 1_000);
+    assert!(signers(&ix).is_empty());
+
+    let ix = instructions::quote_sell(market, Some(position), false, 2_000);
+    let data: prediction_market::instruction::QuoteSell = args(&ix);
+    assert!(!data.outcome);
+    assert_eq!(data.shares, 2_000);
+    assert!(has(&ix, &position) && !writable(&ix, &position));
+    assert!(!writable(&ix, &market));
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use driftshield_sdk::prediction_market::{self, calculate_buy, calculate_sell, calculate_trading_fee, Market, Position};
+use driftshield_sdk::{pricing, SdkError};
+
+fn skewed_market() -> Market {
+    let mut market = common::market(1_000_000);
+    let (shares, yes_reserve, no_reserve) = calculate_buy(&market, true, 400_000).unwrap();
+    market.yes_reserve = yes_reserve;
+    market.no_reserve = no_reserve;
+    market.total_yes_shares = shares;
+    market.total_collateral += 400_000;
+    market
+}
+
+/// A position holding `yes_shares` and `no_shares`
+fn holder(yes_shares: u64, no_shares: u64) -> Position {
+    let mut position: Position = common::zeroed();
+    position.yes_shares = yes_shares;
+    position.no_shares = no_shares;
+    position.yes_stake = yes_shares / 2;
+    position.no_stake = no_shares / 2;
+    position.total_stake = position.yes_stake + position.no_stake;
+    position
+}
+
+/// Whether `result` failed with the program error `code`
+fn fails<T>(result: driftshield_sdk::Result<T>, code: &str) -> bool {
+    matches!(result, Err(SdkError::Program(msg)) if msg.contains(code))
+}
+
+#[test]
+fn quote_buy_matches_calculate_buy() {
+    let config = common::config();
+    for market in [common::market(1_000_000), skewed_market()] {
+        for (outcome, amount) in [(true, 1), (true, 10_000), (false, 10_000), (false, 777_777), (true, 5_000_000)] {
+            let quote = pricing::quote_buy(&config, &market, None, outcome, amount, common::NOW).unwrap();
+
+            let (fee, _, _) = calculate_trading_fee(&config, config.trading_fee_bps, amount, false).unwrap();
+            let (shares, yes_reserve, no_reserve) = calculate_buy(&market, outcome, amount - fee).unwrap();
+            let (yes_after, no_after) = prediction_market::prices_from_reserves(yes_reserve, no_reserve).unwrap();
+
+            assert_eq!(quote.fee, fee);
+            assert_eq!(quote.fee_bps, config.trading_fee_bps);
+            assert_eq!(quote.net_amount, amount - fee);
+            assert_eq!(quote.shares, shares);
+            assert_eq!((quote.yes_price_after, quote.no_price_after), (yes_after, no_after));
+            // A buy never prices shares above one unit of collateral
+            assert!(quote.shares >= quote.net_amount);
+        }
+    }
+}
+
+#[test]
+fn quote_sell_matches_calculate_sell() {
+    let config = common::config();
+    let mut market = skewed_market();
+    market.total_no_shares = 50_000;
+    let position = holder(market.total_yes_shares, market.total_no_shares);
+    for (outcome, shares) in [(true, 1), (true, 50_000), (true, market.total_yes_shares), (false, 50_000)] {
+        let quote = pricing::quote_sell(&config, &market, Some(&position), outcome, shares, common::NOW).unwrap();
+
+        let (gross, yes_reserve, no_reserve) = calculate_sell(&market, outcome, shares).unwrap();
+        let (fee, _, _) = calculate_trading_fee(&config, config.trading_fee_bps, gross, false).unwrap();
+        let (yes_after, no_after) = prediction_market::prices_from_reserves(yes_reserve, no_reserve).unwrap();
+
+        assert_eq!(quote.gross_proceeds, gross);
+        assert_eq!(quote.fee, fee);
+        assert_eq!(quote.proceeds, gross - fee);
+        assert_eq!((quote.yes_price_after, quote.no_price_after), (yes_after, no_after));
+    }
+}
+
+#[test]
+fn buy_then_sell_round_trip_favors_the_pool() {
+    let config = common::config();
+    let market = skewed_market();
+    let amount = 250_000;
+
+    let (fee, _, _) = calculate_trading_fee(&config, config.trading_fee_bps, amount, false).unwrap();
+    let (shares, yes_reserve, no_reserve) = calculate_buy(&market, false, amount - fee).unwrap();
+    let mut after = market.clone();
+    after.yes_reserve = yes_reserve;
+    after.no_reserve = no_reserve;
+
+    let (gross, yes_reserve, no_reserve) = calculate_sell(&after, false, shares).unwrap();
+    assert!(gross <= amount - fee);
+    assert!(yes_reserve as u128 * no_reserve as u128 >= market.k_constant);
+}
+
+#[test]
+fn quotes_include_the_dynamic_fee() {
+    let config = common::config();
+    let mut market = common::market(1_000_000);
+    market.fee_ramp_bps = 200;
+    market.fee_ramp_window = 86_400;
+    market.max_dynamic_fee_bps = 300;
+    let now = market.resolution_time - 43_200;
+
+    let fee_bps = pricing::fee_bps(&config, &market, now).unwrap();
+    assert_eq!(fee_bps, config.trading_fee_bps + 100);
+
+    let quote = pricing::quote_buy(&config, &market, None, true, 100_000, now).unwrap();
+    let (fee, lp_fee, _) = calculate_trading_fee(&config, fee_bps, 100_000, false).unwrap();
+    assert_eq!((quote.fee_bps, quote.fee), (fee_bps, fee));
+    assert_eq!(lp_fee, 1_000);
+    assert_eq!(quote.shares, calculate_buy(&market, true, 100_000 - fee).unwrap().0);
+}
+
+#[test]
+fn quotes_reject_closed_markets() {
+    let config = common::config();
+    let mut market = common::market(1_000_000);
+    market.status = prediction_market::MarketStatus::Closed;
+    let position = holder(1_000, 0);
+    assert!(fails(pricing::quote_buy(&config, &market, None, true, 1_000, common::NOW), "MarketClosed"));
+    assert!(fails(pricing::quote_sell(&config, &market, Some(&position), true, 1_000, common::NOW), "MarketClosed"));
+}
+
+#[test]
+fn quotes_run_the_trade_checks() {
+    let config = common::config();
+    let quote_buy = |market: &Market, position: Option<&Position>, amount, now| {
+        pricing::quote_buy(&config, market, position, true, amount, now)
+    };
+
+    let mut market = skewed_market();
+    assert!(fails(quote_buy(&market, None, 1_000, market.resolution_time), "MarketExpired"));
+
+    market.min_stake = 5_000;
+    assert!(fails(quote_buy(&market, None, 1_000, common::NOW), "StakeTooLow"));
+    market.min_stake = 0;
+
+    market.halted_until = common::NOW + 60;
+    assert!(fails(quote_buy(&market, None, 1_000, common::NOW), "TradingHalted"));
+    assert!(quote_buy(&market, None, 1_000, common::NOW + 60).is_ok());
+    market.halted_until = 0;
+
+    // Limits count the trader's existing stake
+    market.max_position_stake = 100_000;
+    let mut position = holder(0, 0);
+    position.total_stake = 95_000;
+    assert!(quote_buy(&market, None, 10_000, common::NOW).is_ok());
+    assert!(fails(quote_buy(&market, Some(&position), 10_000, common::NOW), "PositionStakeLimitExceeded"));
+    market.max_position_stake = 0;
+
+    market.max_price_impact_bps = 100;
+    assert!(quote_buy(&market, None, 1_000, common::NOW).is_ok());
+    assert!(fails(quote_buy(&market, None, 500_000, common::NOW), "PriceImpactTooHigh"));
+    market.max_price_impact_bps = 0;
+
+    market.legacy = true;
+    assert!(fails(quote_buy(&market, None, 1_000, common::NOW), "LegacyMarket"));
+    market.legacy = false;
+
+    // A sell needs the shares in the position being quoted
+    assert!(fails(pricing::quote_sell(&config, &market, None, true, 1_000, common::NOW), "InsufficientShares"));
+    let position = holder(500, 0);
+    let quote = pricing::quote_sell(&config, &market, Some(&position), true, 1_000, common::NOW);
+    assert!(fails(quote, "InsufficientShares"));
+}
+
+#[test]
+fn quote_claim_requires_a_resolved_outcome() {
+    let mut market = common::market(1_000_000);
+    let mut position: prediction_market::Position = common::zeroed();
+    position.yes_shares = 500_000;
+    position.yes_stake = 300_000;
+    let rejects = |market: &Market, code: &str| {
+        matches!(pricing::quote_claim(market, &position), Err(SdkError::Program(msg)) if msg.contains(code))
+    };
+
+    assert!(rejects(&market, "MarketNotResolved"));
+    market.status = prediction_market::MarketStatus::Resolved;
+    assert!(rejects(&market, "NoWinningOutcome"));
+
+    market.winning_outcome = Some(true);
+    let quote = pricing::quote_claim(&market, &position).unwrap();
+    assert!(quote.winning_outcome);
+    assert_eq!((quote.winning_shares, quote.payout), (500_000, 500_000));
+    assert!(!quote.already_claimed);
+}
@@ -0,0 +1,64 @@
+//! Pricing models.
+//!
+//! A curve decides how many shares a buy mints and how much collateral a sell
+//! releases, given the market's reserves. Everything else (fees, limits,
+//! accounting, solvency) is the on-chain code, so a candidate curve can be
+//! judged against the live one on the same trades.
+
+use anchor_lang::prelude::Result;
+use driftshield_sdk::prediction_market::{self, Market};
+
+pub trait Curve {
+    fn name(&self) -> String;
+
+    /// Whether the market prices from its reserves (`Market::amm_enabled`)
+    fn amm_enabled(&self) -> bool {
+        true
+    }
+
+    /// (shares, new_yes_reserve, new_no_reserve) for a buy of `net_amount`
+    fn buy(&self, market: &Market, outcome: bool, net_amount: u64) -> Result<(u64, u64, u64)>;
+
+    /// (collateral_out, new_yes_reserve, new_no_reserve) for a sell of `shares`
+    fn sell(&self, market: &Market, outcome: bool, shares: u64) -> Result<(u64, u64, u64)>;
+}
+
+/// The on-chain constant-product AMM over complete sets
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConstantProduct;
+
+impl Curve for ConstantProduct {
+    fn name(&self) -> String {
+        "constant-product".to_string()
+    }
+
+    fn buy(&self, market: &Market, outcome: bool, net_amount: u64) -> Result<(u64, u64, u64)> {
+        prediction_market::calculate_buy(market, outcome, net_amount)
+    }
+
+    fn sell(&self, market: &Market, outcome: bool, shares: u64) -> Result<(u64, u64, u64)> {
+        prediction_market::calculate_sell(market, outcome, shares)
+    }
+}
+
+/// The on-chain 1:1 fallback for markets with the AMM disabled
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PeerToPeer;
+
+impl Curve for PeerToPeer {
+    fn name(&self) -> String {
+        "peer-to-peer".to_string()
+    }
+
+    fn amm_enabled(&self) -> bool {
+        false
+    }
+
+    fn buy(&self, market: &Market, _outcome: bool, net_amount: u64) -> Result<(u64, u64, u64)> {
+        Ok((net_amount, market.yes_reserve, market.no_reserve))
+    }
+
+    fn sell(&self, market: &Market, _outcome: bool, shares: u64) -> Result<(u64, u64, u64)> {
+        Ok((shares, market.yes_reserve, market.no_reserve))
+    }
+}
@@ -0,0 +1,43 @@
+#![allow(clippy::result_large_err)]
+
+//! Offline simulator and backtester for DriftShield prediction markets.
+//!
+//! A [`Simulator`] holds a real `prediction_market::Market` and replays a
+//! [`Scenario`] against it, running the same fee, AMM, exposure-limit,
+//! circuit-breaker and solvency code `place_bet`, `sell_shares`,
+//! `close_market`, `resolve_market` and `cancel_market` run on-chain. Token
+//! transfers are replaced by a simulated vault and ledger.
+//!
+//! - `scenario`: scripted trade sequences, or ones recorded from program events
+//! - `curve`: pricing models; [`ConstantProduct`] is the on-chain AMM
+//! - `sim`: the simulator and its [`Setup`] parameters
+//! - `report`: LP and trader PnL, price paths, solvency and rounding dust,
+//!   plus side-by-side comparisons of several setups
+//!
+//! ```
+//! use driftshield_sim::{compare, Scenario, Setup};
+//!
+//! let scenario = Scenario::new()
+//!     .buy(100, "alice", true, 50_000_000)
+//!     .buy(200, "bob", false, 20_000_000)
+//!     .sell(300, "alice", true, 10_000_000)
+//!     .resolve(86_400, true);
+//!
+//! let thin = Setup { initial_liquidity: 100_000_000, resolution_time: 86_400, ..Setup::default() };
+//! let deep = Setup { initial_liquidity: 1_000_000_000, ..thin.clone() };
+//! let comparison = compare(&scenario, &[("thin", thin), ("deep", deep)]).unwrap();
+//! println!("{}", comparison);
+//! ```
+
+pub mod curve;
+pub mod report;
+pub mod scenario;
+pub mod sim;
+
+pub use curve::{ConstantProduct, Curve, PeerToPeer};
+pub use report::{compare, Comparison, Report};
+pub use scenario::{Action, Scenario, Step};
+pub use sim::{Setup, Simulator};
+
+pub use driftshield_sdk::prediction_market;
+pub use driftshield_sdk::{Result, SdkError};
@@ -0,0 +1,210 @@
+//! Simulation results.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use driftshield_sdk::prediction_market::MarketStatus;
+
+use crate::scenario::{Scenario, Step};
+use crate::sim::{Setup, Simulator};
+
+/// A trade that went through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fill {
+    /// Collateral paid for a buy (fee included) or received from a sell (fee deducted)
+    pub collateral: u64,
+    pub shares: u64,
+    pub fee: u64,
+    /// Dynamic part of `fee`, kept in the vault for the LP
+    pub lp_fee: u64,
+    pub fee_bps: u16,
+}
+
+#[derive(Clone, Debug)]
+pub struct StepResult {
+    pub step: Step,
+    /// Fill for trades, `None` for resolution and cancellation, or the program error name
+    pub result: Result<Option<Fill>, String>,
+}
+
+/// Market state after a step (the first point is the initial state)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PricePoint {
+    pub time: i64,
+    pub yes_price: u64,
+    pub no_price: u64,
+    /// Fee rate a trade would pay at `time`
+    pub fee_bps: u16,
+    pub vault: u64,
+    /// Vault balance minus the maximum payout owed; negative means insolvent
+    pub headroom: i128,
+}
+
+/// Collateral flows of one trader
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TraderPnl {
+    pub paid: u64,
+    pub received: u64,
+    pub fees: u64,
+    /// Winnings or refund; the mark-to-market value if the market is unresolved
+    pub payout: u64,
+}
+
+impl TraderPnl {
+    pub fn pnl(&self) -> i128 {
+        self.received as i128 + self.payout as i128 - self.paid as i128
+    }
+}
+
+#[derive(Clone)]
+pub struct Report {
+    pub curve: String,
+    pub status: MarketStatus,
+    /// Whether every position and the LP were paid out
+    pub settled: bool,
+    pub steps: Vec<StepResult>,
+    pub prices: Vec<PricePoint>,
+    pub traders: BTreeMap<String, TraderPnl>,
+    pub lp_deposited: u64,
+    /// Liquidity returned; the mark-to-market value of the reserves if unresolved
+    pub lp_withdrawn: u64,
+    /// Fees paid out to the protocol; dynamic fees are part of `lp_withdrawn`
+    pub fees: u64,
+    pub volume: u64,
+    pub min_headroom: i128,
+    /// Collateral left in the vault after every payout
+    pub dust: u64,
+    /// Payouts that would fail on-chain
+    pub settlement_errors: Vec<String>,
+}
+
+impl Report {
+    pub fn lp_pnl(&self) -> i128 {
+        self.lp_withdrawn as i128 - self.lp_deposited as i128
+    }
+
+    pub fn trader_pnl(&self) -> i128 {
+        self.traders.values().map(TraderPnl::pnl).sum()
+    }
+
+    pub fn filled(&self) -> usize {
+        self.steps.iter().filter(|s| s.result.is_ok()).count()
+    }
+
+    pub fn rejected(&self) -> usize {
+        self.steps.len() - self.filled()
+    }
+
+    /// Rejection counts by program error
+    pub fn rejections(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for step in &self.steps {
+            if let Err(err) = &step.result {
+                *counts.entry(err.as_str()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Collateral created or destroyed by the run: traders, LP, fees and dust
+    /// sum to zero for a settled market, so anything else is an accounting bug
+    pub fn unaccounted(&self) -> i128 {
+        if !self.settled {
+            return 0;
+        }
+        self.trader_pnl() + self.lp_pnl() + self.fees as i128 + self.dust as i128
+    }
+
+    pub fn final_price(&self) -> (u64, u64) {
+        self.prices.last().map_or((5000, 5000), |p| (p.yes_price, p.no_price))
+    }
+}
+
+fn status_name(status: MarketStatus) -> &'static str {
+    match status {
+        MarketStatus::Open => "Open",
+        MarketStatus::Resolved => "Resolved",
+        MarketStatus::Cancelled => "Cancelled",
+        MarketStatus::Closed => "Closed",
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (yes_price, no_price) = self.final_price();
+        writeln!(f, "Curve:          {}", self.curve)?;
+        writeln!(f, "Status:         {}{}", status_name(self.status), if self.settled { "" } else { " (marked to spot)" })?;
+        writeln!(f, "Steps:          {} filled, {} rejected", self.filled(), self.rejected())?;
+        for (error, count) in self.rejections() {
+            writeln!(f, "  {:<28} {}", error, count)?;
+        }
+        writeln!(f, "Final price:    YES {} / NO {} bps", yes_price, no_price)?;
+        writeln!(f, "Volume:         {}", self.volume)?;
+        writeln!(f, "Fees:           {}", self.fees)?;
+        writeln!(f, "LP PnL:         {} ({} in, {} out)", self.lp_pnl(), self.lp_deposited, self.lp_withdrawn)?;
+        writeln!(f, "Trader PnL:     {}", self.trader_pnl())?;
+        for (trader, pnl) in &self.traders {
+            writeln!(
+                f,
+                "  {:<44} {:>16} (paid {}, received {}, payout {}, fees {})",
+                trader,
+                pnl.pnl(),
+                pnl.paid,
+                pnl.received,
+                pnl.payout,
+                pnl.fees
+            )?;
+        }
+        writeln!(f, "Min headroom:   {}", self.min_headroom)?;
+        writeln!(f, "Dust:           {}", self.dust)?;
+        writeln!(f, "Unaccounted:    {}", self.unaccounted())?;
+        for error in &self.settlement_errors {
+            writeln!(f, "Settlement error: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reports for several setups run over the same scenario
+#[derive(Clone)]
+pub struct Comparison {
+    pub runs: Vec<(String, Report)>,
+}
+
+/// Run `scenario` once per labelled setup
+pub fn compare(scenario: &Scenario, setups: &[(&str, Setup)]) -> crate::Result<Comparison> {
+    let mut runs = Vec::with_capacity(setups.len());
+    for (label, setup) in setups {
+        let mut sim = Simulator::new(setup.clone())?;
+        sim.run(scenario);
+        runs.push((label.to_string(), sim.finish()));
+    }
+    Ok(Comparison { runs })
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:<18} {:>6} {:>8} {:>9} {:>14} {:>14} {:>12} {:>12} {:>6}",
+            "setup", "curve", "filled", "rejected", "final YES", "LP PnL", "trader PnL", "fees", "min headroom", "dust"
+        )?;
+        for (label, report) in &self.runs {
+            writeln!(
+                f,
+                "{:<16} {:<18} {:>6} {:>8} {:>9} {:>14} {:>14} {:>12} {:>12} {:>6}",
+                label,
+                report.curve,
+                report.filled(),
+                report.rejected(),
+                report.final_price().0,
+                report.lp_pnl(),
+                report.trader_pnl(),
+                report.fees,
+                report.min_headroom,
+                report.dust
+            )?;
+        }
+        Ok(())
+    }
+}
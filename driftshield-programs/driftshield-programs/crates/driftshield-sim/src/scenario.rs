@@ -0,0 +1,105 @@
+//! Trade sequences to replay.
+
+use driftshield_sdk::events::PredictionMarketEvent;
+use driftshield_sdk::Pubkey;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// `place_bet` of `amount` collateral, fee included
+    Buy { trader: String, outcome: bool, amount: u64 },
+    /// `sell_shares` of `shares`
+    Sell { trader: String, outcome: bool, shares: u64 },
+    /// `close_market` crank
+    Close,
+    /// `resolve_market` by the creator
+    Resolve { outcome: bool },
+    /// `cancel_market`: refund positions and return the rest to the LP
+    Cancel,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// Unix time the instruction executes at
+    pub time: i64,
+    pub action: Action,
+}
+
+/// An ordered list of steps
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scenario {
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(mut self, time: i64, action: Action) -> Self {
+        self.steps.push(Step { time, action });
+        self
+    }
+
+    pub fn buy(self, time: i64, trader: &str, outcome: bool, amount: u64) -> Self {
+        self.push(time, Action::Buy { trader: trader.to_string(), outcome, amount })
+    }
+
+    pub fn sell(self, time: i64, trader: &str, outcome: bool, shares: u64) -> Self {
+        self.push(time, Action::Sell { trader: trader.to_string(), outcome, shares })
+    }
+
+    pub fn close(self, time: i64) -> Self {
+        self.push(time, Action::Close)
+    }
+
+    pub fn resolve(self, time: i64, outcome: bool) -> Self {
+        self.push(time, Action::Resolve { outcome })
+    }
+
+    pub fn cancel(self, time: i64) -> Self {
+        self.push(time, Action::Cancel)
+    }
+
+    /// Rebuild the trade sequence of `market` from its events (e.g. from
+    /// `driftshield_sdk::events::parse_logs` or the indexer), oldest first.
+    /// Traders are named by wallet. Sells replay the recorded share count, so
+    /// under a different setup they fail if the trader holds fewer shares.
+    pub fn from_events<'a>(market: &Pubkey, events: impl IntoIterator<Item = &'a PredictionMarketEvent>) -> Self {
+        let mut scenario = Self::new();
+        for event in events {
+            let step = match event {
+                PredictionMarketEvent::BetPlaced(e) if e.market_key == *market => Step {
+                    time: e.timestamp,
+                    action: Action::Buy { trader: e.user.to_string(), outcome: e.outcome, amount: e.amount },
+                },
+                PredictionMarketEvent::SharesSold(e) if e.market_key == *market => Step {
+                    time: e.timestamp,
+                    action: Action::Sell { trader: e.user.to_string(), outcome: e.outcome, shares: e.shares },
+                },
+                PredictionMarketEvent::MarketClosed(e) if e.market_key == *market => Step {
+                    time: e.timestamp,
+                    action: Action::Close,
+                },
+                PredictionMarketEvent::MarketResolved(e) if e.market_key == *market => Step {
+                    time: e.timestamp,
+                    action: Action::Resolve { outcome: e.winning_outcome },
+                },
+                PredictionMarketEvent::MarketCancelled(e) if e.market_key == *market => Step {
+                    time: e.timestamp,
+                    action: Action::Cancel,
+                },
+                PredictionMarketEvent::ConditionalMarketVoided(e) if e.market_key == *market => Step {
+                    time: e.timestamp,
+                    action: Action::Cancel,
+                },
+                PredictionMarketEvent::AbandonedMarketVoided(e) if e.market_key == *market => Step {
+                    time: e.timestamp,
+                    action: Action::Cancel,
+                },
+                _ => continue,
+            };
+            scenario.steps.push(step);
+        }
+        scenario
+    }
+}
@@ -0,0 +1,411 @@
+//! The simulator.
+//!
+//! Each step runs against copies of the market, position and vault and is
+//! only committed if it succeeds, so a rejected step leaves no trace, as a
+//! failed transaction would.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anchor_lang::prelude::*;
+use driftshield_math::{bps, Rounding};
+use driftshield_sdk::prediction_market::{
+    self, ErrorCode, Market, MarketParams, MarketStatus, Position, ProtocolConfig, Versioned, MAX_TRADING_FEE_BPS,
+};
+
+use crate::curve::{ConstantProduct, Curve};
+use crate::report::{Fill, PricePoint, Report, StepResult, TraderPnl};
+use crate::scenario::{Action, Scenario, Step};
+
+/// Market and protocol parameters for a run. Fields mirror `create_market`,
+/// `update_config`, `set_dynamic_fees`, `set_circuit_breaker` and
+/// `set_exposure_limits`; anything else can be set through `Simulator::market_mut`.
+#[derive(Clone)]
+pub struct Setup {
+    pub curve: Arc<dyn Curve>,
+    pub initial_liquidity: u64,
+    pub trading_fee_bps: u16,
+    pub created_at: i64,
+    pub resolution_time: i64,
+    pub min_stake: u64,
+
+    // Dynamic fees
+    pub fee_ramp_bps: u16,
+    pub fee_ramp_window: i64,
+    pub volatility_fee_multiplier_bps: u16,
+    pub volatility_window: i64,
+    pub max_dynamic_fee_bps: u16,
+
+    // Circuit breaker
+    pub breaker_move_bps: u16,
+    pub breaker_window: i64,
+    pub breaker_cooldown: i64,
+
+    // Exposure limits
+    pub max_position_stake: u64,
+    pub max_wallet_oi_bps: u16,
+    pub max_price_impact_bps: u16,
+    pub wallet_oi_floor: u64,
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Self {
+            curve: Arc::new(ConstantProduct),
+            initial_liquidity: 1_000_000_000,
+            trading_fee_bps: 100,
+            created_at: 0,
+            resolution_time: 30 * 86_400,
+            min_stake: 1,
+            fee_ramp_bps: 0,
+            fee_ramp_window: 0,
+            volatility_fee_multiplier_bps: 0,
+            volatility_window: 0,
+            max_dynamic_fee_bps: 0,
+            breaker_move_bps: 0,
+            breaker_window: 0,
+            breaker_cooldown: 0,
+            max_position_stake: 0,
+            max_wallet_oi_bps: 0,
+            max_price_impact_bps: 0,
+            wallet_oi_floor: 0,
+        }
+    }
+}
+
+impl Setup {
+    /// Protocol config the market trades under
+    pub fn config(&self) -> Result<ProtocolConfig> {
+        require!(
+            self.trading_fee_bps <= MAX_TRADING_FEE_BPS,
+            ErrorCode::InvalidFeeParameter
+        );
+
+        let mut config: ProtocolConfig = zeroed();
+        config.trading_fee_bps = self.trading_fee_bps;
+        config.version = ProtocolConfig::VERSION;
+        Ok(config)
+    }
+
+    /// A market as `create_market` and the creator's setters leave it
+    pub fn market(&self) -> Result<Market> {
+        let mut market: Market = zeroed();
+        market.open(
+            MarketParams {
+                model: Pubkey::default(),
+                question: String::new(),
+                resolution_time: self.resolution_time,
+                min_stake: self.min_stake,
+                initial_liquidity: self.initial_liquidity,
+                allowlist_root: None,
+            },
+            self.created_at,
+        )?;
+        market.amm_enabled = self.curve.amm_enabled();
+
+        market.set_exposure_limits(
+            self.max_position_stake,
+            self.max_wallet_oi_bps,
+            self.max_price_impact_bps,
+            self.wallet_oi_floor,
+        )?;
+        market.set_dynamic_fees(
+            self.fee_ramp_bps,
+            self.fee_ramp_window,
+            self.volatility_fee_multiplier_bps,
+            self.volatility_window,
+            self.max_dynamic_fee_bps,
+            self.created_at,
+        )?;
+        market.set_circuit_breaker(self.breaker_move_bps, self.breaker_window, self.breaker_cooldown, self.created_at)?;
+        Ok(market)
+    }
+}
+
+/// An account with every field zeroed, as freshly allocated space reads
+fn zeroed<T: Versioned + AnchorDeserialize>() -> T {
+    let data = vec![0u8; T::SPACE - 8];
+    T::deserialize(&mut data.as_slice()).expect("zeroed account layout")
+}
+
+/// Name of the program error behind `err`
+fn error_name(err: &Error) -> String {
+    match err {
+        Error::AnchorError(e) => e.error_name.clone(),
+        Error::ProgramError(e) => e.program_error.to_string(),
+    }
+}
+
+pub struct Simulator {
+    setup: Setup,
+    config: ProtocolConfig,
+    market: Market,
+    vault: u64,
+    fees: u64,
+    positions: BTreeMap<String, Position>,
+    traders: BTreeMap<String, TraderPnl>,
+    steps: Vec<StepResult>,
+    prices: Vec<PricePoint>,
+    min_headroom: i128,
+}
+
+impl Simulator {
+    pub fn new(setup: Setup) -> Result<Self> {
+        let config = setup.config()?;
+        let market = setup.market()?;
+        let mut sim = Self {
+            vault: setup.initial_liquidity,
+            setup,
+            config,
+            market,
+            fees: 0,
+            positions: BTreeMap::new(),
+            traders: BTreeMap::new(),
+            steps: Vec::new(),
+            prices: Vec::new(),
+            min_headroom: i128::MAX,
+        };
+        sim.record_price(sim.setup.created_at)?;
+        Ok(sim)
+    }
+
+    pub fn market(&self) -> &Market {
+        &self.market
+    }
+
+    /// Direct access to the on-chain state, e.g. to set fields `Setup` doesn't cover
+    pub fn market_mut(&mut self) -> &mut Market {
+        &mut self.market
+    }
+
+    pub fn config(&self) -> &ProtocolConfig {
+        &self.config
+    }
+
+    pub fn position(&self, trader: &str) -> Option<&Position> {
+        self.positions.get(trader)
+    }
+
+    /// Simulated market vault balance
+    pub fn vault(&self) -> u64 {
+        self.vault
+    }
+
+    /// Run every step of `scenario`
+    pub fn run(&mut self, scenario: &Scenario) {
+        for step in &scenario.steps {
+            self.apply(step);
+        }
+    }
+
+    /// Execute one step, committing its effects only if it succeeds
+    pub fn apply(&mut self, step: &Step) -> &StepResult {
+        let mut market = self.market.clone();
+        let mut vault = self.vault;
+
+        let result = match &step.action {
+            Action::Buy { trader, outcome, amount } => {
+                let mut position = self.position_or_new(trader);
+                self.buy(&mut market, &mut vault, &mut position, step.time, *outcome, *amount)
+                    .map(|fill| {
+                        self.positions.insert(trader.clone(), position);
+                        let pnl = self.traders.entry(trader.clone()).or_default();
+                        pnl.paid += fill.collateral;
+                        pnl.fees += fill.fee;
+                        Some(fill)
+                    })
+            }
+            Action::Sell { trader, outcome, shares } => {
+                let mut position = self.position_or_new(trader);
+                self.sell(&mut market, &mut vault, &mut position, step.time, *outcome, *shares)
+                    .map(|fill| {
+                        self.positions.insert(trader.clone(), position);
+                        let pnl = self.traders.entry(trader.clone()).or_default();
+                        pnl.received += fill.collateral;
+                        pnl.fees += fill.fee;
+                        Some(fill)
+                    })
+            }
+            Action::Close => market.close_trading(step.time).map(|_| None),
+            Action::Resolve { outcome } => market.resolve(*outcome, step.time).map(|_| None),
+            Action::Cancel => market.cancel(step.time).map(|_| None),
+        };
+
+        let result = match result {
+            Ok(fill) => {
+                if let Some(fill) = &fill {
+                    self.fees += fill.fee - fill.lp_fee;
+                }
+                self.market = market;
+                self.vault = vault;
+                Ok(fill)
+            }
+            Err(err) => Err(error_name(&err)),
+        };
+
+        self.record_price(step.time).expect("trades leave the reserves priceable");
+        self.steps.push(StepResult { step: step.clone(), result });
+        self.steps.last().unwrap()
+    }
+
+    fn position_or_new(&self, trader: &str) -> Position {
+        self.positions.get(trader).cloned().unwrap_or_else(|| {
+            let mut position: Position = zeroed();
+            position.version = Position::VERSION;
+            position
+        })
+    }
+
+    /// `place_bet` with the setup's curve and a simulated vault
+    fn buy(
+        &self,
+        market: &mut Market,
+        vault: &mut u64,
+        position: &mut Position,
+        now: i64,
+        outcome: bool,
+        amount: u64,
+    ) -> Result<Fill> {
+        let curve = &self.setup.curve;
+        let fill = market.apply_buy_priced(position, &self.config, outcome, amount, now, |market, outcome, net_amount| {
+            curve.buy(market, outcome, net_amount)
+        })?;
+
+        *vault = vault.checked_add(fill.vault_amount).ok_or(ErrorCode::MathOverflow)?;
+        market.assert_solvent(*vault)?;
+        // On-chain the trade event prices the new reserves, failing the trade if it can't
+        prediction_market::prices_from_reserves(market.yes_reserve, market.no_reserve)?;
+
+        Ok(Fill { collateral: amount, shares: fill.shares, fee: fill.fee, lp_fee: fill.lp_fee, fee_bps: fill.fee_bps })
+    }
+
+    /// `sell_shares` with the setup's curve and a simulated vault
+    fn sell(
+        &self,
+        market: &mut Market,
+        vault: &mut u64,
+        position: &mut Position,
+        now: i64,
+        outcome: bool,
+        shares: u64,
+    ) -> Result<Fill> {
+        let curve = &self.setup.curve;
+        let fill = market.apply_sell_priced(position, &self.config, outcome, shares, now, |market, outcome, shares| {
+            curve.sell(market, outcome, shares)
+        })?;
+
+        *vault = vault.checked_sub(fill.vault_amount).ok_or(ErrorCode::InsolventMarket)?;
+        market.assert_solvent(*vault)?;
+        prediction_market::prices_from_reserves(market.yes_reserve, market.no_reserve)?;
+
+        Ok(Fill { collateral: fill.proceeds, shares, fee: fill.fee, lp_fee: fill.lp_fee, fee_bps: fill.fee_bps })
+    }
+
+    fn record_price(&mut self, time: i64) -> Result<()> {
+        let (yes_price, no_price) = prediction_market::prices_from_reserves(self.market.yes_reserve, self.market.no_reserve)?;
+        let headroom = self.vault as i128 - self.market.max_payout_owed().map_or(i128::MAX, |owed| owed as i128);
+        self.min_headroom = self.min_headroom.min(headroom);
+        self.prices.push(PricePoint {
+            time,
+            yes_price,
+            no_price,
+            fee_bps: prediction_market::trading_fee_bps(&self.config, &self.market, time)?,
+            vault: self.vault,
+            headroom,
+        });
+        Ok(())
+    }
+
+    /// Pay out every position and the LP (`claim_winnings` / `claim_refund`,
+    /// then `withdraw_liquidity`) and report. Unresolved markets are marked to
+    /// the spot price instead.
+    pub fn finish(mut self) -> Report {
+        let mut settlement_errors = Vec::new();
+        let settled = !self.market.status.is_unresolved();
+        let (yes_price, no_price) = prediction_market::prices_from_reserves(self.market.yes_reserve, self.market.no_reserve)
+            .unwrap_or_else(|err| {
+                settlement_errors.push(format!("mark: {}", error_name(&err)));
+                (0, 0)
+            });
+
+        for (trader, position) in self.positions.iter_mut() {
+            let pnl = self.traders.entry(trader.clone()).or_default();
+            if !settled {
+                pnl.payout = mark(position.yes_shares, yes_price) + mark(position.no_shares, no_price);
+                continue;
+            }
+
+            // Losing positions have nothing to claim
+            let status = self.market.status;
+            if status == MarketStatus::Resolved
+                && matches!(prediction_market::calculate_payout(&self.market, position), Ok((0, _)))
+            {
+                continue;
+            }
+
+            let mut market = self.market.clone();
+            let mut claimed = position.clone();
+            let paid = match status {
+                MarketStatus::Resolved => market.claim_winnings(&mut claimed).map(|(_, payout)| payout),
+                _ => market.claim_refund(&mut claimed),
+            }
+            .and_then(|payout| pay_out(&market, self.vault, payout));
+            match paid {
+                Ok(payout) => {
+                    self.vault -= payout;
+                    self.market = market;
+                    *position = claimed;
+                    pnl.payout = payout;
+                }
+                Err(err) => settlement_errors.push(format!("{}: {}", trader, error_name(&err))),
+            }
+        }
+
+        let lp_withdrawn = if !settled {
+            mark(self.market.yes_reserve, yes_price) + mark(self.market.no_reserve, no_price) + self.market.lp_fees
+        } else if self.market.lp_claimable == 0 {
+            0
+        } else {
+            let mut market = self.market.clone();
+            match market.withdraw_liquidity().and_then(|amount| pay_out(&market, self.vault, amount)) {
+                Ok(amount) => {
+                    self.vault -= amount;
+                    self.market = market;
+                    amount
+                }
+                Err(err) => {
+                    settlement_errors.push(format!("lp: {}", error_name(&err)));
+                    0
+                }
+            }
+        };
+
+        Report {
+            curve: self.setup.curve.name(),
+            status: self.market.status,
+            settled,
+            steps: self.steps,
+            prices: self.prices,
+            traders: self.traders,
+            lp_deposited: self.setup.initial_liquidity,
+            lp_withdrawn,
+            fees: self.fees,
+            volume: self.market.total_volume,
+            min_headroom: self.min_headroom,
+            dust: if settled { self.vault } else { 0 },
+            settlement_errors,
+        }
+    }
+}
+
+/// Check the vault can pay `amount` out of `market` and stay solvent
+fn pay_out(market: &Market, vault: u64, amount: u64) -> Result<u64> {
+    let vault = vault.checked_sub(amount).ok_or(ErrorCode::InsolventMarket)?;
+    market.assert_solvent(vault)?;
+    Ok(amount)
+}
+
+/// Value of `shares` at `price` bps
+fn mark(shares: u64, price: u64) -> u64 {
+    bps::apply(shares, price, Rounding::Down).unwrap_or(u64::MAX)
+}
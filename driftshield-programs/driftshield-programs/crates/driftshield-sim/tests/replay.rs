@@ -0,0 +1,188 @@
+use driftshield_sdk::events::PredictionMarketEvent;
+use driftshield_sdk::pricing;
+use driftshield_sdk::Pubkey;
+use driftshield_sim::prediction_market::{BetPlaced, MarketResolved, MarketStatus, SharesSold};
+use driftshield_sim::{Action, PeerToPeer, Scenario, Setup, Simulator};
+
+const DAY: i64 = 86_400;
+
+fn setup() -> Setup {
+    Setup { initial_liquidity: 100_000_000, resolution_time: 10 * DAY, ..Setup::default() }
+}
+
+fn trading(alice: &str, bob: &str) -> Scenario {
+    Scenario::new()
+        .buy(100, alice, true, 20_000_000)
+        .buy(200, bob, false, 5_000_000)
+        .buy(300, alice, true, 7_500_000)
+        .sell(400, alice, true, 10_000_000)
+        .buy(DAY, bob, true, 1_000_000)
+        .sell(2 * DAY, bob, false, 2_000_000)
+}
+
+#[test]
+fn fills_match_quotes_and_settlement_balances() {
+    let scenario = trading("alice", "bob").close(10 * DAY).resolve(10 * DAY + 60, true);
+    let mut sim = Simulator::new(setup()).unwrap();
+
+    for step in &scenario.steps {
+        let (config, market) = (sim.config().clone(), sim.market().clone());
+        let position = match &step.action {
+            Action::Buy { trader, .. } | Action::Sell { trader, .. } => sim.position(trader).cloned(),
+            _ => None,
+        };
+        let result = sim.apply(step).result.clone().unwrap();
+        match &step.action {
+            Action::Buy { outcome, amount, .. } => {
+                let quote =
+                    pricing::quote_buy(&config, &market, position.as_ref(), *outcome, *amount, step.time).unwrap();
+                let fill = result.unwrap();
+                assert_eq!((fill.shares, fill.fee, fill.fee_bps), (quote.shares, quote.fee, quote.fee_bps));
+            }
+            Action::Sell { outcome, shares, .. } => {
+                let quote =
+                    pricing::quote_sell(&config, &market, position.as_ref(), *outcome, *shares, step.time).unwrap();
+                let fill = result.unwrap();
+                assert_eq!((fill.collateral, fill.fee), (quote.proceeds, quote.fee));
+            }
+            _ => assert!(result.is_none()),
+        }
+    }
+
+    let vault = sim.vault();
+    assert_eq!(vault, sim.market().total_collateral);
+    let report = sim.finish();
+    assert!(report.status == MarketStatus::Resolved);
+    assert_eq!(report.rejected(), 0);
+    assert!(report.settlement_errors.is_empty(), "{:?}", report.settlement_errors);
+    assert!(report.min_headroom >= 0);
+    assert_eq!(report.unaccounted(), 0);
+    assert_eq!(report.lp_withdrawn + report.traders.values().map(|t| t.payout).sum::<u64>() + report.dust, vault);
+}
+
+fn resolved(market_key: Pubkey, winning_outcome: bool, timestamp: i64) -> MarketResolved {
+    MarketResolved {
+        market_key,
+        winning_outcome,
+        yes_pool: 0,
+        no_pool: 0,
+        total_yes_shares: 0,
+        total_no_shares: 0,
+        timestamp,
+    }
+}
+
+#[test]
+fn replays_recorded_events() {
+    let market_key = Pubkey::new_unique();
+    let (alice, bob) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+    let scenario = trading(&alice, &bob).resolve(10 * DAY, false);
+
+    let mut sim = Simulator::new(setup()).unwrap();
+    sim.run(&scenario);
+    let report = sim.finish();
+
+    // Rebuild the events the program would have logged, after another market's resolution
+    let mut events = vec![PredictionMarketEvent::MarketResolved(resolved(Pubkey::new_unique(), true, 50))];
+    for step in &report.steps {
+        let fill = step.result.clone().unwrap();
+        let event = match (&step.step.action, fill) {
+            (Action::Buy { trader, outcome, amount }, Some(fill)) => PredictionMarketEvent::BetPlaced(BetPlaced {
+                market_key,
+                user: trader.parse().unwrap(),
+                outcome: *outcome,
+                amount: *amount,
+                shares: fill.shares,
+                fee: fill.fee,
+                fee_bps: fill.fee_bps,
+                referrer: None,
+                yes_price: 0,
+                no_price: 0,
+                timestamp: step.step.time,
+            }),
+            (Action::Sell { trader, outcome, shares }, Some(fill)) => PredictionMarketEvent::SharesSold(SharesSold {
+                market_key,
+                user: trader.parse().unwrap(),
+                outcome: *outcome,
+                shares: *shares,
+                sol_received: fill.collateral,
+                fee: fill.fee,
+                fee_bps: fill.fee_bps,
+                referrer: None,
+                yes_price: 0,
+                no_price: 0,
+                timestamp: step.step.time,
+            }),
+            (Action::Resolve { outcome }, None) => {
+                PredictionMarketEvent::MarketResolved(resolved(market_key, *outcome, step.step.time))
+            }
+            other => panic!("unexpected step {:?}", other),
+        };
+        events.push(event);
+    }
+
+    let replayed = Scenario::from_events(&market_key, &events);
+    assert_eq!(replayed, scenario);
+
+    let mut sim = Simulator::new(setup()).unwrap();
+    sim.run(&replayed);
+    let again = sim.finish();
+    for (original, replay) in report.steps.iter().zip(&again.steps) {
+        assert_eq!(original.result, replay.result);
+    }
+    assert_eq!(again.traders, report.traders);
+    assert_eq!((again.lp_withdrawn, again.fees, again.dust), (report.lp_withdrawn, report.fees, report.dust));
+}
+
+#[test]
+fn rejected_steps_leave_no_trace() {
+    let mut sim = Simulator::new(setup()).unwrap();
+    sim.run(&Scenario::new().buy(100, "alice", true, 10_000_000));
+    let (vault, reserves, position) = (
+        sim.vault(),
+        (sim.market().yes_reserve, sim.market().no_reserve),
+        sim.position("alice").cloned().unwrap(),
+    );
+
+    let rejected = Scenario::new()
+        .sell(200, "alice", true, position.yes_shares + 1)
+        .sell(200, "bob", false, 1)
+        .buy(300, "alice", false, 0)
+        .buy(10 * DAY, "alice", true, 1_000_000)
+        .resolve(DAY, true);
+    for step in &rejected.steps {
+        assert!(sim.apply(step).result.is_err());
+    }
+
+    let errors: Vec<_> = sim.finish().steps.iter().skip(1).map(|s| s.result.clone().unwrap_err()).collect();
+    assert_eq!(
+        errors,
+        ["InsufficientShares", "InsufficientShares", "StakeTooLow", "MarketExpired", "MarketNotExpired"]
+    );
+
+    let mut sim = Simulator::new(setup()).unwrap();
+    sim.run(&Scenario::new().buy(100, "alice", true, 10_000_000).sell(200, "alice", true, position.yes_shares + 1));
+    assert_eq!(sim.vault(), vault);
+    assert_eq!((sim.market().yes_reserve, sim.market().no_reserve), reserves);
+    assert_eq!(sim.position("alice").unwrap().yes_shares, position.yes_shares);
+}
+
+#[test]
+fn cancellation_refunds_stakes() {
+    for setup in [setup(), Setup { curve: std::sync::Arc::new(PeerToPeer), ..setup() }] {
+        let scenario = Scenario::new()
+            .buy(100, "alice", true, 20_000_000)
+            .buy(200, "bob", false, 30_000_000)
+            .cancel(DAY);
+        let mut sim = Simulator::new(setup).unwrap();
+        sim.run(&scenario);
+        let stakes: Vec<u64> = ["alice", "bob"].iter().map(|t| sim.position(t).unwrap().total_stake).collect();
+        let report = sim.finish();
+
+        assert!(report.status == MarketStatus::Cancelled);
+        assert!(report.settlement_errors.is_empty(), "{:?}", report.settlement_errors);
+        assert_eq!(report.traders["alice"].payout, stakes[0]);
+        assert_eq!(report.traders["bob"].payout, stakes[1]);
+        assert_eq!(report.unaccounted(), 0);
+    }
+}
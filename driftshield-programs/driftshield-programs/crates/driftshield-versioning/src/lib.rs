@@ -0,0 +1,151 @@
+#![allow(clippy::result_large_err)]
+
+//! Account layout versioning shared by the DriftShield programs.
+//!
+//! Every account carries a `version` byte followed by zeroed `reserved`
+//! space. New fields are carved out of `reserved`, or appended when an
+//! account grows, so bytes an older layout never wrote read as zero.
+//! `migrate_*` instructions grow an account to the current layout and bump
+//! its version in place. Layouts that are not a prefix of the current one
+//! (see [`Versioned::APPEND_ONLY_SINCE`]) need their own translation.
+//!
+//! `#[derive(Accounts)]` structs can only be used by the crate that declares
+//! them, so each program declares its own `MigrateAccount` context with
+//! [`migrate_account_context!`].
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+/// Accounts that carry a layout version and can be upgraded in place by `migrate_*`
+pub trait Versioned: AccountSerialize + AccountDeserialize + Owner + Clone + Space {
+    /// Current layout version
+    const VERSION: u8;
+    /// Oldest version whose layout is a prefix of the current one. Older
+    /// versions need an explicit translation and are rejected by `migrate_account`.
+    const APPEND_ONLY_SINCE: u8 = Self::VERSION;
+    /// Allocated size of the current layout, discriminator included
+    const SPACE: usize = 8 + Self::INIT_SPACE;
+
+    fn version(&self) -> u8;
+    fn set_version(&mut self, version: u8);
+}
+
+/// Grow an account to `space` bytes, topping up rent from the payer.
+/// New bytes are zeroed, so fields appended since the account's layout read as zero.
+pub fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> Result<()> {
+    if account.data_len() >= space {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?.minimum_balance(space);
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        let cpi_accounts = system_program::Transfer {
+            from: payer.clone(),
+            to: account.clone(),
+        };
+        let cpi_ctx = CpiContext::new(system_program.clone(), cpi_accounts);
+        system_program::transfer(cpi_ctx, top_up)?;
+    }
+
+    account.realloc(space, true)?;
+    Ok(())
+}
+
+/// Upgrade an account written under an older layout to the current one
+pub fn migrate_account<'info, T: Versioned>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    require_keys_eq!(*account.owner, T::owner(), ErrorCode::AccountOwnedByWrongProgram);
+    grow_account(account, payer, system_program, T::SPACE)?;
+
+    let from_version = upgrade_account_data::<T>(&mut account.try_borrow_mut_data()?)?;
+
+    emit!(AccountMigrated {
+        account: account.key(),
+        from_version,
+        to_version: T::VERSION,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Rewrite account data, already grown to `T::SPACE`, in the current layout.
+/// Returns the version it was written under.
+pub fn upgrade_account_data<T: Versioned>(data: &mut [u8]) -> Result<u8> {
+    let mut account = T::try_deserialize(&mut &data[..])?;
+    let from_version = account.version();
+    require!(from_version < T::VERSION, VersioningError::AlreadyMigrated);
+    require!(from_version >= T::APPEND_ONLY_SINCE, VersioningError::UnsupportedVersion);
+
+    account.set_version(T::VERSION);
+    account.try_serialize(&mut &mut data[..])?;
+    Ok(from_version)
+}
+
+/// Declare the `MigrateAccount` context taken by a program's `migrate_*` instructions.
+/// Invoke it at the crate root, next to `declare_id!`'s `ID`.
+#[macro_export]
+macro_rules! migrate_account_context {
+    () => {
+        #[derive(Accounts)]
+        pub struct MigrateAccount<'info> {
+            /// CHECK: May still be in an older layout; the discriminator is checked once it has been grown
+            #[account(mut, owner = ID)]
+            pub account: UncheckedAccount<'info>,
+            #[account(mut)]
+            pub payer: Signer<'info>,
+            pub system_program: Program<'info, System>,
+        }
+
+        impl<'info> MigrateAccount<'info> {
+            /// Grow the account to `space` bytes, payer covering any extra rent
+            pub fn grow(&self, space: usize) -> Result<()> {
+                $crate::grow_account(
+                    &self.account.to_account_info(),
+                    &self.payer.to_account_info(),
+                    &self.system_program.to_account_info(),
+                    space,
+                )
+            }
+
+            /// Upgrade the account to the current layout of `T`
+            pub fn migrate<T: $crate::Versioned>(&self) -> Result<()> {
+                $crate::migrate_account::<T>(
+                    &self.account.to_account_info(),
+                    &self.payer.to_account_info(),
+                    &self.system_program.to_account_info(),
+                )
+            }
+        }
+    };
+}
+
+// Events
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub timestamp: i64,
+}
+
+// Errors
+
+/// Offset past the programs' own error codes
+#[error_code(offset = 7000)]
+pub enum VersioningError {
+    #[msg("Account is already at the current version")]
+    AlreadyMigrated,
+    #[msg("Account layout has no migration to the current version")]
+    UnsupportedVersion,
+}
@@ -0,0 +1,482 @@
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::prelude::*;
+use driftshield_versioning::migrate_account_context;
+pub use driftshield_versioning::{AccountMigrated, Versioned};
+
+declare_id!("HWTu6d47u2jHgjaZgtTupxNpceUVDwp1YBUbtYUEC6x3");
+
+pub const MAX_AGENT_ID_LEN: usize = 32;
+pub const MAX_DISPLAY_NAME_LEN: usize = 50;
+
+#[program]
+pub mod agent_registry {
+    use super::*;
+
+    /// Initialize the registry config with the admin allowed to verify agents
+    /// (program upgrade authority only)
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.admin = ctx.accounts.admin.key();
+        config.total_agents = 0;
+        config.bump = *ctx.bumps.get("config").unwrap();
+        config.version = RegistryConfig::VERSION;
+
+        Ok(())
+    }
+
+    /// Hand the admin role to a new key
+    pub fn set_registry_admin(
+        ctx: Context<SetRegistryAdmin>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.config.admin = new_admin;
+
+        Ok(())
+    }
+
+    /// Register a new trading agent
+    pub fn register_agent(
+        ctx: Context<RegisterAgent>,
+        agent_id: String,
+        display_name: String,
+        strategy_hash: [u8; 32],  // sha256 of the strategy description
+    ) -> Result<()> {
+        let agent = &mut ctx.accounts.agent;
+        let clock = Clock::get()?;
+
+        require!(
+            !agent_id.is_empty() && agent_id.len() <= MAX_AGENT_ID_LEN,
+            ErrorCode::AgentIdTooLong
+        );
+
+        require!(
+            display_name.len() >= 2 && display_name.len() <= MAX_DISPLAY_NAME_LEN,
+            ErrorCode::InvalidDisplayName
+        );
+
+        agent.owner = ctx.accounts.owner.key();
+        agent.agent_id = agent_id;
+        agent.display_name = display_name;
+        agent.strategy_hash = strategy_hash;
+        agent.trading_wallet = ctx.accounts.trading_wallet.key();
+        agent.verified = false;
+        agent.status = AgentStatus::Active;
+        agent.suspended_by_admin = false;
+        agent.created_at = clock.unix_timestamp;
+        agent.updated_at = clock.unix_timestamp;
+        agent.bump = *ctx.bumps.get("agent").unwrap();
+        agent.version = AgentAccount::VERSION;
+
+        let config = &mut ctx.accounts.config;
+        config.total_agents = config.total_agents.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(AgentRegistered {
+            agent_key: agent.key(),
+            owner: agent.owner,
+            agent_id: agent.agent_id.clone(),
+            display_name: agent.display_name.clone(),
+            strategy_hash,
+            trading_wallet: agent.trading_wallet,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Update an agent's profile. Changing the strategy or trading wallet
+    /// clears the verified flag until the admin re-verifies it. A new trading
+    /// wallet is passed as a signer, so it can't be claimed without its key.
+    /// Agents suspended by the admin are frozen until reinstated.
+    pub fn update_agent(
+        ctx: Context<UpdateAgent>,
+        display_name: Option<String>,
+        strategy_hash: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let agent = &mut ctx.accounts.agent;
+        let clock = Clock::get()?;
+
+        require!(
+            !agent.suspended_by_admin,
+            ErrorCode::SuspendedByAdmin
+        );
+
+        if let Some(display_name) = display_name {
+            require!(
+                display_name.len() >= 2 && display_name.len() <= MAX_DISPLAY_NAME_LEN,
+                ErrorCode::InvalidDisplayName
+            );
+            agent.display_name = display_name;
+        }
+
+        if let Some(strategy_hash) = strategy_hash {
+            if strategy_hash != agent.strategy_hash {
+                agent.strategy_hash = strategy_hash;
+                agent.verified = false;
+            }
+        }
+
+        if let Some(trading_wallet) = &ctx.accounts.trading_wallet {
+            if trading_wallet.key() != agent.trading_wallet {
+                agent.trading_wallet = trading_wallet.key();
+                agent.verified = false;
+            }
+        }
+
+        agent.updated_at = clock.unix_timestamp;
+
+        emit!(AgentUpdated {
+            agent_key: agent.key(),
+            display_name: agent.display_name.clone(),
+            strategy_hash: agent.strategy_hash,
+            trading_wallet: agent.trading_wallet,
+            verified: agent.verified,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Suspend an agent (owner or admin)
+    pub fn suspend_agent(ctx: Context<SetAgentStatus>) -> Result<()> {
+        let agent = &mut ctx.accounts.agent;
+        let clock = Clock::get()?;
+
+        require!(
+            agent.status == AgentStatus::Active,
+            ErrorCode::InvalidStatus
+        );
+
+        agent.status = AgentStatus::Suspended;
+        agent.suspended_by_admin = ctx.accounts.authority.key() == ctx.accounts.config.admin;
+        agent.updated_at = clock.unix_timestamp;
+
+        emit!(AgentSuspended {
+            agent_key: agent.key(),
+            authority: ctx.accounts.authority.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Reinstate a suspended agent (owner or admin; admin only after an admin suspension)
+    pub fn reinstate_agent(ctx: Context<SetAgentStatus>) -> Result<()> {
+        let agent = &mut ctx.accounts.agent;
+        let clock = Clock::get()?;
+
+        require!(
+            agent.status == AgentStatus::Suspended,
+            ErrorCode::InvalidStatus
+        );
+
+        require!(
+            !agent.suspended_by_admin || ctx.accounts.authority.key() == ctx.accounts.config.admin,
+            ErrorCode::Unauthorized
+        );
+
+        agent.status = AgentStatus::Active;
+        agent.suspended_by_admin = false;
+        agent.updated_at = clock.unix_timestamp;
+
+        emit!(AgentReinstated {
+            agent_key: agent.key(),
+            authority: ctx.accounts.authority.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Mark an agent as verified or unverified (admin only)
+    pub fn set_agent_verified(
+        ctx: Context<SetAgentVerified>,
+        verified: bool,
+    ) -> Result<()> {
+        let agent = &mut ctx.accounts.agent;
+        let clock = Clock::get()?;
+
+        agent.verified = verified;
+        agent.updated_at = clock.unix_timestamp;
+
+        emit!(AgentVerificationChanged {
+            agent_key: agent.key(),
+            verified,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Deregister an agent and return its rent to the owner. An agent suspended
+    /// by the admin stays registered, so its id can't be re-registered as active.
+    pub fn deregister_agent(ctx: Context<DeregisterAgent>) -> Result<()> {
+        require!(
+            !ctx.accounts.agent.suspended_by_admin,
+            ErrorCode::SuspendedByAdmin
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.total_agents = config.total_agents.saturating_sub(1);
+
+        emit!(AgentDeregistered {
+            agent_key: ctx.accounts.agent.key(),
+            owner: ctx.accounts.owner.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrade the registry config created under an older layout
+    pub fn migrate_registry_config(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<RegistryConfig>()
+    }
+
+    /// Upgrade an agent account created under an older layout
+    pub fn migrate_agent(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<AgentAccount>()
+    }
+}
+
+// Account Structures
+
+#[account]
+#[derive(InitSpace)]
+pub struct RegistryConfig {
+    pub admin: Pubkey,             // Can verify agents and suspend any agent
+    pub total_agents: u64,
+    pub bump: u8,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 64],         // Zeroed space for future fields
+}
+
+impl Versioned for RegistryConfig {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AgentAccount {
+    pub owner: Pubkey,
+    #[max_len(32)]
+    pub agent_id: String,          // Max 32 chars
+    #[max_len(50)]
+    pub display_name: String,      // Max 50 chars
+    pub strategy_hash: [u8; 32],   // Hash of the off-chain strategy description
+    pub trading_wallet: Pubkey,    // Wallet (or session key owner) the agent trades from
+    pub verified: bool,
+    pub status: AgentStatus,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub bump: u8,
+    pub suspended_by_admin: bool,   // Only the admin can reinstate
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 64],         // Zeroed space for future fields
+}
+
+impl Versioned for AgentAccount {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum AgentStatus {
+    Active,
+    Suspended,
+}
+
+// Context Structures
+
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = RegistryConfig::SPACE,
+        seeds = [b"registry_config"],
+        bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    // Only the upgrade authority can claim the config, so it can't be front-run
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized)]
+    pub program: Program<'info, crate::program::AgentRegistry>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRegistryAdmin<'info> {
+    #[account(mut, seeds = [b"registry_config"], bump = config.bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, RegistryConfig>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(agent_id: String)]
+pub struct RegisterAgent<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = AgentAccount::SPACE,
+        seeds = [b"agent", owner.key().as_ref(), agent_id.as_bytes()],
+        bump
+    )]
+    pub agent: Account<'info, AgentAccount>,
+    #[account(mut, seeds = [b"registry_config"], bump = config.bump)]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub trading_wallet: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAgent<'info> {
+    #[account(
+        mut,
+        seeds = [b"agent", owner.key().as_ref(), agent.agent_id.as_bytes()],
+        bump = agent.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub agent: Account<'info, AgentAccount>,
+    pub owner: Signer<'info>,
+    pub trading_wallet: Option<Signer<'info>>,  // New trading wallet, if changing it
+}
+
+#[derive(Accounts)]
+pub struct SetAgentStatus<'info> {
+    #[account(
+        mut,
+        seeds = [b"agent", agent.owner.as_ref(), agent.agent_id.as_bytes()],
+        bump = agent.bump,
+        constraint = authority.key() == agent.owner || authority.key() == config.admin @ ErrorCode::Unauthorized
+    )]
+    pub agent: Account<'info, AgentAccount>,
+    #[account(seeds = [b"registry_config"], bump = config.bump)]
+    pub config: Account<'info, RegistryConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAgentVerified<'info> {
+    #[account(
+        mut,
+        seeds = [b"agent", agent.owner.as_ref(), agent.agent_id.as_bytes()],
+        bump = agent.bump
+    )]
+    pub agent: Account<'info, AgentAccount>,
+    #[account(seeds = [b"registry_config"], bump = config.bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, RegistryConfig>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeregisterAgent<'info> {
+    #[account(
+        mut,
+        seeds = [b"agent", owner.key().as_ref(), agent.agent_id.as_bytes()],
+        bump = agent.bump,
+        has_one = owner @ ErrorCode::Unauthorized,
+        close = owner
+    )]
+    pub agent: Account<'info, AgentAccount>,
+    #[account(mut, seeds = [b"registry_config"], bump = config.bump)]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+migrate_account_context!();
+
+// Events
+
+#[event]
+pub struct AgentRegistered {
+    pub agent_key: Pubkey,
+    pub owner: Pubkey,
+    pub agent_id: String,
+    pub display_name: String,
+    pub strategy_hash: [u8; 32],
+    pub trading_wallet: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AgentUpdated {
+    pub agent_key: Pubkey,
+    pub display_name: String,
+    pub strategy_hash: [u8; 32],
+    pub trading_wallet: Pubkey,
+    pub verified: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AgentSuspended {
+    pub agent_key: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AgentReinstated {
+    pub agent_key: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AgentVerificationChanged {
+    pub agent_key: Pubkey,
+    pub verified: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AgentDeregistered {
+    pub agent_key: Pubkey,
+    pub owner: Pubkey,
+    pub timestamp: i64,
+}
+
+// Errors
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Unauthorized access")]
+    Unauthorized,
+    #[msg("Invalid agent status")]
+    InvalidStatus,
+    #[msg("Agent ID must be 1-32 chars")]
+    AgentIdTooLong,
+    #[msg("Display name must be 2-50 chars")]
+    InvalidDisplayName,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Agent is suspended by the admin")]
+    SuspendedByAdmin,
+}
@@ -1,5 +1,10 @@
+#![allow(clippy::result_large_err)]
+
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use driftshield_math::{bps, mul_div, Rounding};
+use driftshield_versioning::migrate_account_context;
+pub use driftshield_versioning::{AccountMigrated, Versioned};
 
 declare_id!("2YbvCZwBSQN9Pe8hmcPDHk2MBCpwHk4tZ11WVuB7LXwC");
 
@@ -39,6 +44,7 @@ pub mod insurance {
         policy.expiry_time = clock.unix_timestamp + (duration_days * 86400);
         policy.claim_paid = 0;
         policy.bump = *ctx.bumps.get("policy").unwrap();
+        policy.version = InsurancePolicy::VERSION;
 
         emit!(PolicyPurchased {
             policy_key: policy.key(),
@@ -130,14 +136,7 @@ pub mod insurance {
             ErrorCode::PolicyNotActive
         );
 
-        // Calculate refund (pro-rated based on time remaining)
-        let total_duration = policy.expiry_time - policy.start_time;
-        let time_remaining = policy.expiry_time - clock.unix_timestamp;
-        let refund = if time_remaining > 0 {
-            (policy.premium_paid as u128 * time_remaining as u128 / total_duration as u128) as u64
-        } else {
-            0
-        };
+        let refund = policy.refund_at(clock.unix_timestamp)?;
 
         if refund > 0 {
             let seeds = &[
@@ -168,11 +167,82 @@ pub mod insurance {
 
         Ok(())
     }
+
+    /// Mark a policy past its expiry as expired (permissionless crank). The caller
+    /// is tipped `EXPIRY_TIP_BPS` of the premium, paid out of the expired premium
+    /// held in the policy vault and capped at its balance; omit the token accounts
+    /// to crank without a tip.
+    pub fn expire_policy(ctx: Context<ExpirePolicy>) -> Result<()> {
+        let policy = &mut ctx.accounts.policy;
+        let clock = Clock::get()?;
+
+        require!(
+            policy.status == PolicyStatus::Active,
+            ErrorCode::PolicyNotActive
+        );
+
+        require!(
+            clock.unix_timestamp > policy.expiry_time,
+            ErrorCode::PolicyNotExpired
+        );
+
+        policy.status = PolicyStatus::Expired;
+
+        let mut tip = 0;
+        if let (Some(vault), Some(cranker_token_account)) =
+            (&ctx.accounts.insurance_vault, &ctx.accounts.cranker_token_account)
+        {
+            require!(
+                cranker_token_account.mint == vault.mint,
+                ErrorCode::InvalidVault
+            );
+
+            tip = bps::apply(policy.premium_paid, EXPIRY_TIP_BPS, Rounding::Down).ok_or(ErrorCode::MathOverflow)?;
+            tip = tip.min(vault.amount);
+
+            if tip > 0 {
+                let seeds = &[
+                    b"policy",
+                    policy.owner.as_ref(),
+                    policy.model.as_ref(),
+                    &[policy.bump],
+                ];
+                let signer = &[&seeds[..]];
+
+                let cpi_accounts = Transfer {
+                    from: vault.to_account_info(),
+                    to: cranker_token_account.to_account_info(),
+                    authority: policy.to_account_info(),
+                };
+                let cpi_program = ctx.accounts.token_program.to_account_info();
+                let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+                token::transfer(cpi_ctx, tip)?;
+            }
+        }
+
+        emit!(PolicyExpired {
+            policy_key: policy.key(),
+            cranker: ctx.accounts.cranker.key(),
+            tip,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrade an insurance policy created under an older layout
+    pub fn migrate_policy(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<InsurancePolicy>()
+    }
 }
 
+/// Share of the premium paid to whoever cranks `expire_policy` (0.5%)
+pub const EXPIRY_TIP_BPS: u64 = 50;
+
 // Account Structures
 
 #[account]
+#[derive(InitSpace)]
 pub struct InsurancePolicy {
     pub owner: Pubkey,
     pub model: Pubkey,
@@ -184,9 +254,42 @@ pub struct InsurancePolicy {
     pub expiry_time: i64,
     pub claim_paid: u64,
     pub bump: u8,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 64],         // Zeroed space for future fields
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+impl Versioned for InsurancePolicy {
+    const VERSION: u8 = 1;
+    // Unversioned accounts predate the version byte, which reads as zero
+    const APPEND_ONLY_SINCE: u8 = 0;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl InsurancePolicy {
+    /// Premium refunded by `cancel_policy` at `now` (pro-rated based on time
+    /// remaining, rounded down)
+    pub fn refund_at(&self, now: i64) -> Result<u64> {
+        let total_duration = self.expiry_time.saturating_sub(self.start_time);
+        let time_remaining = self.expiry_time.saturating_sub(now).min(total_duration);
+        if time_remaining <= 0 {
+            return Ok(0);
+        }
+        let refund = mul_div(self.premium_paid, time_remaining as u64, total_duration as u64, Rounding::Down)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(refund)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
 pub enum PolicyStatus {
     Active,
     Claimed,
@@ -202,7 +305,7 @@ pub struct PurchasePolicy<'info> {
     #[account(
         init,
         payer = owner,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 1 + 100,
+        space = InsurancePolicy::SPACE,
         seeds = [b"policy", owner.key().as_ref(), model_pubkey.as_ref()],
         bump
     )]
@@ -249,6 +352,24 @@ pub struct CancelPolicy<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct ExpirePolicy<'info> {
+    #[account(
+        mut,
+        seeds = [b"policy", policy.owner.as_ref(), policy.model.as_ref()],
+        bump
+    )]
+    pub policy: Account<'info, InsurancePolicy>,
+    pub cranker: Signer<'info>,
+    #[account(mut, constraint = insurance_vault.owner == policy.key() @ ErrorCode::InvalidVault)]
+    pub insurance_vault: Option<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = cranker_token_account.owner == cranker.key() @ ErrorCode::Unauthorized)]
+    pub cranker_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
+
+migrate_account_context!();
+
 // Events
 
 #[event]
@@ -277,6 +398,14 @@ pub struct PolicyCancelled {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct PolicyExpired {
+    pub policy_key: Pubkey,
+    pub cranker: Pubkey,
+    pub tip: u64,
+    pub timestamp: i64,
+}
+
 // Errors
 
 #[error_code]
@@ -289,4 +418,10 @@ pub enum ErrorCode {
     PolicyExpired,
     #[msg("Accuracy threshold not met for claim")]
     ThresholdNotMet,
+    #[msg("Policy has not expired yet")]
+    PolicyNotExpired,
+    #[msg("Vault is not owned by the policy")]
+    InvalidVault,
+    #[msg("Math overflow")]
+    MathOverflow,
 }
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use driftshield_versioning::{upgrade_account_data, VersioningError};
+use insurance::{InsurancePolicy, PolicyStatus, Versioned};
+
+/// Allocated by v0 `purchase_policy`, padding included
+const POLICY_V0_SPACE: usize = 8 + 32 + 32 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 1 + 100;
+
+/// Policy layout written by the unversioned program
+#[derive(AnchorSerialize)]
+struct PolicyV0 {
+    owner: Pubkey,
+    model: Pubkey,
+    coverage_amount: u64,
+    premium_paid: u64,
+    accuracy_threshold: u64,
+    status: PolicyStatus,
+    start_time: i64,
+    expiry_time: i64,
+    claim_paid: u64,
+    bump: u8,
+}
+
+fn v0_data(policy: &PolicyV0) -> Vec<u8> {
+    let mut data = InsurancePolicy::DISCRIMINATOR.to_vec();
+    policy.serialize(&mut data).unwrap();
+    data.resize(POLICY_V0_SPACE, 0);
+    data
+}
+
+#[test]
+fn migrates_v0_policy() {
+    let v0 = PolicyV0 {
+        owner: Pubkey::new_unique(),
+        model: Pubkey::new_unique(),
+        coverage_amount: 5_000_000,
+        premium_paid: 250_000,
+        accuracy_threshold: 9_000,
+        status: PolicyStatus::Claimed,
+        start_time: 1_700_000_000,
+        expiry_time: 1_702_592_000,
+        claim_paid: 5_000_000,
+        bump: 252,
+    };
+    // The v0 allocation already covers the current layout, so nothing grows
+    let mut data = v0_data(&v0);
+    assert!(data.len() >= InsurancePolicy::SPACE);
+
+    assert_eq!(upgrade_account_data::<InsurancePolicy>(&mut data).unwrap(), 0);
+    let policy = InsurancePolicy::try_deserialize(&mut data.as_slice()).unwrap();
+    assert_eq!((policy.owner, policy.model), (v0.owner, v0.model));
+    assert_eq!((policy.coverage_amount, policy.premium_paid, policy.accuracy_threshold), (5_000_000, 250_000, 9_000));
+    assert!(policy.status == PolicyStatus::Claimed);
+    assert_eq!((policy.start_time, policy.expiry_time), (v0.start_time, v0.expiry_time));
+    assert_eq!((policy.claim_paid, policy.bump), (5_000_000, 252));
+    assert_eq!(policy.version, InsurancePolicy::VERSION);
+
+    let err = upgrade_account_data::<InsurancePolicy>(&mut data).unwrap_err();
+    assert_eq!(err, VersioningError::AlreadyMigrated.into());
+}
@@ -1,4 +1,8 @@
+#![allow(clippy::result_large_err)]
+
 use anchor_lang::prelude::*;
+use driftshield_versioning::migrate_account_context;
+pub use driftshield_versioning::{AccountMigrated, Versioned};
 
 declare_id!("34HbFEsYeFa1NrdUyShWXKB36NZ5p4tCjogDbg2p98xm");
 
@@ -32,6 +36,7 @@ pub mod model_registry {
         model.last_check_at = clock.unix_timestamp;
         model.is_insured = false;
         model.has_active_market = false;
+        model.version = ModelAccount::VERSION;
 
         emit!(ModelRegistered {
             model_key: model.key(),
@@ -73,6 +78,7 @@ pub mod model_registry {
         receipt.drift_score = drift_score;
         receipt.metadata_uri = metadata_uri;
         receipt.timestamp = clock.unix_timestamp;
+        receipt.version = MonitoringReceipt::VERSION;
 
         // Update model stats
         model.current_accuracy = accuracy;
@@ -80,11 +86,7 @@ pub mod model_registry {
         model.last_check_at = clock.unix_timestamp;
 
         // Check for drift alert (>5% accuracy drop from baseline)
-        let accuracy_drop = if model.baseline_accuracy > accuracy {
-            model.baseline_accuracy - accuracy
-        } else {
-            0
-        };
+        let accuracy_drop = model.baseline_accuracy.saturating_sub(accuracy);
 
         if accuracy_drop > 500 {  // 5% = 500 basis points
             model.drift_alerts += 1;
@@ -144,16 +146,31 @@ pub mod model_registry {
 
         Ok(())
     }
+
+    /// Upgrade a model account created under an older layout
+    pub fn migrate_model(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<ModelAccount>()
+    }
+
+    /// Upgrade a monitoring receipt created under an older layout
+    pub fn migrate_receipt(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<MonitoringReceipt>()
+    }
 }
 
 // Account Structures
 
 #[account]
+#[derive(InitSpace)]
 pub struct ModelAccount {
     pub owner: Pubkey,
+    #[max_len(64)]
     pub model_id: String,          // Max 64 chars
+    #[max_len(128)]
     pub name: String,              // Max 128 chars
+    #[max_len(64)]
     pub model_type: String,        // Max 64 chars
+    #[max_len(64)]
     pub framework: String,         // Max 64 chars
     pub baseline_accuracy: u64,    // Basis points
     pub current_accuracy: u64,     // Basis points
@@ -164,9 +181,28 @@ pub struct ModelAccount {
     pub last_check_at: i64,
     pub is_insured: bool,
     pub has_active_market: bool,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 64],         // Zeroed space for future fields
+}
+
+impl Versioned for ModelAccount {
+    const VERSION: u8 = 1;
+    // Unversioned accounts predate the version byte, which reads as zero
+    const APPEND_ONLY_SINCE: u8 = 0;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct MonitoringReceipt {
     pub model: Pubkey,
     pub checker: Pubkey,
@@ -175,11 +211,30 @@ pub struct MonitoringReceipt {
     pub recall: u64,
     pub f1_score: u64,
     pub drift_score: u64,
+    #[max_len(256)]
     pub metadata_uri: String,      // Shadow Drive URI
     pub timestamp: i64,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 32],         // Zeroed space for future fields
+}
+
+impl Versioned for MonitoringReceipt {
+    const VERSION: u8 = 1;
+    // Unversioned accounts predate the version byte, which reads as zero
+    const APPEND_ONLY_SINCE: u8 = 0;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
 pub enum ModelStatus {
     Active,
     DriftDetected,
@@ -194,7 +249,7 @@ pub struct RegisterModel<'info> {
     #[account(
         init,
         payer = owner,
-        space = 8 + 32 + 64 + 128 + 64 + 64 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 1 + 1 + 100, // Add padding
+        space = ModelAccount::SPACE,
         seeds = [b"model", owner.key().as_ref(), model_id.as_bytes()],
         bump
     )]
@@ -211,7 +266,7 @@ pub struct SubmitReceipt<'info> {
     #[account(
         init,
         payer = owner,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 256 + 8 + 100,
+        space = MonitoringReceipt::SPACE,
     )]
     pub receipt: Account<'info, MonitoringReceipt>,
     #[account(mut)]
@@ -237,6 +292,8 @@ pub struct UpdateMarketStatus<'info> {
     pub market_program: AccountInfo<'info>,
 }
 
+migrate_account_context!();
+
 // Events
 
 #[event]
@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use driftshield_programs::{ModelAccount, ModelStatus, MonitoringReceipt, Versioned};
+use driftshield_versioning::upgrade_account_data;
+
+/// Allocated by v0 `register_model`, padding included
+const MODEL_V0_SPACE: usize = 8 + 32 + 64 + 128 + 64 + 64 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 1 + 1 + 100;
+/// Allocated by v0 `submit_monitoring_receipt`, padding included
+const RECEIPT_V0_SPACE: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 256 + 8 + 100;
+
+/// Model layout written by the unversioned program
+#[derive(AnchorSerialize)]
+struct ModelV0 {
+    owner: Pubkey,
+    model_id: String,
+    name: String,
+    model_type: String,
+    framework: String,
+    baseline_accuracy: u64,
+    current_accuracy: u64,
+    total_checks: u64,
+    drift_alerts: u64,
+    status: ModelStatus,
+    created_at: i64,
+    last_check_at: i64,
+    is_insured: bool,
+    has_active_market: bool,
+}
+
+/// Receipt layout written by the unversioned program
+#[derive(AnchorSerialize)]
+struct ReceiptV0 {
+    model: Pubkey,
+    checker: Pubkey,
+    accuracy: u64,
+    precision: u64,
+    recall: u64,
+    f1_score: u64,
+    drift_score: u64,
+    metadata_uri: String,
+    timestamp: i64,
+}
+
+/// Run the migration over v0 account data, grown to `T::SPACE` as `migrate_account` does
+fn migrate<T: Versioned + Discriminator, V: AnchorSerialize>(v0: &V, space: usize) -> T {
+    let mut data = T::DISCRIMINATOR.to_vec();
+    v0.serialize(&mut data).unwrap();
+    assert!(data.len() <= space, "v0 account overflows its allocation");
+    data.resize(space.max(T::SPACE), 0);
+
+    assert_eq!(upgrade_account_data::<T>(&mut data).unwrap(), 0);
+    T::try_deserialize(&mut data.as_slice()).unwrap()
+}
+
+#[test]
+fn migrates_v0_model() {
+    let v0 = ModelV0 {
+        owner: Pubkey::new_unique(),
+        model_id: "fraud-v3".to_string(),
+        name: "Fraud detector".to_string(),
+        model_type: "classifier".to_string(),
+        framework: "xgboost".to_string(),
+        baseline_accuracy: 9_400,
+        current_accuracy: 9_050,
+        total_checks: 12,
+        drift_alerts: 1,
+        status: ModelStatus::DriftDetected,
+        created_at: 1_700_000_000,
+        last_check_at: 1_700_086_400,
+        is_insured: true,
+        has_active_market: false,
+    };
+
+    let model: ModelAccount = migrate(&v0, MODEL_V0_SPACE);
+    assert_eq!(model.owner, v0.owner);
+    assert_eq!((model.model_id.as_str(), model.name.as_str()), ("fraud-v3", "Fraud detector"));
+    assert_eq!((model.model_type.as_str(), model.framework.as_str()), ("classifier", "xgboost"));
+    assert_eq!((model.baseline_accuracy, model.current_accuracy), (9_400, 9_050));
+    assert_eq!((model.total_checks, model.drift_alerts), (12, 1));
+    assert!(model.status == ModelStatus::DriftDetected);
+    assert_eq!((model.created_at, model.last_check_at), (v0.created_at, v0.last_check_at));
+    assert!(model.is_insured && !model.has_active_market);
+    assert_eq!(model.version, ModelAccount::VERSION);
+}
+
+#[test]
+fn migrates_v0_receipt() {
+    let v0 = ReceiptV0 {
+        model: Pubkey::new_unique(),
+        checker: Pubkey::new_unique(),
+        accuracy: 8_900,
+        precision: 9_100,
+        recall: 8_700,
+        f1_score: 8_895,
+        drift_score: 420,
+        metadata_uri: "https://shdw-drive.genesysgo.net/bucket/run-42.json".to_string(),
+        timestamp: 1_700_086_400,
+    };
+
+    let receipt: MonitoringReceipt = migrate(&v0, RECEIPT_V0_SPACE);
+    assert_eq!((receipt.model, receipt.checker), (v0.model, v0.checker));
+    assert_eq!((receipt.accuracy, receipt.precision, receipt.recall), (8_900, 9_100, 8_700));
+    assert_eq!((receipt.f1_score, receipt.drift_score), (8_895, 420));
+    assert_eq!(receipt.metadata_uri, v0.metadata_uri);
+    assert_eq!(receipt.timestamp, v0.timestamp);
+    assert_eq!(receipt.version, MonitoringReceipt::VERSION);
+}
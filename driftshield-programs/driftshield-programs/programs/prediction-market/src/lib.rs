@@ -1,5 +1,12 @@
+#![allow(clippy::result_large_err)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::keccak;
+use anchor_spl::token::{self, CloseAccount, Token, TokenAccount, Transfer};
+use driftshield_math::{self as math, bps, Fixed, Rounding};
+use driftshield_programs::{ModelAccount, MonitoringReceipt};
+use driftshield_versioning::{grow_account, migrate_account_context, VersioningError};
+pub use driftshield_versioning::{AccountMigrated, Versioned};
 
 declare_id!("APvSf7hDoZDyYgshb4LPm2mpBanbiWgdqJ53TKvKQ7Da");
 
@@ -7,6 +14,176 @@ declare_id!("APvSf7hDoZDyYgshb4LPm2mpBanbiWgdqJ53TKvKQ7Da");
 pub mod prediction_market {
     use super::*;
 
+    /// Initialize the protocol config (one per program; upgrade authority only)
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        creation_bond: u64,  // Refundable bond posted by market creators
+        creation_fee: u64,   // Non-refundable fee paid to the treasury
+        trading_fee_bps: u16,   // Fee charged on every buy and sell
+        referral_fee_bps: u16,  // Share of the trading fee paid to referrers
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            trading_fee_bps <= MAX_TRADING_FEE_BPS && referral_fee_bps <= 10000,
+            ErrorCode::InvalidFeeParameter
+        );
+
+        config.admin = ctx.accounts.admin.key();
+        config.treasury = ctx.accounts.treasury.key();
+        config.creation_bond = creation_bond;
+        config.creation_fee = creation_fee;
+        config.bump = *ctx.bumps.get("config").unwrap();
+        config.version = ProtocolConfig::VERSION;
+        config.trading_fee_bps = trading_fee_bps;
+        config.referral_fee_bps = referral_fee_bps;
+        config.referral_vault = ctx.accounts.referral_vault.key();
+
+        emit!(ConfigUpdated {
+            admin: config.admin,
+            treasury: config.treasury,
+            creation_bond,
+            creation_fee,
+            trading_fee_bps,
+            referral_fee_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Update protocol config (admin only)
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        new_admin: Pubkey,
+        creation_bond: u64,
+        creation_fee: u64,
+        trading_fee_bps: u16,
+        referral_fee_bps: u16,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            ctx.accounts.admin.key() == config.admin,
+            ErrorCode::Unauthorized
+        );
+
+        require!(
+            trading_fee_bps <= MAX_TRADING_FEE_BPS && referral_fee_bps <= 10000,
+            ErrorCode::InvalidFeeParameter
+        );
+
+        require!(
+            config.crank_tip <= creation_fee,
+            ErrorCode::InvalidCrankTip
+        );
+
+        config.admin = new_admin;
+        config.treasury = ctx.accounts.treasury.key();
+        config.creation_bond = creation_bond;
+        config.creation_fee = creation_fee;
+        config.trading_fee_bps = trading_fee_bps;
+        config.referral_fee_bps = referral_fee_bps;
+
+        emit!(ConfigUpdated {
+            admin: config.admin,
+            treasury: config.treasury,
+            creation_bond,
+            creation_fee,
+            trading_fee_bps,
+            referral_fee_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the tip paid to callers of the crank instructions (admin only).
+    /// Tips come out of `crank_vault`, a token account owned by the config PDA
+    /// that the protocol keeps funded; 0 disables tips. Capped at the creation
+    /// fee so tips stay in proportion to what markets pay in.
+    pub fn set_crank_tip(ctx: Context<SetCrankTip>, crank_tip: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            ctx.accounts.admin.key() == config.admin,
+            ErrorCode::Unauthorized
+        );
+
+        require!(
+            crank_tip <= config.creation_fee,
+            ErrorCode::InvalidCrankTip
+        );
+
+        config.crank_tip = crank_tip;
+        config.crank_vault = ctx.accounts.crank_vault.key();
+
+        emit!(CrankTipUpdated {
+            crank_tip,
+            crank_vault: config.crank_vault,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set protocol-wide exposure caps (admin only). Every market enforces the
+    /// stricter of these and its own limits; 0 leaves a cap to the market.
+    pub fn set_exposure_floors(
+        ctx: Context<SetExposureFloors>,
+        max_position_stake: u64,
+        max_wallet_oi_bps: u16,
+        max_price_impact_bps: u16,
+        wallet_oi_floor: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            ctx.accounts.admin.key() == config.admin,
+            ErrorCode::Unauthorized
+        );
+
+        require!(
+            max_wallet_oi_bps <= 10000 && max_price_impact_bps <= 10000,
+            ErrorCode::InvalidExposureLimit
+        );
+
+        config.max_position_stake = max_position_stake;
+        config.max_wallet_oi_bps = max_wallet_oi_bps;
+        config.max_price_impact_bps = max_price_impact_bps;
+        config.wallet_oi_floor = wallet_oi_floor;
+
+        emit!(ExposureFloorsUpdated {
+            max_position_stake,
+            max_wallet_oi_bps,
+            max_price_impact_bps,
+            wallet_oi_floor,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the key that attests drift settlements (admin only). Until one is
+    /// set, drift markets can only resolve NO through `finalize_drift_market`.
+    pub fn set_drift_attester(ctx: Context<SetDriftAttester>, drift_attester: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(
+            ctx.accounts.admin.key() == config.admin,
+            ErrorCode::Unauthorized
+        );
+
+        config.drift_attester = drift_attester;
+
+        emit!(DriftAttesterUpdated {
+            drift_attester,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
     /// Create a new prediction market with AMM (Constant Product)
     pub fn create_market(
         ctx: Context<CreateMarket>,
@@ -14,57 +191,64 @@ pub mod prediction_market {
         question: String,
         resolution_time: i64,
         min_stake: u64,
-        virtual_liquidity: u64,  // NEW: Virtual reserves for AMM
+        initial_liquidity: u64,  // Collateral the creator deposits to seed the AMM
+        allowlist_root: Option<[u8; 32]>,  // Merkle root of allowed traders (private markets)
     ) -> Result<()> {
-        let market = &mut ctx.accounts.market;
-        let clock = Clock::get()?;
+        let bump = *ctx.bumps.get("market").unwrap();
+        ctx.accounts.initialize(bump, MarketParams {
+            model: model_pubkey,
+            question,
+            resolution_time,
+            min_stake,
+            initial_liquidity,
+            allowlist_root,
+        })
+    }
 
-        require!(
-            resolution_time > clock.unix_timestamp,
-            ErrorCode::InvalidResolutionTime
-        );
+    /// Create a drift market from a registered model. The question is generated
+    /// from the model and the structured condition is stored so the market can be
+    /// settled from monitoring receipts with `settle_drift_market`.
+    pub fn create_drift_market(
+        ctx: Context<CreateDriftMarket>,
+        model_pubkey: Pubkey,
+        condition: DriftCondition,
+        resolution_time: i64,
+        min_stake: u64,
+        initial_liquidity: u64,
+        allowlist_root: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let model = &ctx.accounts.model;
 
-        require!(
-            virtual_liquidity >= 10,
-            ErrorCode::LiquidityTooLow
-        );
+        require_keys_eq!(model.key(), model_pubkey, ErrorCode::InvalidModel);
 
         require!(
-            virtual_liquidity <= 10000,
-            ErrorCode::LiquidityTooHigh
+            condition.threshold <= 10000,
+            ErrorCode::InvalidDriftThreshold
         );
 
-        market.creator = ctx.accounts.creator.key();
-        market.model = model_pubkey;
-        market.question = question;
-        market.yes_pool = 0;
-        market.no_pool = 0;
-        market.total_volume = 0;
-        market.status = MarketStatus::Open;
-        market.resolution_time = resolution_time;
-        market.resolved_at = 0;
-        market.winning_outcome = None;
-        market.min_stake = min_stake;
-        market.created_at = clock.unix_timestamp;
-        market.bump = *ctx.bumps.get("market").unwrap();
+        let question = condition.question(&model.name, resolution_time);
 
-        // AMM initialization (Constant Product: x * y = k)
-        market.amm_enabled = true;
-        market.virtual_yes_reserve = virtual_liquidity;
-        market.virtual_no_reserve = virtual_liquidity;
-        market.k_constant = virtual_liquidity
-            .checked_mul(virtual_liquidity)
-            .ok_or(ErrorCode::MathOverflow)?;
-        market.total_yes_shares = 0;
-        market.total_no_shares = 0;
+        let bump = *ctx.bumps.get("market").unwrap();
+        ctx.accounts.create.initialize(bump, MarketParams {
+            model: model_pubkey,
+            question,
+            resolution_time,
+            min_stake,
+            initial_liquidity,
+            allowlist_root,
+        })?;
 
-        emit!(MarketCreated {
+        let market = &mut ctx.accounts.create.market;
+        market.drift_condition = Some(condition);
+
+        emit!(DriftMarketCreated {
             market_key: market.key(),
-            creator: market.creator,
             model: model_pubkey,
-            question: market.question.clone(),
-            virtual_liquidity,
-            timestamp: clock.unix_timestamp,
+            metric: condition.metric,
+            comparator: condition.comparator,
+            threshold: condition.threshold,
+            resolution_time,
+            timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
@@ -75,76 +259,131 @@ pub mod prediction_market {
         ctx: Context<PlaceBet>,
         outcome: bool,  // true = YES, false = NO
         amount: u64,
+        allowlist_proof: Vec<[u8; 32]>,  // Empty for public markets
     ) -> Result<()> {
         let market = &mut ctx.accounts.market;
         let position = &mut ctx.accounts.position;
         let clock = Clock::get()?;
 
-        require!(
-            market.status == MarketStatus::Open,
-            ErrorCode::MarketClosed
-        );
+        // Session keys trade on behalf of their owner within the session's limits
+        let trader = trader_key(&ctx.accounts.user, &ctx.accounts.session);
+        if let Some(session) = ctx.accounts.session.as_mut() {
+            session.authorize(market.key(), amount, clock.unix_timestamp)?;
+            // Stake comes out of the session vault, never the owner's wallet
+            require_keys_eq!(ctx.accounts.user_token_account.key(), session.vault, ErrorCode::InvalidSessionVault);
+        }
 
-        require!(
-            clock.unix_timestamp < market.resolution_time,
-            ErrorCode::MarketExpired
-        );
+        if let Some(root) = market.allowlist_root {
+            require!(
+                verify_allowlist_proof(&allowlist_proof, root, &trader),
+                ErrorCode::NotAllowlisted
+            );
+        }
+
+        check_parent_market(market, ctx.accounts.parent_market.as_ref(), true)?;
 
+        // The referrer (if any) gets a slice of the protocol fee
+        let referrer = ctx.accounts.referral.as_ref().map(|r| r.referrer);
         require!(
-            amount >= market.min_stake,
-            ErrorCode::StakeTooLow
+            referrer != Some(trader),
+            ErrorCode::SelfReferral
         );
 
-        // Calculate shares using Constant Product AMM
-        let shares = if market.amm_enabled {
-            calculate_shares_out(market, outcome, amount)?
-        } else {
-            // Fallback to 1:1 for P2P markets
-            amount
-        };
+        let first_trade = position.market == Pubkey::default();
+        let fill = market.apply_buy(position, &ctx.accounts.config, outcome, amount, clock.unix_timestamp)?;
 
-        // Transfer tokens to market vault
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_token_account.to_account_info(),
-            to: ctx.accounts.market_vault.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
+        if fill.resumed {
+            emit!(MarketResumed {
+                market_key: market.key(),
+                authority: None,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        // Transfer the stake and LP fee to the market vault
+        let payer = TradePayer {
+            user: &ctx.accounts.user,
+            session: &ctx.accounts.session,
+            token_account: &ctx.accounts.user_token_account,
+            token_program: &ctx.accounts.token_program,
         };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, amount)?;
+        payer.transfer(ctx.accounts.market_vault.to_account_info(), fill.vault_amount)?;
 
-        // Update market pools and reserves
-        if outcome {
-            market.yes_pool = market.yes_pool.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
-            market.total_yes_shares = market.total_yes_shares.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
+        // Transfer protocol fee to treasury and referral slice to the referral vault
+        let referral_fee = if referrer.is_some() {
+            ctx.accounts.config.referral_fee(fill.fee - fill.lp_fee)?
         } else {
-            market.no_pool = market.no_pool.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
-            market.total_no_shares = market.total_no_shares.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
+            0
+        };
+        let protocol_fee = fill.fee - fill.lp_fee - referral_fee;
+        if protocol_fee > 0 {
+            payer.transfer(ctx.accounts.treasury.to_account_info(), protocol_fee)?;
+        }
+        if let Some(referral) = ctx.accounts.referral.as_mut() {
+            if referral_fee > 0 {
+                let referral_vault = ctx.accounts.referral_vault.as_ref()
+                    .ok_or(ErrorCode::MissingReferralVault)?;
+                payer.transfer(referral_vault.to_account_info(), referral_fee)?;
+            }
+            referral.accrue(amount, referral_fee)?;
+
+            emit!(ReferralRewardAccrued {
+                referrer: referral.referrer,
+                market_key: market.key(),
+                user: trader,
+                volume: amount,
+                reward: referral_fee,
+                timestamp: clock.unix_timestamp,
+            });
         }
-        market.total_volume = market.total_volume.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
 
-        // Update user position with shares
         position.market = market.key();
-        position.user = ctx.accounts.user.key();
-        position.yes_shares = position.yes_shares.checked_add(if outcome { shares } else { 0 })
-            .ok_or(ErrorCode::MathOverflow)?;
-        position.no_shares = position.no_shares.checked_add(if !outcome { shares } else { 0 })
-            .ok_or(ErrorCode::MathOverflow)?;
-        position.yes_stake = position.yes_stake.checked_add(if outcome { amount } else { 0 })
-            .ok_or(ErrorCode::MathOverflow)?;
-        position.no_stake = position.no_stake.checked_add(if !outcome { amount } else { 0 })
-            .ok_or(ErrorCode::MathOverflow)?;
-        position.total_stake = position.total_stake.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
-        position.claimed = false;
+        position.user = trader;
+        position.version = Position::VERSION;
+
+        let reward_stake_bump = ctx.bumps.get("reward_stake").copied();
+        sync_reward_stake(
+            market,
+            ctx.accounts.reward_pool.as_mut(),
+            ctx.accounts.reward_stake.as_mut(),
+            reward_stake_bump,
+            trader,
+            position.total_stake,
+            clock.unix_timestamp,
+        )?;
+
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.user = trader;
+        user_stats.bump = *ctx.bumps.get("user_stats").unwrap();
+        user_stats.version = UserStats::VERSION;
+        user_stats.record_trade(amount, fill.fee, first_trade, clock.unix_timestamp)?;
+
+        ctx.accounts.market_vault.reload()?;
+        market.assert_solvent(ctx.accounts.market_vault.amount)?;
+
+        record_price_history(market, ctx.accounts.price_history.as_ref(), clock.unix_timestamp, amount)?;
+
+        if fill.halted {
+            emit!(MarketHalted {
+                market_key: market.key(),
+                reference_price: market.breaker_ref_price,
+                yes_price: get_yes_price(market)?,
+                halted_until: market.halted_until,
+                timestamp: clock.unix_timestamp,
+            });
+        }
 
         emit!(BetPlaced {
             market_key: market.key(),
-            user: ctx.accounts.user.key(),
+            user: trader,
             outcome,
             amount,
-            shares,
-            yes_price: get_yes_price(market),
-            no_price: get_no_price(market),
+            shares: fill.shares,
+            fee: fill.fee,
+            fee_bps: fill.fee_bps,
+            referrer,
+            yes_price: get_yes_price(market)?,
+            no_price: get_no_price(market)?,
             timestamp: clock.unix_timestamp,
         });
 
@@ -161,39 +400,35 @@ pub mod prediction_market {
         let position = &mut ctx.accounts.position;
         let clock = Clock::get()?;
 
-        require!(
-            market.status == MarketStatus::Open,
-            ErrorCode::MarketClosed
-        );
-
-        require!(
-            clock.unix_timestamp < market.resolution_time,
-            ErrorCode::MarketExpired
-        );
+        let trader = trader_key(&ctx.accounts.user, &ctx.accounts.session);
+        if let Some(session) = ctx.accounts.session.as_mut() {
+            session.authorize(market.key(), 0, clock.unix_timestamp)?;
+            // Proceeds must go back to the owner, never to the session key
+            require_keys_eq!(ctx.accounts.user_token_account.owner, trader, ErrorCode::InvalidTokenOwner);
+        }
 
         require!(
-            position.user == ctx.accounts.user.key(),
+            position.user == trader,
             ErrorCode::Unauthorized
         );
 
-        // Check user has enough shares
-        let user_shares = if outcome {
-            position.yes_shares
-        } else {
-            position.no_shares
-        };
+        check_parent_market(market, ctx.accounts.parent_market.as_ref(), true)?;
 
+        let referrer = ctx.accounts.referral.as_ref().map(|r| r.referrer);
         require!(
-            user_shares >= shares,
-            ErrorCode::InsufficientShares
+            referrer != Some(trader),
+            ErrorCode::SelfReferral
         );
 
-        // Calculate SOL to return using AMM (reverse of buying)
-        let sol_out = if market.amm_enabled {
-            calculate_sol_out(market, outcome, shares)?
-        } else {
-            shares // 1:1 fallback
-        };
+        let fill = market.apply_sell(position, &ctx.accounts.config, outcome, shares, clock.unix_timestamp)?;
+
+        if fill.resumed {
+            emit!(MarketResumed {
+                market_key: market.key(),
+                authority: None,
+                timestamp: clock.unix_timestamp,
+            });
+        }
 
         // Transfer SOL from vault to user
         let seeds = &[
@@ -211,46 +446,92 @@ pub mod prediction_market {
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        token::transfer(cpi_ctx, sol_out)?;
+        token::transfer(cpi_ctx, fill.proceeds)?;
 
-        // Update market pools and reserves (reverse of buying)
-        if outcome {
-            market.yes_pool = market.yes_pool.checked_sub(sol_out).ok_or(ErrorCode::InsufficientLiquidity)?;
-            market.total_yes_shares = market.total_yes_shares.checked_sub(shares).ok_or(ErrorCode::InsufficientShares)?;
-            // Update virtual reserves
-            market.virtual_no_reserve = market.virtual_no_reserve.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
-            market.virtual_yes_reserve = (market.k_constant as u128)
-                .checked_div(market.virtual_no_reserve as u128)
-                .ok_or(ErrorCode::MathOverflow)? as u64;
+        // Transfer protocol fee to treasury and referral slice to the referral vault;
+        // the dynamic fee stays in the vault for the liquidity provider
+        let referral_fee = if referrer.is_some() {
+            ctx.accounts.config.referral_fee(fill.fee - fill.lp_fee)?
         } else {
-            market.no_pool = market.no_pool.checked_sub(sol_out).ok_or(ErrorCode::InsufficientLiquidity)?;
-            market.total_no_shares = market.total_no_shares.checked_sub(shares).ok_or(ErrorCode::InsufficientShares)?;
-            // Update virtual reserves
-            market.virtual_yes_reserve = market.virtual_yes_reserve.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
-            market.virtual_no_reserve = (market.k_constant as u128)
-                .checked_div(market.virtual_yes_reserve as u128)
-                .ok_or(ErrorCode::MathOverflow)? as u64;
+            0
+        };
+        let protocol_fee = fill.fee - fill.lp_fee - referral_fee;
+        if protocol_fee > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.market_vault.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, protocol_fee)?;
         }
-        market.total_volume = market.total_volume.checked_sub(sol_out).ok_or(ErrorCode::MathOverflow)?;
+        if let Some(referral) = ctx.accounts.referral.as_mut() {
+            if referral_fee > 0 {
+                let referral_vault = ctx.accounts.referral_vault.as_ref()
+                    .ok_or(ErrorCode::MissingReferralVault)?;
+                let cpi_accounts = Transfer {
+                    from: ctx.accounts.market_vault.to_account_info(),
+                    to: referral_vault.to_account_info(),
+                    authority: market.to_account_info(),
+                };
+                let cpi_program = ctx.accounts.token_program.to_account_info();
+                let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+                token::transfer(cpi_ctx, referral_fee)?;
+            }
+            referral.accrue(fill.collateral_out, referral_fee)?;
 
-        // Update user position
-        if outcome {
-            position.yes_shares = position.yes_shares.checked_sub(shares).ok_or(ErrorCode::InsufficientShares)?;
-            position.yes_stake = position.yes_stake.checked_sub(sol_out).ok_or(ErrorCode::MathOverflow)?;
-        } else {
-            position.no_shares = position.no_shares.checked_sub(shares).ok_or(ErrorCode::InsufficientShares)?;
-            position.no_stake = position.no_stake.checked_sub(sol_out).ok_or(ErrorCode::MathOverflow)?;
+            emit!(ReferralRewardAccrued {
+                referrer: referral.referrer,
+                market_key: market.key(),
+                user: trader,
+                volume: fill.collateral_out,
+                reward: referral_fee,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        let reward_stake_bump = ctx.bumps.get("reward_stake").copied();
+        sync_reward_stake(
+            market,
+            ctx.accounts.reward_pool.as_mut(),
+            ctx.accounts.reward_stake.as_mut(),
+            reward_stake_bump,
+            trader,
+            position.total_stake,
+            clock.unix_timestamp,
+        )?;
+
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.record_trade(fill.collateral_out, fill.fee, false, clock.unix_timestamp)?;
+        user_stats.record_pnl(signed_pnl(fill.collateral_out, fill.released_stake)?)?;
+
+        ctx.accounts.market_vault.reload()?;
+        market.assert_solvent(ctx.accounts.market_vault.amount)?;
+
+        record_price_history(market, ctx.accounts.price_history.as_ref(), clock.unix_timestamp, fill.collateral_out)?;
+
+        if fill.halted {
+            emit!(MarketHalted {
+                market_key: market.key(),
+                reference_price: market.breaker_ref_price,
+                yes_price: get_yes_price(market)?,
+                halted_until: market.halted_until,
+                timestamp: clock.unix_timestamp,
+            });
         }
-        position.total_stake = position.total_stake.checked_sub(sol_out).ok_or(ErrorCode::MathOverflow)?;
 
         emit!(SharesSold {
             market_key: market.key(),
-            user: ctx.accounts.user.key(),
+            user: trader,
             outcome,
             shares,
-            sol_received: sol_out,
-            yes_price: get_yes_price(market),
-            no_price: get_no_price(market),
+            sol_received: fill.proceeds,
+            fee: fill.fee,
+            fee_bps: fill.fee_bps,
+            referrer,
+            yes_price: get_yes_price(market)?,
+            no_price: get_no_price(market)?,
             timestamp: clock.unix_timestamp,
         });
 
@@ -270,19 +551,10 @@ pub mod prediction_market {
             ErrorCode::Unauthorized
         );
 
-        require!(
-            market.status == MarketStatus::Open,
-            ErrorCode::MarketAlreadyResolved
-        );
-
-        require!(
-            clock.unix_timestamp >= market.resolution_time,
-            ErrorCode::MarketNotExpired
-        );
+        // Conditional markets only resolve once the parent condition holds
+        check_parent_market(market, ctx.accounts.parent_market.as_ref(), false)?;
 
-        market.status = MarketStatus::Resolved;
-        market.resolved_at = clock.unix_timestamp;
-        market.winning_outcome = Some(outcome);
+        market.resolve(outcome, clock.unix_timestamp)?;
 
         emit!(MarketResolved {
             market_key: market.key(),
@@ -297,324 +569,4082 @@ pub mod prediction_market {
         Ok(())
     }
 
-    /// Claim winnings from a resolved market (AMM with shares)
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
-        let market = &ctx.accounts.market;
-        let position = &mut ctx.accounts.position;
+    /// Resolve a drift market YES from a monitoring receipt that meets its
+    /// condition. Receipts are self-reported by the model owner, so the
+    /// protocol's drift attester must co-sign. Receipts must be for the
+    /// market's model and fall between market creation and `resolution_time`.
+    /// Markets that never see such a receipt resolve NO through `finalize_drift_market`.
+    pub fn settle_drift_market(ctx: Context<SettleDriftMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let receipt = &ctx.accounts.receipt;
+        let clock = Clock::get()?;
+
+        let condition = market.drift_condition.ok_or(ErrorCode::NotDriftMarket)?;
 
         require!(
-            market.status == MarketStatus::Resolved,
-            ErrorCode::MarketNotResolved
+            market.status.is_unresolved(),
+            ErrorCode::MarketAlreadyResolved
         );
 
+        require_keys_eq!(receipt.model, market.model, ErrorCode::InvalidModel);
+
         require!(
-            position.user == ctx.accounts.user.key(),
-            ErrorCode::Unauthorized
+            receipt.timestamp >= market.created_at && receipt.timestamp <= market.resolution_time,
+            ErrorCode::ReceiptOutsideWindow
         );
 
         require!(
-            !position.claimed,
-            ErrorCode::AlreadyClaimed
+            condition.is_met(receipt),
+            ErrorCode::DriftConditionNotMet
         );
 
-        let winning_outcome = market.winning_outcome.ok_or(ErrorCode::NoWinningOutcome)?;
-
-        let user_winning_shares = if winning_outcome {
-            position.yes_shares
-        } else {
-            position.no_shares
-        };
-
-        require!(user_winning_shares > 0, ErrorCode::NoWinningStake);
-
-        // Calculate payout using shares
-        // Payout = (user_shares / total_winning_shares) * total_pool
-        let total_pool = market.yes_pool.checked_add(market.no_pool)
-            .ok_or(ErrorCode::MathOverflow)?;
-
-        let total_winning_shares = if winning_outcome {
-            market.total_yes_shares
-        } else {
-            market.total_no_shares
-        };
+        check_parent_market(market, ctx.accounts.parent_market.as_ref(), false)?;
 
-        require!(total_winning_shares > 0, ErrorCode::NoWinningStake);
+        market.update_price_accumulators(clock.unix_timestamp)?;
+        market.set_outcome(true, clock.unix_timestamp)?;
 
-        // Use u128 for intermediate calculation to prevent overflow
-        let payout = (user_winning_shares as u128)
-            .checked_mul(total_pool as u128)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(total_winning_shares as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+        emit!(MarketResolved {
+            market_key: market.key(),
+            winning_outcome: true,
+            yes_pool: market.yes_pool,
+            no_pool: market.no_pool,
+            total_yes_shares: market.total_yes_shares,
+            total_no_shares: market.total_no_shares,
+            timestamp: clock.unix_timestamp,
+        });
 
-        // Transfer winnings from vault to user
-        let seeds = &[
-            b"market",
-            market.creator.as_ref(),
-            market.model.as_ref(),
-            &[market.bump],
-        ];
-        let signer = &[&seeds[..]];
+        Ok(())
+    }
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.market_vault.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
-            authority: market.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        token::transfer(cpi_ctx, payout)?;
+    /// Close trading on a market past its `resolution_time` (permissionless crank).
+    /// Folds the final price into the TWAP and pays the caller the crank tip.
+    pub fn close_market(ctx: Context<CloseMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
 
-        position.claimed = true;
+        market.close_trading(clock.unix_timestamp)?;
 
-        emit!(WinningsClaimed {
+        emit!(MarketClosed {
             market_key: market.key(),
-            user: ctx.accounts.user.key(),
-            shares: user_winning_shares,
-            payout,
-            timestamp: Clock::get()?.unix_timestamp,
+            yes_price: get_yes_price(market)?,
+            no_price: get_no_price(market)?,
+            timestamp: clock.unix_timestamp,
         });
 
+        ctx.accounts.tip.pay(market.key(), clock.unix_timestamp)?;
+
         Ok(())
     }
 
-    /// Get current market prices (view function - call off-chain)
-    pub fn get_prices(ctx: Context<GetPrices>) -> Result<(u64, u64)> {
-        let market = &ctx.accounts.market;
-        let yes_price = get_yes_price(market);
-        let no_price = get_no_price(market);
-        Ok((yes_price, no_price))
-    }
-}
+    /// Resolve a drift market NO once `DRIFT_SETTLEMENT_GRACE` has passed after
+    /// `resolution_time` without anyone settling it YES (permissionless crank).
+    /// Pays the caller the crank tip.
+    pub fn finalize_drift_market(ctx: Context<FinalizeDriftMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
 
-// AMM Helper Functions
+        require!(
+            market.drift_condition.is_some(),
+            ErrorCode::NotDriftMarket
+        );
 
-/// Calculate shares out using Constant Product AMM (x * y = k)
-/// When betting on YES:
-/// - Add bet to yes_reserve
-/// - Calculate new no_reserve to maintain k
-/// - Shares = old_no_reserve - new_no_reserve
-fn calculate_shares_out(market: &Market, outcome: bool, bet_amount: u64) -> Result<u64> {
-    if outcome {
-        // Betting on YES
-        let new_yes_reserve = market.virtual_yes_reserve
-            .checked_add(bet_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            market.status.is_unresolved(),
+            ErrorCode::MarketAlreadyResolved
+        );
 
-        // k = x * y, so new_y = k / new_x
-        let new_no_reserve = (market.k_constant as u128)
-            .checked_div(new_yes_reserve as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+        require!(
+            clock.unix_timestamp >= market.resolution_time.saturating_add(DRIFT_SETTLEMENT_GRACE),
+            ErrorCode::SettlementGraceActive
+        );
 
-        let shares = market.virtual_no_reserve
-            .checked_sub(new_no_reserve)
-            .ok_or(ErrorCode::InsufficientLiquidity)?;
+        check_parent_market(market, ctx.accounts.parent_market.as_ref(), false)?;
 
-        Ok(shares)
-    } else {
-        // Betting on NO
-        let new_no_reserve = market.virtual_no_reserve
-            .checked_add(bet_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+        market.update_price_accumulators(clock.unix_timestamp)?;
+        market.set_outcome(false, clock.unix_timestamp)?;
 
-        let new_yes_reserve = (market.k_constant as u128)
-            .checked_div(new_no_reserve as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+        emit!(MarketResolved {
+            market_key: market.key(),
+            winning_outcome: false,
+            yes_pool: market.yes_pool,
+            no_pool: market.no_pool,
+            total_yes_shares: market.total_yes_shares,
+            total_no_shares: market.total_no_shares,
+            timestamp: clock.unix_timestamp,
+        });
 
-        let shares = market.virtual_yes_reserve
-            .checked_sub(new_yes_reserve)
-            .ok_or(ErrorCode::InsufficientLiquidity)?;
+        ctx.accounts.tip.pay(market.key(), clock.unix_timestamp)?;
 
-        Ok(shares)
+        Ok(())
+    }
+
+    /// Void a non-drift market its creator never resolved, once
+    /// `ABANDONED_MARKET_GRACE` has passed after `resolution_time`
+    /// (permissionless crank). Positions are refunded through `claim_refund`,
+    /// the creator bond is slashed to the treasury and the caller is paid the
+    /// crank tip.
+    pub fn void_abandoned_market(ctx: Context<VoidAbandonedMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        // Drift markets always resolve through finalize_drift_market
+        require!(
+            market.drift_condition.is_none(),
+            ErrorCode::DriftMarketResolution
+        );
+
+        require!(
+            clock.unix_timestamp >= market.resolution_time.saturating_add(ABANDONED_MARKET_GRACE),
+            ErrorCode::AbandonGraceActive
+        );
+
+        market.update_price_accumulators(clock.unix_timestamp)?;
+        let bond_slashed = market.cancel(clock.unix_timestamp)?;
+        if bond_slashed > 0 {
+            let seeds = &[
+                b"market",
+                market.creator.as_ref(),
+                market.model.as_ref(),
+                &[market.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.bond_vault.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.tip.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, bond_slashed)?;
+        }
+
+        emit!(AbandonedMarketVoided {
+            market_key: market.key(),
+            bond_slashed,
+            refund_pool: market.refund_pool,
+            timestamp: clock.unix_timestamp,
+        });
+
+        ctx.accounts.tip.pay(market.key(), clock.unix_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Claim winnings from a resolved market (AMM with shares)
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let position = &mut ctx.accounts.position;
+        let now = Clock::get()?.unix_timestamp;
+
+        let trader = trader_key(&ctx.accounts.user, &ctx.accounts.session);
+        if let Some(session) = ctx.accounts.session.as_mut() {
+            session.authorize(market.key(), 0, now)?;
+            require_keys_eq!(ctx.accounts.user_token_account.owner, trader, ErrorCode::InvalidTokenOwner);
+        }
+
+        require!(
+            position.user == trader,
+            ErrorCode::Unauthorized
+        );
+
+        let (user_winning_shares, payout) = market.claim_winnings(position)?;
+
+        // Transfer winnings from vault to user
+        let seeds = &[
+            b"market",
+            market.creator.as_ref(),
+            market.model.as_ref(),
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.market_vault.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: market.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, payout)?;
+
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.user = trader;
+        user_stats.bump = *ctx.bumps.get("user_stats").unwrap();
+        user_stats.version = UserStats::VERSION;
+        user_stats.record_resolution(signed_pnl(payout, position.total_stake)?, true)?;
+
+        ctx.accounts.market_vault.reload()?;
+        market.assert_solvent(ctx.accounts.market_vault.amount)?;
+
+        emit!(WinningsClaimed {
+            market_key: market.key(),
+            user: trader,
+            shares: user_winning_shares,
+            payout,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Record a losing position in its owner's stats (permissionless, so losses
+    /// can't be hidden by never touching the position again)
+    pub fn settle_losing_position(ctx: Context<SettleLosingPosition>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let position = &mut ctx.accounts.position;
+
+        require!(
+            market.status == MarketStatus::Resolved,
+            ErrorCode::MarketNotResolved
+        );
+
+        require!(
+            !position.claimed,
+            ErrorCode::AlreadyClaimed
+        );
+
+        let (user_winning_shares, _) = calculate_payout(market, position)?;
+        require!(
+            user_winning_shares == 0 && position.total_stake > 0,
+            ErrorCode::NotALosingPosition
+        );
+
+        ctx.accounts.user_stats.record_resolution(signed_pnl(0, position.total_stake)?, false)?;
+        position.claimed = true;
+
+        emit!(LosingPositionSettled {
+            market_key: market.key(),
+            user: position.user,
+            stake_lost: position.total_stake,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Claim winnings from many resolved markets in one transaction.
+    /// remaining_accounts: (market, position, market_vault) triples, all writable.
+    /// Unresolved, already-claimed and losing positions are skipped.
+    pub fn claim_many<'info>(ctx: Context<'_, '_, '_, 'info, ClaimMany<'info>>) -> Result<()> {
+        let user = ctx.accounts.user.key();
+        let clock = Clock::get()?;
+        let triples = ctx.remaining_accounts.chunks_exact(3);
+
+        require!(
+            !ctx.remaining_accounts.is_empty() && triples.remainder().is_empty(),
+            ErrorCode::InvalidClaimAccounts
+        );
+
+        let mut claimed_count: u32 = 0;
+        let mut skipped_count: u32 = 0;
+        let mut total_payout: u64 = 0;
+
+        for triple in triples {
+            let mut market: Account<'info, Market> = Account::try_from(&triple[0])?;
+            let mut position: Account<'info, Position> = Account::try_from(&triple[1])?;
+            let mut market_vault: Account<'info, TokenAccount> = Account::try_from(&triple[2])?;
+
+            require!(
+                market_vault.key() == market.vault,
+                ErrorCode::InvalidMarketVault
+            );
+
+            let market_key = market.key();
+            let (user_winning_shares, payout) = match market.claim_in_batch(market_key, &mut position, user)? {
+                Some(claim) => claim,
+                None => {
+                    skipped_count += 1;
+                    continue;
+                }
+            };
+
+            let seeds = &[
+                b"market",
+                market.creator.as_ref(),
+                market.model.as_ref(),
+                &[market.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: market_vault.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, payout)?;
+
+            ctx.accounts.user_stats.record_resolution(signed_pnl(payout, position.total_stake)?, true)?;
+
+            market_vault.reload()?;
+            market.assert_solvent(market_vault.amount)?;
+
+            // Persist before the next triple so duplicates are seen as claimed
+            market.exit(ctx.program_id)?;
+            position.exit(ctx.program_id)?;
+
+            emit!(WinningsClaimed {
+                market_key: market.key(),
+                user,
+                shares: user_winning_shares,
+                payout,
+                timestamp: clock.unix_timestamp,
+            });
+
+            claimed_count += 1;
+            total_payout = total_payout.checked_add(payout).ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        emit!(BatchClaimed {
+            user,
+            claimed_count,
+            skipped_count,
+            total_payout,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Authorize an ephemeral key to trade on the owner's behalf. `spend_cap` is
+    /// escrowed in `session_vault`, a token account owned by the session PDA, and
+    /// the key can only stake it through `place_bet` on the listed markets
+    /// (empty = any market) until `expires_at`.
+    pub fn create_session_key(
+        ctx: Context<CreateSessionKey>,
+        session_key: Pubkey,
+        spend_cap: u64,
+        allowed_markets: Vec<Pubkey>,
+        expires_at: i64,
+    ) -> Result<()> {
+        let session = &mut ctx.accounts.session;
+        let clock = Clock::get()?;
+
+        require!(
+            session_key != ctx.accounts.owner.key(),
+            ErrorCode::InvalidSessionKey
+        );
+
+        require!(
+            expires_at > clock.unix_timestamp,
+            ErrorCode::InvalidSessionKey
+        );
+
+        require!(
+            allowed_markets.len() <= MAX_SESSION_MARKETS,
+            ErrorCode::TooManySessionMarkets
+        );
+
+        // Escrow the spend cap; the session key can only move it through place_bet
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.owner_token_account.to_account_info(),
+            to: ctx.accounts.session_vault.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, spend_cap)?;
+
+        session.owner = ctx.accounts.owner.key();
+        session.session_key = session_key;
+        session.vault = ctx.accounts.session_vault.key();
+        session.spend_cap = spend_cap;
+        session.spent = 0;
+        session.allowed_markets = allowed_markets;
+        session.expires_at = expires_at;
+        session.created_at = clock.unix_timestamp;
+        session.bump = *ctx.bumps.get("session").unwrap();
+        session.version = SessionKey::VERSION;
+
+        emit!(SessionKeyCreated {
+            owner: session.owner,
+            session_key,
+            vault: session.vault,
+            spend_cap,
+            allowed_markets: session.allowed_markets.clone(),
+            expires_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Revoke a session key: return the unspent escrow to the owner and close
+    /// the session vault and account
+    pub fn revoke_session_key(ctx: Context<RevokeSessionKey>) -> Result<()> {
+        let session = &ctx.accounts.session;
+
+        let seeds = &[
+            b"session",
+            session.owner.as_ref(),
+            session.session_key.as_ref(),
+            &[session.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let refund = ctx.accounts.session_vault.amount;
+        if refund > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.session_vault.to_account_info(),
+                to: ctx.accounts.owner_token_account.to_account_info(),
+                authority: session.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, refund)?;
+        }
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.session_vault.to_account_info(),
+            destination: ctx.accounts.owner.to_account_info(),
+            authority: session.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+
+        emit!(SessionKeyRevoked {
+            owner: session.owner,
+            session_key: session.session_key,
+            spent: session.spent,
+            refund,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sponsor liquidity mining on a market. The sponsor (the market creator or
+    /// the protocol admin) funds `emission_rate` per second until
+    /// `min(now + duration, resolution_time)`; open positions earn in proportion
+    /// to their stake over time. More markets can join with `add_reward_market`.
+    pub fn create_reward_pool(
+        ctx: Context<CreateRewardPool>,
+        pool_id: u64,        // Distinguishes pools funded by the same sponsor
+        emission_rate: u64,  // Reward tokens per second
+        duration: i64,       // Seconds
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let pool = &mut ctx.accounts.reward_pool;
+        let clock = Clock::get()?;
+
+        require!(
+            emission_rate > 0 && duration > 0,
+            ErrorCode::InvalidRewardParams
+        );
+
+        let end_time = clock.unix_timestamp.saturating_add(duration).min(market.resolution_time);
+        require!(
+            end_time > clock.unix_timestamp,
+            ErrorCode::MarketClosed
+        );
+
+        let total_rewards = emission_rate
+            .checked_mul((end_time - clock.unix_timestamp) as u64)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        require!(
+            total_rewards >= MIN_REWARD_POOL_FUNDING,
+            ErrorCode::RewardPoolUnderfunded
+        );
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.sponsor_token_account.to_account_info(),
+            to: ctx.accounts.reward_vault.to_account_info(),
+            authority: ctx.accounts.sponsor.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, total_rewards)?;
+
+        pool.sponsor = ctx.accounts.sponsor.key();
+        pool.pool_id = pool_id;
+        pool.reward_vault = ctx.accounts.reward_vault.key();
+        pool.emission_rate = emission_rate;
+        pool.start_time = clock.unix_timestamp;
+        pool.end_time = end_time;
+        pool.last_update = clock.unix_timestamp;
+        pool.reward_per_share = 0;
+        // Positions opened before the pool start earning on their next trade
+        pool.total_staked = 0;
+        pool.total_funded = total_rewards;
+        pool.unallocated = 0;
+        pool.bump = *ctx.bumps.get("reward_pool").unwrap();
+        pool.version = RewardPool::VERSION;
+
+        attach_reward_pool(market, pool, &ctx.accounts.config, clock.unix_timestamp)?;
+
+        emit!(RewardPoolCreated {
+            market_key: market.key(),
+            reward_pool: pool.key(),
+            sponsor: pool.sponsor,
+            emission_rate,
+            start_time: pool.start_time,
+            end_time,
+            total_rewards,
+        });
+
+        Ok(())
+    }
+
+    /// Add another market to a running reward pool (sponsor only; the sponsor
+    /// must be the market's creator or the protocol admin). The market must stay
+    /// open until the pool ends.
+    pub fn add_reward_market(ctx: Context<AddRewardMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let pool = &ctx.accounts.reward_pool;
+        let clock = Clock::get()?;
+
+        attach_reward_pool(market, pool, &ctx.accounts.config, clock.unix_timestamp)?;
+
+        emit!(RewardMarketAdded {
+            reward_pool: pool.key(),
+            market_key: market.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Claim liquidity mining rewards accrued on a market
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        let pool = &mut ctx.accounts.reward_pool;
+        let stake = &mut ctx.accounts.reward_stake;
+        let clock = Clock::get()?;
+
+        pool.update(clock.unix_timestamp)?;
+        let staked = stake.staked;
+        stake.sync(pool, staked)?;
+
+        let amount = stake.pending;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+
+        let market_key = ctx.accounts.market.key();
+        let pool_id = pool.pool_id.to_le_bytes();
+        let seeds = &[
+            b"reward_pool",
+            pool.sponsor.as_ref(),
+            pool_id.as_ref(),
+            &[pool.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reward_vault.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: pool.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        stake.pending = 0;
+        stake.total_claimed = stake.total_claimed.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(RewardsClaimed {
+            market_key,
+            user: stake.user,
+            amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Return rewards emitted while nothing was staked to the sponsor
+    pub fn withdraw_unallocated_rewards(ctx: Context<WithdrawUnallocatedRewards>) -> Result<()> {
+        let pool = &mut ctx.accounts.reward_pool;
+        let clock = Clock::get()?;
+
+        pool.update(clock.unix_timestamp)?;
+
+        let amount = pool.unallocated;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+
+        let pool_id = pool.pool_id.to_le_bytes();
+        let seeds = &[
+            b"reward_pool",
+            pool.sponsor.as_ref(),
+            pool_id.as_ref(),
+            &[pool.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reward_vault.to_account_info(),
+            to: ctx.accounts.sponsor_token_account.to_account_info(),
+            authority: pool.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        pool.unallocated = 0;
+
+        emit!(UnallocatedRewardsWithdrawn {
+            reward_pool: pool.key(),
+            sponsor: pool.sponsor,
+            amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Create the referral account that accrues a referrer's share of trading fees
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        let referral = &mut ctx.accounts.referral;
+        let clock = Clock::get()?;
+
+        referral.referrer = ctx.accounts.referrer.key();
+        referral.referred_volume = 0;
+        referral.referred_trades = 0;
+        referral.total_earned = 0;
+        referral.unclaimed = 0;
+        referral.total_claimed = 0;
+        referral.created_at = clock.unix_timestamp;
+        referral.bump = *ctx.bumps.get("referral").unwrap();
+        referral.version = ReferralAccount::VERSION;
+
+        emit!(ReferrerRegistered {
+            referrer: referral.referrer,
+            referral_key: referral.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw all accrued referral rewards
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        let referral = &mut ctx.accounts.referral;
+        let config = &ctx.accounts.config;
+
+        require!(
+            ctx.accounts.referrer.key() == referral.referrer,
+            ErrorCode::Unauthorized
+        );
+
+        let amount = referral.unclaimed;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+
+        let seeds = &[b"config".as_ref(), &[config.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.referral_vault.to_account_info(),
+            to: ctx.accounts.referrer_token_account.to_account_info(),
+            authority: config.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        referral.unclaimed = 0;
+        referral.total_claimed = referral.total_claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(ReferralRewardsClaimed {
+            referrer: referral.referrer,
+            amount,
+            total_claimed: referral.total_claimed,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Return the creator bond after a clean resolution
+    pub fn reclaim_creator_bond(ctx: Context<ReclaimCreatorBond>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(
+            ctx.accounts.creator.key() == market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        // Resolved markets and markets voided by their parent get the bond back;
+        // markets cancelled as invalid were already slashed (bond_settled)
+        require!(
+            !market.status.is_unresolved(),
+            ErrorCode::MarketNotResolved
+        );
+
+        require!(
+            !market.bond_settled,
+            ErrorCode::BondAlreadySettled
+        );
+
+        let seeds = &[
+            b"market",
+            market.creator.as_ref(),
+            market.model.as_ref(),
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.bond_vault.to_account_info(),
+            to: ctx.accounts.creator_token_account.to_account_info(),
+            authority: market.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, market.creator_bond)?;
+
+        market.bond_settled = true;
+
+        emit!(CreatorBondReturned {
+            market_key: market.key(),
+            creator: market.creator,
+            amount: market.creator_bond,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel an invalid market (admin only); slashes the creator bond to the treasury
+    pub fn cancel_market(ctx: Context<CancelMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.admin.key() == ctx.accounts.config.admin,
+            ErrorCode::Unauthorized
+        );
+
+        let bond_slashed = market.cancel(clock.unix_timestamp)?;
+        if bond_slashed > 0 {
+            let seeds = &[
+                b"market",
+                market.creator.as_ref(),
+                market.model.as_ref(),
+                &[market.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.bond_vault.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, bond_slashed)?;
+        }
+
+        emit!(MarketCancelled {
+            market_key: market.key(),
+            bond_slashed,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Make this market conditional on `parent_market` resolving to `parent_outcome`.
+    /// Only allowed before the market has any trades.
+    pub fn link_parent_market(
+        ctx: Context<LinkParentMarket>,
+        parent_outcome: bool,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let parent = &ctx.accounts.parent_market;
+
+        require!(
+            ctx.accounts.creator.key() == market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        require!(
+            market.status == MarketStatus::Open
+                && market.total_yes_shares == 0
+                && market.total_no_shares == 0,
+            ErrorCode::MarketHasTrades
+        );
+
+        // Parents must resolve strictly earlier, so links can never form a cycle
+        require!(
+            parent.status.is_unresolved()
+                && parent.resolution_time < market.resolution_time,
+            ErrorCode::InvalidParentMarket
+        );
+
+        market.parent_market = Some(parent.key());
+        market.parent_outcome = parent_outcome;
+
+        emit!(ParentMarketLinked {
+            market_key: market.key(),
+            parent_market: parent.key(),
+            parent_outcome,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Void a conditional market whose parent resolved the other way (permissionless).
+    /// Positions are then refunded through `claim_refund`.
+    pub fn void_conditional_market(ctx: Context<VoidConditionalMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let parent = &ctx.accounts.parent_market;
+        let clock = Clock::get()?;
+
+        require!(
+            market.parent_market == Some(parent.key()),
+            ErrorCode::InvalidParentMarket
+        );
+
+        require!(
+            market.status.is_unresolved(),
+            ErrorCode::MarketAlreadyResolved
+        );
+
+        let parent_failed = match parent.status {
+            MarketStatus::Open | MarketStatus::Closed => false,
+            MarketStatus::Resolved => parent.winning_outcome != Some(market.parent_outcome),
+            MarketStatus::Cancelled => true,
+        };
+        require!(parent_failed, ErrorCode::ParentConditionNotFailed);
+
+        market.update_price_accumulators(clock.unix_timestamp)?;
+        market.void(clock.unix_timestamp);
+
+        emit!(ConditionalMarketVoided {
+            market_key: market.key(),
+            parent_market: parent.key(),
+            refund_pool: market.refund_pool,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Refund a position's net stake from a cancelled market
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let position = &mut ctx.accounts.position;
+
+        require!(
+            position.user == ctx.accounts.user.key(),
+            ErrorCode::Unauthorized
+        );
+
+        let refund = market.claim_refund(position)?;
+
+        if refund > 0 {
+            let seeds = &[
+                b"market",
+                market.creator.as_ref(),
+                market.model.as_ref(),
+                &[market.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.market_vault.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, refund)?;
+        }
+
+        ctx.accounts.market_vault.reload()?;
+        market.assert_solvent(ctx.accounts.market_vault.amount)?;
+
+        emit!(RefundClaimed {
+            market_key: market.key(),
+            user: ctx.accounts.user.key(),
+            refund,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw the creator's seed liquidity share after resolution or cancellation
+    pub fn withdraw_liquidity(ctx: Context<WithdrawLiquidity>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(
+            ctx.accounts.creator.key() == market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        let amount = market.withdraw_liquidity()?;
+
+        let seeds = &[
+            b"market",
+            market.creator.as_ref(),
+            market.model.as_ref(),
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.market_vault.to_account_info(),
+            to: ctx.accounts.creator_token_account.to_account_info(),
+            authority: market.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        ctx.accounts.market_vault.reload()?;
+        market.assert_solvent(ctx.accounts.market_vault.amount)?;
+
+        emit!(LiquidityWithdrawn {
+            market_key: market.key(),
+            creator: market.creator,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Replace (or clear) the Merkle root of wallets allowed to trade
+    pub fn set_allowlist_root(
+        ctx: Context<SetAllowlistRoot>,
+        allowlist_root: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.creator.key() == market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        let previous_root = market.allowlist_root;
+        market.allowlist_root = allowlist_root;
+
+        emit!(AllowlistRootUpdated {
+            market_key: market.key(),
+            previous_root,
+            new_root: allowlist_root,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set per-market exposure caps (0 disables a cap). The protocol floors in
+    /// the config still apply, and the limits are fixed once trading starts.
+    pub fn set_exposure_limits(
+        ctx: Context<SetExposureLimits>,
+        max_position_stake: u64,    // Max total stake per position
+        max_wallet_oi_bps: u16,     // Max share of open interest per wallet
+        max_price_impact_bps: u16,  // Max price move from a single trade
+        wallet_oi_floor: u64,       // Open interest below which the wallet cap is not enforced
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.creator.key() == market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        market.set_exposure_limits(max_position_stake, max_wallet_oi_bps, max_price_impact_bps, wallet_oi_floor)?;
+
+        emit!(ExposureLimitsUpdated {
+            market_key: market.key(),
+            max_position_stake,
+            max_wallet_oi_bps,
+            max_price_impact_bps,
+            wallet_oi_floor,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the market's dynamic fee curve (creator only, before the first trade).
+    /// Fees ramp up by `fee_ramp_bps` over the `fee_ramp_window` before resolution
+    /// and rise with recent volatility; the combined extra fee is capped at
+    /// `max_dynamic_fee_bps` and accrues to the liquidity provider.
+    pub fn set_dynamic_fees(
+        ctx: Context<SetDynamicFees>,
+        fee_ramp_bps: u16,
+        fee_ramp_window: i64,
+        volatility_fee_multiplier_bps: u16,
+        volatility_window: i64,
+        max_dynamic_fee_bps: u16,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.creator.key() == market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        market.set_dynamic_fees(
+            fee_ramp_bps,
+            fee_ramp_window,
+            volatility_fee_multiplier_bps,
+            volatility_window,
+            max_dynamic_fee_bps,
+            clock.unix_timestamp,
+        )?;
+
+        emit!(DynamicFeesUpdated {
+            market_key: market.key(),
+            fee_ramp_bps,
+            fee_ramp_window,
+            volatility_fee_multiplier_bps,
+            volatility_window,
+            max_dynamic_fee_bps,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Configure the market's circuit breaker (creator only). A YES price move of
+    /// more than `move_bps` from its reference within `window` seconds halts
+    /// trading for `cooldown` seconds. `move_bps == 0` disables the breaker.
+    pub fn set_circuit_breaker(
+        ctx: Context<SetCircuitBreaker>,
+        move_bps: u16,
+        window: i64,
+        cooldown: i64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.creator.key() == market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        market.set_circuit_breaker(move_bps, window, cooldown, clock.unix_timestamp)?;
+
+        emit!(CircuitBreakerUpdated {
+            market_key: market.key(),
+            move_bps,
+            window,
+            cooldown,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Lift a circuit-breaker halt before its cooldown ends (resolver or protocol admin)
+    pub fn resume_market(ctx: Context<ResumeMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.authority.key() == market.creator
+                || ctx.accounts.authority.key() == ctx.accounts.config.admin,
+            ErrorCode::Unauthorized
+        );
+
+        market.resume(clock.unix_timestamp)?;
+
+        emit!(MarketResumed {
+            market_key: market.key(),
+            authority: Some(ctx.accounts.authority.key()),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Create the optional on-chain price history ring buffer for a market
+    pub fn initialize_price_history(
+        ctx: Context<InitializePriceHistory>,
+        sample_interval: i64,  // Minimum seconds between samples
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let mut price_history = ctx.accounts.price_history.load_init()?;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.creator.key() == market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        require!(
+            sample_interval > 0,
+            ErrorCode::InvalidSampleInterval
+        );
+
+        price_history.market = market.key();
+        price_history.sample_interval = sample_interval;
+        price_history.last_sample_at = 0;
+        price_history.pending_volume = 0;
+        price_history.head = 0;
+        price_history.len = 0;
+        price_history.version = PriceHistory::VERSION;
+
+        // Seed the buffer with the opening price
+        price_history.record(market, clock.unix_timestamp, 0)?;
+
+        market.price_history = Some(ctx.accounts.price_history.key());
+
+        emit!(PriceHistoryInitialized {
+            market_key: market.key(),
+            price_history: ctx.accounts.price_history.key(),
+            sample_interval,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Change how often trades write a price history sample
+    pub fn set_price_history_interval(
+        ctx: Context<SetPriceHistoryInterval>,
+        sample_interval: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.creator.key() == ctx.accounts.market.creator,
+            ErrorCode::Unauthorized
+        );
+
+        require!(
+            sample_interval > 0,
+            ErrorCode::InvalidSampleInterval
+        );
+
+        ctx.accounts.price_history.load_mut()?.sample_interval = sample_interval;
+
+        Ok(())
+    }
+
+    /// Get current market prices (view function - call off-chain)
+    pub fn get_prices(ctx: Context<GetPrices>) -> Result<(u64, u64)> {
+        let market = &ctx.accounts.market;
+        let yes_price = get_yes_price(market)?;
+        let no_price = get_no_price(market)?;
+        Ok((yes_price, no_price))
+    }
+
+    /// Quote a `place_bet` of `amount` (view function - use simulateTransaction).
+    /// The quote is written with set_return_data as a Borsh-encoded `BuyQuote`.
+    /// It runs the same checks as the trade, so it fails where the trade would.
+    pub fn quote_buy(
+        ctx: Context<QuoteTrade>,
+        outcome: bool,
+        amount: u64,
+    ) -> Result<BuyQuote> {
+        let position = ctx.accounts.position.as_deref();
+        let now = Clock::get()?.unix_timestamp;
+        quote_buy_amount(&ctx.accounts.config, &ctx.accounts.market, position, outcome, amount, now)
+    }
+
+    /// Quote a `sell_shares` of `shares` (view function - use simulateTransaction).
+    /// The quote is written with set_return_data as a Borsh-encoded `SellQuote`.
+    /// Like `quote_buy` it fails where the trade would.
+    pub fn quote_sell(
+        ctx: Context<QuoteTrade>,
+        outcome: bool,
+        shares: u64,
+    ) -> Result<SellQuote> {
+        let position = ctx.accounts.position.as_deref();
+        let now = Clock::get()?.unix_timestamp;
+        quote_sell_shares(&ctx.accounts.config, &ctx.accounts.market, position, outcome, shares, now)
+    }
+
+    /// Quote `claim_winnings` for a position (view function - use simulateTransaction).
+    /// The quote is written with set_return_data as a Borsh-encoded `ClaimQuote`.
+    pub fn quote_claim(ctx: Context<QuoteClaim>) -> Result<ClaimQuote> {
+        quote_claim_payout(&ctx.accounts.market, &ctx.accounts.position)
+    }
+
+    /// Translate a market created before accounts were versioned. Its creator
+    /// names `market_vault`, the token account its bets were paid into.
+    pub fn migrate_market(ctx: Context<MigrateMarket>) -> Result<()> {
+        let info = ctx.accounts.market.to_account_info();
+        require!(info.data_len() != Market::SPACE, VersioningError::AlreadyMigrated);
+        require!(info.data_len() == MarketV0::SPACE, VersioningError::UnsupportedVersion);
+
+        let v0 = MarketV0::try_decode(&info.try_borrow_data()?)?;
+        require_keys_eq!(v0.creator, ctx.accounts.creator.key(), ErrorCode::Unauthorized);
+
+        let clock = Clock::get()?;
+        let vault = &ctx.accounts.market_vault;
+        let market = Market::from_v0(v0, vault.key(), vault.amount, clock.unix_timestamp)?;
+
+        grow_account(
+            &info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            Market::SPACE,
+        )?;
+        let mut data = info.try_borrow_mut_data()?;
+        market.try_serialize(&mut &mut data[..])?;
+
+        emit!(AccountMigrated {
+            account: info.key(),
+            from_version: 0,
+            to_version: Market::VERSION,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrade a position created under an older layout
+    pub fn migrate_position(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<Position>()
+    }
+
+    /// Upgrade the protocol config created under an older layout
+    pub fn migrate_config(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<ProtocolConfig>()
+    }
+
+    /// Upgrade a referral account created under an older layout
+    pub fn migrate_referral(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<ReferralAccount>()
+    }
+
+    /// Upgrade a session key created under an older layout
+    pub fn migrate_session_key(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<SessionKey>()
+    }
+
+    /// Upgrade a user stats account created under an older layout
+    pub fn migrate_user_stats(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<UserStats>()
+    }
+
+    /// Upgrade a reward pool created under an older layout
+    pub fn migrate_reward_pool(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<RewardPool>()
+    }
+
+    /// Upgrade a reward stake created under an older layout
+    pub fn migrate_reward_stake(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<RewardStake>()
+    }
+
+    /// Upgrade a price history buffer created under an older layout
+    pub fn migrate_price_history(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.grow(8 + std::mem::size_of::<PriceHistory>())?;
+
+        let info = ctx.accounts.account.to_account_info();
+        let price_history = AccountLoader::<PriceHistory>::try_from(&info)?;
+        let mut history = price_history.load_mut()?;
+        let from_version = history.version;
+        require!(from_version < PriceHistory::VERSION, VersioningError::AlreadyMigrated);
+        history.version = PriceHistory::VERSION;
+
+        emit!(AccountMigrated {
+            account: info.key(),
+            from_version,
+            to_version: PriceHistory::VERSION,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+// AMM Helper Functions
+
+/// Bounds on the collateral a creator seeds the AMM with (token base units)
+pub const MIN_INITIAL_LIQUIDITY: u64 = 10;
+/// Smallest total a reward pool can be funded with
+pub const MIN_REWARD_POOL_FUNDING: u64 = 1_000_000;
+pub const MAX_INITIAL_LIQUIDITY: u64 = 1_000_000_000_000_000;
+
+/// Upper bound on the protocol trading fee (10%)
+pub const MAX_TRADING_FEE_BPS: u16 = 1000;
+
+/// Upper bound on a market's dynamic fee on top of the protocol fee (20%)
+pub const MAX_DYNAMIC_FEE_BPS: u16 = 2000;
+
+/// Time after `resolution_time` a drift market can still be settled YES
+/// before `finalize_drift_market` may resolve it NO
+pub const DRIFT_SETTLEMENT_GRACE: i64 = 86400;
+
+/// Time after `resolution_time` a non-drift market can still be resolved by
+/// its creator before `void_abandoned_market` may void it
+pub const ABANDONED_MARKET_GRACE: i64 = 30 * 86400;
+
+/// Quote a buy of `amount` at `now` for `position` (None before its first trade); shared by
+/// `quote_buy` and off-chain clients. The trade runs on copies, so the quote fails where it would.
+pub fn quote_buy_amount(
+    config: &ProtocolConfig,
+    market: &Market,
+    position: Option<&Position>,
+    outcome: bool,
+    amount: u64,
+    now: i64,
+) -> Result<BuyQuote> {
+    let mut after = Box::new(market.clone());
+    let mut position = position.cloned().unwrap_or_default();
+    let fill = after.apply_buy(&mut position, config, outcome, amount, now)?;
+
+    let (yes_price_after, no_price_after) = prices_from_reserves(after.yes_reserve, after.no_reserve)?;
+    let (price_before, price_after) = if outcome {
+        (get_yes_price(market)?, yes_price_after)
+    } else {
+        (get_no_price(market)?, no_price_after)
+    };
+
+    Ok(BuyQuote {
+        amount,
+        fee: fill.fee,
+        fee_bps: fill.fee_bps,
+        net_amount: fill.net_amount,
+        shares: fill.shares,
+        average_price: average_price_bps(amount, fill.shares)?,
+        price_impact: price_before.abs_diff(price_after),
+        yes_price_after,
+        no_price_after,
+    })
+}
+
+/// Quote a sell of `shares` at `now` from `position`; shared by `quote_sell` and off-chain
+/// clients. Like `quote_buy_amount` it runs the trade on copies.
+pub fn quote_sell_shares(
+    config: &ProtocolConfig,
+    market: &Market,
+    position: Option<&Position>,
+    outcome: bool,
+    shares: u64,
+    now: i64,
+) -> Result<SellQuote> {
+    let mut after = Box::new(market.clone());
+    let mut position = position.cloned().unwrap_or_default();
+    let fill = after.apply_sell(&mut position, config, outcome, shares, now)?;
+
+    let (yes_price_after, no_price_after) = prices_from_reserves(after.yes_reserve, after.no_reserve)?;
+    let (price_before, price_after) = if outcome {
+        (get_yes_price(market)?, yes_price_after)
+    } else {
+        (get_no_price(market)?, no_price_after)
+    };
+
+    Ok(SellQuote {
+        shares,
+        gross_proceeds: fill.collateral_out,
+        fee: fill.fee,
+        fee_bps: fill.fee_bps,
+        proceeds: fill.proceeds,
+        average_price: average_price_bps(fill.proceeds, shares)?,
+        price_impact: price_before.abs_diff(price_after),
+        yes_price_after,
+        no_price_after,
+    })
+}
+
+/// Quote a position's claim; shared by `quote_claim` and off-chain clients
+pub fn quote_claim_payout(market: &Market, position: &Position) -> Result<ClaimQuote> {
+    require!(
+        market.status == MarketStatus::Resolved,
+        ErrorCode::MarketNotResolved
+    );
+
+    let (winning_shares, payout) = calculate_payout(market, position)?;
+
+    Ok(ClaimQuote {
+        winning_outcome: market.winning_outcome.ok_or(ErrorCode::NoWinningOutcome)?,
+        winning_shares,
+        payout,
+        already_claimed: position.claimed,
+    })
+}
+
+/// Fee rate for a trade right now: protocol fee plus the market's dynamic fee
+pub fn trading_fee_bps(config: &ProtocolConfig, market: &Market, now: i64) -> Result<u16> {
+    Ok(config.trading_fee_bps.saturating_add(market.dynamic_fee_bps(now)?))
+}
+
+/// Split the fee on a trade amount into (total_fee, lp_fee, referral_fee).
+/// `fee_bps` is the protocol fee plus the market's dynamic fee; the dynamic
+/// part (`lp_fee`) goes to the liquidity provider and the referrer takes a
+/// slice of the protocol part. The fee rounds up; the protocol part rounds up
+/// and the referral slice rounds down, so the LP share is never overstated.
+pub fn calculate_trading_fee(config: &ProtocolConfig, fee_bps: u16, amount: u64, has_referrer: bool) -> Result<(u64, u64, u64)> {
+    let fee = bps::apply(amount, fee_bps as u64, Rounding::Up).ok_or(ErrorCode::MathOverflow)?;
+
+    let protocol_bps = config.trading_fee_bps.min(fee_bps);
+    let protocol_fee = bps::apply(amount, protocol_bps as u64, Rounding::Up)
+        .ok_or(ErrorCode::MathOverflow)?
+        .min(fee);
+    let lp_fee = fee - protocol_fee;
+
+    let referral_fee = if has_referrer { config.referral_fee(protocol_fee)? } else { 0 };
+
+    Ok((fee, lp_fee, referral_fee))
+}
+
+/// Calculate a buy using a fixed-product market maker over complete sets.
+/// `net_amount` of collateral mints that many YES+NO sets; the pool keeps
+/// the opposite side and pays out enough of the bought side to restore
+/// `yes_reserve * no_reserve >= k`. Returns (shares, new_yes_reserve, new_no_reserve).
+/// The pool's reserve is rounded up, so shares round down in the pool's favor.
+pub fn calculate_buy(market: &Market, outcome: bool, net_amount: u64) -> Result<(u64, u64, u64)> {
+    let (bought_reserve, other_reserve) = if outcome {
+        (market.yes_reserve, market.no_reserve)
+    } else {
+        (market.no_reserve, market.yes_reserve)
+    };
+
+    let bought_after_mint = bought_reserve
+        .checked_add(net_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let new_other_reserve = other_reserve
+        .checked_add(net_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // k = x * y, so new_x = ceil(k / new_y)
+    let new_bought_reserve = math::div(market.k_constant, new_other_reserve as u128, Rounding::Up)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let new_bought_reserve = u64::try_from(new_bought_reserve).map_err(|_| ErrorCode::MathOverflow)?;
+
+    let shares = bought_after_mint
+        .checked_sub(new_bought_reserve)
+        .ok_or(ErrorCode::InsufficientLiquidity)?;
+
+    Ok(if outcome {
+        (shares, new_bought_reserve, new_other_reserve)
+    } else {
+        (shares, new_other_reserve, new_bought_reserve)
+    })
+}
+
+/// Calculate a sell (reverse of calculate_buy).
+/// The seller returns `shares` to the pool and the pool burns `r` complete
+/// sets, releasing `r` collateral, where (x + shares - r) * (y - r) = k.
+/// Solving the quadratic: r = (x + shares + y - sqrt((x + shares - y)^2 + 4k)) / 2.
+/// The square root is rounded up, so `r` rounds down in the pool's favor.
+/// Returns (collateral_out, new_yes_reserve, new_no_reserve).
+pub fn calculate_sell(market: &Market, outcome: bool, shares: u64) -> Result<(u64, u64, u64)> {
+    let (sold_reserve, other_reserve) = if outcome {
+        (market.yes_reserve, market.no_reserve)
+    } else {
+        (market.no_reserve, market.yes_reserve)
+    };
+
+    let sold_after_return = (sold_reserve as u128)
+        .checked_add(shares as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let other = other_reserve as u128;
+
+    let sum = sold_after_return.checked_add(other).ok_or(ErrorCode::MathOverflow)?;
+    let diff = sold_after_return.abs_diff(other);
+    let discriminant = diff
+        .checked_mul(diff)
+        .and_then(|d| d.checked_add(market.k_constant.checked_mul(4)?))
+        .ok_or(ErrorCode::MathOverflow)?;
+    let root = math::sqrt(discriminant, Rounding::Up);
+
+    let collateral_out = math::div(sum.saturating_sub(root), 2, Rounding::Down).ok_or(ErrorCode::MathOverflow)?;
+    let collateral_out = u64::try_from(collateral_out).map_err(|_| ErrorCode::MathOverflow)?;
+
+    let new_sold_reserve = u64::try_from(sold_after_return - collateral_out as u128)
+        .map_err(|_| ErrorCode::MathOverflow)?;
+    let new_other_reserve = other_reserve
+        .checked_sub(collateral_out)
+        .ok_or(ErrorCode::InsufficientLiquidity)?;
+
+    Ok(if outcome {
+        (collateral_out, new_sold_reserve, new_other_reserve)
+    } else {
+        (collateral_out, new_other_reserve, new_sold_reserve)
+    })
+}
+
+/// Cost basis released when selling `shares` out of `held` shares with `stake` basis
+pub fn proportional_stake(stake: u64, shares: u64, held: u64) -> Result<u64> {
+    if held == 0 {
+        return Ok(0);
+    }
+    let released = math::mul_div(stake, shares.min(held), held, Rounding::Down).ok_or(ErrorCode::MathOverflow)?;
+    Ok(released)
+}
+
+/// Verify a Merkle proof that `wallet` is in the allowlist.
+/// Leaves are keccak(0x00 || wallet); inner nodes are keccak(0x01 || min || max)
+/// with sorted children, so proofs carry no left/right flags.
+pub fn verify_allowlist_proof(proof: &[[u8; 32]], root: [u8; 32], wallet: &Pubkey) -> bool {
+    let mut node = keccak::hashv(&[&[0u8], wallet.as_ref()]).to_bytes();
+    for sibling in proof {
+        node = if node <= *sibling {
+            keccak::hashv(&[&[1u8], &node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[&[1u8], sibling, &node]).to_bytes()
+        };
+    }
+    node == root
+}
+
+/// Average fill price in bps (amount paid or received per share)
+pub fn average_price_bps(amount: u64, shares: u64) -> Result<u64> {
+    if shares == 0 {
+        return Ok(0);
+    }
+    let price = bps::ratio(amount, shares, Rounding::Down).ok_or(ErrorCode::MathOverflow)?;
+    Ok(price)
+}
+
+/// (winning_shares, payout) for a position in a resolved market.
+/// Every winning share redeems for exactly one unit of collateral, except in
+/// legacy markets, which split the whole pot across the winning shares.
+pub fn calculate_payout(market: &Market, position: &Position) -> Result<(u64, u64)> {
+    let winning_outcome = market.winning_outcome.ok_or(ErrorCode::NoWinningOutcome)?;
+
+    let (user_winning_shares, total_winning_shares) = if winning_outcome {
+        (position.yes_shares, market.total_yes_shares)
+    } else {
+        (position.no_shares, market.total_no_shares)
+    };
+
+    if !market.legacy || user_winning_shares == 0 {
+        return Ok((user_winning_shares, user_winning_shares));
+    }
+
+    let total_pool = market.yes_pool.checked_add(market.no_pool).ok_or(ErrorCode::MathOverflow)?;
+    let payout = math::mul_div(user_winning_shares, total_pool, total_winning_shares, Rounding::Down)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok((user_winning_shares, payout))
+}
+
+/// Check a conditional market's parent. Trading is allowed while the parent is
+/// open (`allow_open_parent`) or resolved to the required outcome; resolution
+/// requires the latter.
+fn check_parent_market(market: &Market, parent: Option<&Account<Market>>, allow_open_parent: bool) -> Result<()> {
+    let parent_key = match market.parent_market {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+
+    let parent = parent.ok_or(ErrorCode::MissingParentMarket)?;
+    require_keys_eq!(parent.key(), parent_key, ErrorCode::InvalidParentMarket);
+
+    match parent.status {
+        MarketStatus::Open | MarketStatus::Closed => require!(allow_open_parent, ErrorCode::ParentNotResolved),
+        MarketStatus::Resolved => require!(
+            parent.winning_outcome == Some(market.parent_outcome),
+            ErrorCode::ParentConditionFailed
+        ),
+        MarketStatus::Cancelled => return err!(ErrorCode::ParentConditionFailed),
+    }
+
+    Ok(())
+}
+
+/// Enforce the market's optional exposure caps for a bet of `amount`
+pub fn check_exposure_limits(
+    config: &ProtocolConfig,
+    market: &Market,
+    position: &Position,
+    outcome: bool,
+    amount: u64,
+) -> Result<()> {
+    let max_position_stake = stricter_cap(market.max_position_stake, config.max_position_stake);
+    let max_wallet_oi_bps = stricter_cap(market.max_wallet_oi_bps, config.max_wallet_oi_bps);
+    let max_price_impact_bps = stricter_cap(market.max_price_impact_bps, config.max_price_impact_bps);
+    let wallet_oi_floor = if config.max_wallet_oi_bps > 0 {
+        market.wallet_oi_floor.min(config.wallet_oi_floor)
+    } else {
+        market.wallet_oi_floor
+    };
+
+    let new_position_stake = position.total_stake
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    if max_position_stake > 0 {
+        require!(
+            new_position_stake <= max_position_stake,
+            ErrorCode::PositionStakeLimitExceeded
+        );
+    }
+
+    if max_wallet_oi_bps > 0 {
+        let new_open_interest = market.yes_pool
+            .checked_add(market.no_pool)
+            .and_then(|oi| oi.checked_add(amount))
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if new_open_interest >= wallet_oi_floor {
+            // Rounded up so the cap is never exceeded by a fraction of a bps
+            let wallet_share_bps = bps::ratio(new_position_stake, new_open_interest, Rounding::Up)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(
+                wallet_share_bps <= max_wallet_oi_bps as u64,
+                ErrorCode::WalletOpenInterestLimitExceeded
+            );
+        }
+    }
+
+    if max_price_impact_bps > 0 && market.amm_enabled {
+        let price_before = get_yes_price(market)?;
+        let (_, yes_reserve, no_reserve) = calculate_buy(market, outcome, amount)?;
+        let (price_after, _) = prices_from_reserves(yes_reserve, no_reserve)?;
+        require!(
+            price_before.abs_diff(price_after) <= max_price_impact_bps as u64,
+            ErrorCode::PriceImpactTooHigh
+        );
+    }
+
+    Ok(())
+}
+
+/// The tighter of two caps where 0 means no cap
+fn stricter_cap<T: Copy + Ord + Default>(a: T, b: T) -> T {
+    let none = T::default();
+    if a == none {
+        b
+    } else if b == none {
+        a
+    } else {
+        a.min(b)
+    }
+}
+
+/// (YES, NO) prices in basis points for a pair of pool reserves.
+/// The pool holds less of whichever outcome is more expensive. Both round
+/// down, so together they may come to just under 10,000.
+pub fn prices_from_reserves(yes_reserve: u64, no_reserve: u64) -> Result<(u64, u64)> {
+    let total_reserve = yes_reserve.checked_add(no_reserve).ok_or(ErrorCode::MathOverflow)?;
+
+    if total_reserve == 0 {
+        return Ok((5000, 5000)); // 50% default
+    }
+
+    // Price(YES) = no_reserve / total_reserve * 10000
+    let yes_price = bps::ratio(no_reserve, total_reserve, Rounding::Down).ok_or(ErrorCode::MathOverflow)?;
+    let no_price = bps::ratio(yes_reserve, total_reserve, Rounding::Down).ok_or(ErrorCode::MathOverflow)?;
+    Ok((yes_price, no_price))
+}
+
+/// Get YES price (as basis points, e.g., 5000 = 50%)
+pub fn get_yes_price(market: &Market) -> Result<u64> {
+    Ok(prices_from_reserves(market.yes_reserve, market.no_reserve)?.0)
+}
+
+/// Get NO price (as basis points)
+pub fn get_no_price(market: &Market) -> Result<u64> {
+    Ok(prices_from_reserves(market.yes_reserve, market.no_reserve)?.1)
+}
+
+// Account Structures
+
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub creator: Pubkey,
+    pub model: Pubkey,           // Reference to model in registry
+    #[max_len(252)]
+    pub question: String,         // Max MAX_QUESTION_LEN bytes
+    pub yes_pool: u64,           // Total USDC bet on YES
+    pub no_pool: u64,            // Total USDC bet on NO
+    pub total_volume: u64,
+    pub status: MarketStatus,
+    pub resolution_time: i64,
+    pub resolved_at: i64,
+    pub winning_outcome: Option<bool>,
+    pub min_stake: u64,
+    pub created_at: i64,
+    pub bump: u8,
+
+    // AMM fields
+    pub amm_enabled: bool,
+    pub yes_reserve: u64,          // YES outcome tokens held by the pool
+    pub no_reserve: u64,           // NO outcome tokens held by the pool
+    pub k_constant: u128,          // Constant product (x * y = k)
+    pub total_yes_shares: u64,     // Total YES shares held by traders
+    pub total_no_shares: u64,      // Total NO shares held by traders
+
+    // TWAP fields
+    pub yes_price_cumulative: u128, // Sum of yes_price (bps) * seconds
+    pub no_price_cumulative: u128,  // Sum of no_price (bps) * seconds
+    pub last_price_update: i64,     // Timestamp of the last accumulator update
+    pub price_history: Option<Pubkey>, // Ring buffer every trade must record into once initialized
+
+    // Exposure limits (0 = disabled)
+    pub max_position_stake: u64,    // Max total stake per position
+    pub max_wallet_oi_bps: u16,     // Max share of open interest per wallet
+    pub max_price_impact_bps: u16,  // Max YES/NO price move per trade
+    pub wallet_oi_floor: u64,       // Wallet cap applies once open interest reaches this
+
+    // Private markets
+    pub allowlist_root: Option<[u8; 32]>, // Merkle root of wallets allowed to bet
+
+    // Creator bond
+    pub creator_bond: u64,          // Bond escrowed at creation
+    pub bond_vault: Pubkey,         // Token account holding the bond (owned by market PDA)
+    pub bond_settled: bool,         // Bond returned or slashed
+
+    // Solvency accounting
+    pub vault: Pubkey,              // Token account holding market collateral
+    pub total_collateral: u64,      // Collateral owed out of the vault (traders + LP)
+    pub open_stake: u64,            // Sum of open positions' cost basis
+    pub refund_pool: u64,           // Collateral set aside for refunds on cancellation
+    pub lp_claimable: u64,          // Collateral owed to the liquidity provider once closed
+    pub lp_fees: u64,               // Dynamic fees collected for the liquidity provider
+
+    // Conditional markets
+    pub parent_market: Option<Pubkey>, // Market this one is conditional on
+    pub parent_outcome: bool,          // Parent outcome required for this market to stand
+
+    // Drift markets
+    pub drift_condition: Option<DriftCondition>, // Structured condition for registry settlement
+
+    // Liquidity mining
+    pub reward_pool: Option<Pubkey>,    // Sponsor reward pool; trades must sync reward stakes
+
+    // Dynamic fees (0 = disabled), charged on top of the protocol fee
+    pub fee_ramp_bps: u16,              // Extra fee reached at resolution_time
+    pub fee_ramp_window: i64,           // Seconds before resolution_time the ramp starts
+    pub volatility_fee_multiplier_bps: u16, // Extra fee per bps of spot-vs-TWAP deviation
+    pub volatility_window: i64,         // Age of the TWAP reference volatility is measured against
+    pub volatility_ref_old: PriceObservation,
+    pub volatility_ref_new: PriceObservation,
+    pub max_dynamic_fee_bps: u16,       // Cap on the combined dynamic fee
+
+    // Circuit breaker (breaker_move_bps = 0 disables)
+    pub breaker_move_bps: u16,          // Max YES price move within the window
+    pub breaker_window: i64,            // Seconds a reference price stays current
+    pub breaker_cooldown: i64,          // Halt length once tripped
+    pub breaker_ref_price: u64,         // YES price (bps) at the start of the window
+    pub breaker_ref_time: i64,
+    pub halted_until: i64,              // Trading halted while now < halted_until; 0 = not halted
+
+    // Translated from v0 by migrate_market: reserves are virtual, payouts are
+    // pari-mutuel over yes_pool + no_pool and trading stays closed
+    pub legacy: bool,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 127],        // Zeroed space for future fields
+}
+
+impl Versioned for Market {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl Market {
+    /// Maximum collateral the market can owe, across both outcomes.
+    /// Each complete set is either held by the pool or by traders, so
+    /// total_collateral = reserve + trader shares for each outcome.
+    pub fn max_payout_owed(&self) -> Result<u64> {
+        match self.status {
+            MarketStatus::Open | MarketStatus::Closed if self.legacy => Ok(self.total_collateral
+                .max(self.yes_pool.checked_add(self.no_pool).ok_or(ErrorCode::MathOverflow)?)),
+            MarketStatus::Open | MarketStatus::Closed if self.amm_enabled => {
+                let yes_owed = self.lp_payout(true)?
+                    .checked_add(self.total_yes_shares)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                let no_owed = self.lp_payout(false)?
+                    .checked_add(self.total_no_shares)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                Ok(yes_owed.max(no_owed).max(self.total_collateral))
+            }
+            MarketStatus::Open | MarketStatus::Closed => Ok(self.total_collateral
+                .max(self.total_yes_shares.saturating_add(self.lp_fees))
+                .max(self.total_no_shares.saturating_add(self.lp_fees))),
+            _ => Ok(self.total_collateral),
+        }
+    }
+
+    /// Collateral owed to the liquidity provider if `outcome` wins: the pool's
+    /// inventory of that outcome plus the dynamic fees collected. A legacy
+    /// market's reserves are virtual, so its pool holds nothing.
+    pub fn lp_payout(&self, outcome: bool) -> Result<u64> {
+        let reserve = match (self.legacy, outcome) {
+            (true, _) => 0,
+            (false, true) => self.yes_reserve,
+            (false, false) => self.no_reserve,
+        };
+        reserve.checked_add(self.lp_fees).ok_or_else(|| error!(ErrorCode::MathOverflow))
+    }
+
+    /// Fail loudly if the vault cannot cover the maximum payout owed
+    pub fn assert_solvent(&self, vault_balance: u64) -> Result<()> {
+        require!(
+            vault_balance >= self.max_payout_owed()?,
+            ErrorCode::InsolventMarket
+        );
+        Ok(())
+    }
+
+    /// Mark the market cancelled and split collateral between refunds and the LP.
+    /// Positions are refunded their cost basis first; if trading has left less
+    /// collateral than that, refunds are scaled down pro rata.
+    pub fn void(&mut self, now: i64) {
+        self.status = MarketStatus::Cancelled;
+        self.resolved_at = now;
+        self.refund_pool = self.total_collateral.min(self.open_stake);
+        self.lp_claimable = self.total_collateral - self.refund_pool;
+    }
+
+    /// Refund owed to a position with `stake` cost basis in a cancelled market
+    pub fn refund_for(&self, stake: u64) -> Result<u64> {
+        if self.open_stake == 0 {
+            return Ok(0);
+        }
+        let refund = math::mul_div(stake, self.refund_pool, self.open_stake, Rounding::Down)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(refund)
+    }
+
+    /// Fold the current spot price into the cumulative accumulators.
+    /// Must be called before any trade changes the reserves.
+    pub fn update_price_accumulators(&mut self, now: i64) -> Result<()> {
+        let elapsed = now.saturating_sub(self.last_price_update);
+        if elapsed > 0 {
+            self.yes_price_cumulative = self.yes_price_cumulative
+                .checked_add(get_yes_price(self)? as u128 * elapsed as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.no_price_cumulative = self.no_price_cumulative
+                .checked_add(get_no_price(self)? as u128 * elapsed as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.last_price_update = now;
+        }
+        Ok(())
+    }
+
+    /// Dynamic fee in bps at `now`: a linear ramp over the last `fee_ramp_window`
+    /// seconds before resolution, plus a volatility term from the deviation of
+    /// spot from the TWAP since `volatility_ref_old`, capped at `max_dynamic_fee_bps`.
+    /// Both terms round up.
+    pub fn dynamic_fee_bps(&self, now: i64) -> Result<u16> {
+        let mut extra: u64 = 0;
+
+        if self.fee_ramp_bps > 0 && self.fee_ramp_window > 0 {
+            let ramp_start = self.resolution_time.saturating_sub(self.fee_ramp_window);
+            if now > ramp_start {
+                let elapsed = (now - ramp_start).min(self.fee_ramp_window) as u64;
+                let ramp = math::mul_div(self.fee_ramp_bps as u64, elapsed, self.fee_ramp_window as u64, Rounding::Up);
+                extra = extra.saturating_add(ramp.unwrap_or(u64::MAX));
+            }
+        }
+
+        if self.volatility_fee_multiplier_bps > 0 {
+            if let Ok((yes_twap, _)) = get_twap(&self.volatility_ref_old, &self.observe(now)?) {
+                let deviation = get_yes_price(self)?.abs_diff(yes_twap);
+                let volatility = bps::apply(deviation, self.volatility_fee_multiplier_bps as u64, Rounding::Up);
+                extra = extra.saturating_add(volatility.unwrap_or(u64::MAX));
+            }
+        }
+
+        Ok(extra.min(self.max_dynamic_fee_bps as u64) as u16)
+    }
+
+    /// Reject trades while halted and roll the breaker's reference price once
+    /// its window has passed. Returns true if an expired halt was cleared.
+    /// Call before reserves move.
+    pub fn check_circuit_breaker(&mut self, now: i64) -> Result<bool> {
+        let mut resumed = false;
+        if self.halted_until != 0 {
+            require!(now >= self.halted_until, ErrorCode::TradingHalted);
+            self.halted_until = 0;
+            self.breaker_ref_price = get_yes_price(self)?;
+            self.breaker_ref_time = now;
+            resumed = true;
+        }
+
+        if now - self.breaker_ref_time >= self.breaker_window {
+            self.breaker_ref_price = get_yes_price(self)?;
+            self.breaker_ref_time = now;
+        }
+
+        Ok(resumed)
+    }
+
+    /// Lift a halt before its cooldown ends; the current price becomes the reference
+    pub fn resume(&mut self, now: i64) -> Result<()> {
+        require!(
+            self.halted_until > now,
+            ErrorCode::MarketNotHalted
+        );
+
+        self.halted_until = 0;
+        self.breaker_ref_price = get_yes_price(self)?;
+        self.breaker_ref_time = now;
+        Ok(())
+    }
+
+    /// Halt trading if the post-trade price moved too far from the reference.
+    /// The tripping trade stands; later trades are rejected until the cooldown ends.
+    pub fn trip_circuit_breaker(&mut self, now: i64) -> Result<bool> {
+        if self.breaker_move_bps == 0 {
+            return Ok(false);
+        }
+
+        let moved = get_yes_price(self)?.abs_diff(self.breaker_ref_price);
+        if moved <= self.breaker_move_bps as u64 {
+            return Ok(false);
+        }
+
+        self.halted_until = now.saturating_add(self.breaker_cooldown);
+        Ok(true)
+    }
+
+    /// Keep the volatility reference between one and two windows old.
+    /// Call after `update_price_accumulators`.
+    pub fn roll_volatility_reference(&mut self, now: i64) -> Result<()> {
+        if self.volatility_window > 0 && now - self.volatility_ref_new.timestamp >= self.volatility_window {
+            self.volatility_ref_old = self.volatility_ref_new;
+            self.volatility_ref_new = self.observe(now)?;
+        }
+        Ok(())
+    }
+
+    /// Snapshot the accumulators as of `now`, extrapolating the current
+    /// spot price over the time since the last trade.
+    pub fn observe(&self, now: i64) -> Result<PriceObservation> {
+        let elapsed = now.saturating_sub(self.last_price_update).max(0) as u128;
+        Ok(PriceObservation {
+            timestamp: now.max(self.last_price_update),
+            yes_price_cumulative: self.yes_price_cumulative
+                .saturating_add(get_yes_price(self)? as u128 * elapsed),
+            no_price_cumulative: self.no_price_cumulative
+                .saturating_add(get_no_price(self)? as u128 * elapsed),
+        })
+    }
+
+    // State transitions. Each instruction handler checks its accounts, calls
+    // one of these and then moves tokens; the simulator calls them directly.
+
+    /// Validate `params` and reset every field `create_market` owns at `now`.
+    /// The seed liquidity mints complete sets held by the pool (x * y = k).
+    /// The creator, bump, vault and bond are left to the caller.
+    pub fn open(&mut self, params: MarketParams, now: i64) -> Result<()> {
+        require!(
+            params.resolution_time > now,
+            ErrorCode::InvalidResolutionTime
+        );
+
+        require!(
+            params.question.len() <= MAX_QUESTION_LEN,
+            ErrorCode::QuestionTooLong
+        );
+
+        require!(
+            params.initial_liquidity >= MIN_INITIAL_LIQUIDITY,
+            ErrorCode::LiquidityTooLow
+        );
+
+        require!(
+            params.initial_liquidity <= MAX_INITIAL_LIQUIDITY,
+            ErrorCode::LiquidityTooHigh
+        );
+
+        self.model = params.model;
+        self.question = params.question;
+        self.yes_pool = 0;
+        self.no_pool = 0;
+        self.total_volume = 0;
+        self.status = MarketStatus::Open;
+        self.resolution_time = params.resolution_time;
+        self.resolved_at = 0;
+        self.winning_outcome = None;
+        self.min_stake = params.min_stake;
+        self.created_at = now;
+
+        // AMM initialization (Constant Product: x * y = k)
+        self.amm_enabled = true;
+        self.yes_reserve = params.initial_liquidity;
+        self.no_reserve = params.initial_liquidity;
+        self.k_constant = (params.initial_liquidity as u128)
+            .checked_mul(params.initial_liquidity as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_yes_shares = 0;
+        self.total_no_shares = 0;
+
+        // Collateral accounting
+        self.total_collateral = params.initial_liquidity;
+        self.open_stake = 0;
+        self.refund_pool = 0;
+        self.lp_claimable = 0;
+
+        // TWAP accumulators start empty at creation time
+        self.yes_price_cumulative = 0;
+        self.no_price_cumulative = 0;
+        self.last_price_update = now;
+
+        // Exposure limits are disabled until the creator sets them
+        self.max_position_stake = 0;
+        self.max_wallet_oi_bps = 0;
+        self.max_price_impact_bps = 0;
+        self.wallet_oi_floor = 0;
+
+        self.allowlist_root = params.allowlist_root;
+
+        self.parent_market = None;
+        self.parent_outcome = false;
+
+        self.drift_condition = None;
+        self.reward_pool = None;
+        self.price_history = None;
+        self.lp_fees = 0;
+        self.legacy = false;
+
+        // Dynamic fees are disabled until the creator sets them
+        self.fee_ramp_bps = 0;
+        self.fee_ramp_window = 0;
+        self.volatility_fee_multiplier_bps = 0;
+        self.volatility_window = 0;
+        self.volatility_ref_old = self.observe(now)?;
+        self.volatility_ref_new = self.volatility_ref_old;
+        self.max_dynamic_fee_bps = 0;
+
+        // Circuit breaker is disabled until the creator sets it
+        self.breaker_move_bps = 0;
+        self.breaker_window = 0;
+        self.breaker_cooldown = 0;
+        self.breaker_ref_price = get_yes_price(self)?;
+        self.breaker_ref_time = now;
+        self.halted_until = 0;
+        self.version = Market::VERSION;
+        Ok(())
+    }
+
+    /// Translate a v0 market whose collateral sits in `vault`. v0 priced bets
+    /// off unbacked virtual reserves and split the pot pari-mutuel, so the
+    /// result is marked `legacy`: it keeps those payouts and can't be traded.
+    pub fn from_v0(v0: MarketV0, vault: Pubkey, vault_balance: u64, now: i64) -> Result<Market> {
+        let pot = v0.yes_pool.checked_add(v0.no_pool).ok_or(ErrorCode::MathOverflow)?;
+        if v0.status.is_unresolved() {
+            require!(vault_balance >= pot, ErrorCode::InsolventMarket);
+        }
+
+        // v0 grew its YES reserve on YES bets and priced YES at yes / (yes + no);
+        // the pool now holds less of the pricier outcome, so the reserves swap
+        let yes_reserve = v0.virtual_no_reserve;
+        let no_reserve = v0.virtual_yes_reserve;
+        let reference = PriceObservation { timestamp: now, yes_price_cumulative: 0, no_price_cumulative: 0 };
+
+        let mut market = Market {
+            creator: v0.creator,
+            model: v0.model,
+            question: v0.question,
+            yes_pool: v0.yes_pool,
+            no_pool: v0.no_pool,
+            total_volume: v0.total_volume,
+            status: v0.status,
+            resolution_time: v0.resolution_time,
+            resolved_at: v0.resolved_at,
+            winning_outcome: v0.winning_outcome,
+            min_stake: v0.min_stake,
+            created_at: v0.created_at,
+            bump: v0.bump,
+            amm_enabled: v0.amm_enabled,
+            yes_reserve,
+            no_reserve,
+            k_constant: v0.k_constant as u128,
+            total_yes_shares: v0.total_yes_shares,
+            total_no_shares: v0.total_no_shares,
+            yes_price_cumulative: 0,
+            no_price_cumulative: 0,
+            last_price_update: now,
+            price_history: None,
+            max_position_stake: 0,
+            max_wallet_oi_bps: 0,
+            max_price_impact_bps: 0,
+            wallet_oi_floor: 0,
+            allowlist_root: None,
+            // v0 took no bond
+            creator_bond: 0,
+            bond_vault: vault,
+            bond_settled: true,
+            vault,
+            total_collateral: vault_balance,
+            open_stake: pot,
+            refund_pool: 0,
+            lp_claimable: 0,
+            lp_fees: 0,
+            parent_market: None,
+            parent_outcome: false,
+            drift_condition: None,
+            reward_pool: None,
+            fee_ramp_bps: 0,
+            fee_ramp_window: 0,
+            volatility_fee_multiplier_bps: 0,
+            volatility_window: 0,
+            volatility_ref_old: reference,
+            volatility_ref_new: reference,
+            max_dynamic_fee_bps: 0,
+            breaker_move_bps: 0,
+            breaker_window: 0,
+            breaker_cooldown: 0,
+            breaker_ref_price: prices_from_reserves(yes_reserve, no_reserve)?.0,
+            breaker_ref_time: now,
+            halted_until: 0,
+            legacy: true,
+            version: Market::VERSION,
+            reserved: [0; 127],
+        };
+
+        if market.status == MarketStatus::Cancelled {
+            market.void(market.resolved_at);
+        }
+        Ok(market)
+    }
+
+    /// `set_exposure_limits` without accounts; only before the first trade
+    pub fn set_exposure_limits(
+        &mut self,
+        max_position_stake: u64,
+        max_wallet_oi_bps: u16,
+        max_price_impact_bps: u16,
+        wallet_oi_floor: u64,
+    ) -> Result<()> {
+        require!(
+            self.status == MarketStatus::Open,
+            ErrorCode::MarketClosed
+        );
+
+        require!(
+            self.total_volume == 0,
+            ErrorCode::MarketHasTrades
+        );
+
+        require!(
+            max_wallet_oi_bps <= 10000 && max_price_impact_bps <= 10000,
+            ErrorCode::InvalidExposureLimit
+        );
+
+        self.max_position_stake = max_position_stake;
+        self.max_wallet_oi_bps = max_wallet_oi_bps;
+        self.max_price_impact_bps = max_price_impact_bps;
+        self.wallet_oi_floor = wallet_oi_floor;
+        Ok(())
+    }
+
+    /// `set_dynamic_fees` without accounts; only before the first trade
+    pub fn set_dynamic_fees(
+        &mut self,
+        fee_ramp_bps: u16,
+        fee_ramp_window: i64,
+        volatility_fee_multiplier_bps: u16,
+        volatility_window: i64,
+        max_dynamic_fee_bps: u16,
+        now: i64,
+    ) -> Result<()> {
+        require!(
+            self.status == MarketStatus::Open,
+            ErrorCode::MarketClosed
+        );
+
+        require!(
+            self.total_volume == 0,
+            ErrorCode::MarketHasTrades
+        );
+
+        require!(
+            fee_ramp_window >= 0
+                && volatility_window >= 0
+                && fee_ramp_bps <= max_dynamic_fee_bps
+                && max_dynamic_fee_bps <= MAX_DYNAMIC_FEE_BPS,
+            ErrorCode::InvalidFeeParameter
+        );
+
+        self.update_price_accumulators(now)?;
+
+        self.fee_ramp_bps = fee_ramp_bps;
+        self.fee_ramp_window = fee_ramp_window;
+        self.volatility_fee_multiplier_bps = volatility_fee_multiplier_bps;
+        self.volatility_window = volatility_window;
+        self.max_dynamic_fee_bps = max_dynamic_fee_bps;
+
+        // Restart volatility measurement from now
+        self.volatility_ref_old = self.observe(now)?;
+        self.volatility_ref_new = self.volatility_ref_old;
+        Ok(())
+    }
+
+    /// `set_circuit_breaker` without accounts
+    pub fn set_circuit_breaker(&mut self, move_bps: u16, window: i64, cooldown: i64, now: i64) -> Result<()> {
+        require!(
+            self.status == MarketStatus::Open,
+            ErrorCode::MarketClosed
+        );
+
+        require!(
+            move_bps <= 10000 && (move_bps == 0 || (window > 0 && cooldown > 0)),
+            ErrorCode::InvalidCircuitBreaker
+        );
+
+        self.breaker_move_bps = move_bps;
+        self.breaker_window = window;
+        self.breaker_cooldown = cooldown;
+        self.breaker_ref_price = get_yes_price(self)?;
+        self.breaker_ref_time = now;
+        Ok(())
+    }
+
+    /// (shares, new_yes_reserve, new_no_reserve) for a buy of `net_amount`:
+    /// the constant-product AMM, or 1:1 for peer-to-peer markets
+    pub fn price_buy(&self, outcome: bool, net_amount: u64) -> Result<(u64, u64, u64)> {
+        if self.amm_enabled {
+            calculate_buy(self, outcome, net_amount)
+        } else {
+            Ok((net_amount, self.yes_reserve, self.no_reserve))
+        }
+    }
+
+    /// (collateral_out, new_yes_reserve, new_no_reserve) for a sell of `shares`
+    pub fn price_sell(&self, outcome: bool, shares: u64) -> Result<(u64, u64, u64)> {
+        if self.amm_enabled {
+            calculate_sell(self, outcome, shares)
+        } else {
+            Ok((shares, self.yes_reserve, self.no_reserve))
+        }
+    }
+
+    /// `place_bet` without accounts: fee, exposure limits, pricing, market and
+    /// position accounting and the circuit breaker. The caller moves
+    /// `BuyFill::vault_amount` into the vault and checks solvency.
+    pub fn apply_buy(
+        &mut self,
+        position: &mut Position,
+        config: &ProtocolConfig,
+        outcome: bool,
+        amount: u64,
+        now: i64,
+    ) -> Result<BuyFill> {
+        self.apply_buy_priced(position, config, outcome, amount, now, Market::price_buy)
+    }
+
+    /// `apply_buy` with shares priced by `price` instead of `Market::price_buy`
+    pub fn apply_buy_priced(
+        &mut self,
+        position: &mut Position,
+        config: &ProtocolConfig,
+        outcome: bool,
+        amount: u64,
+        now: i64,
+        price: impl FnOnce(&Market, bool, u64) -> Result<(u64, u64, u64)>,
+    ) -> Result<BuyFill> {
+        require!(
+            self.status == MarketStatus::Open,
+            ErrorCode::MarketClosed
+        );
+
+        require!(
+            !self.legacy,
+            ErrorCode::LegacyMarket
+        );
+
+        require!(
+            now < self.resolution_time,
+            ErrorCode::MarketExpired
+        );
+
+        require!(
+            amount >= self.min_stake,
+            ErrorCode::StakeTooLow
+        );
+
+        let resumed = self.check_circuit_breaker(now)?;
+
+        // Trading fee comes off the top
+        let fee_bps = trading_fee_bps(config, self, now)?;
+        let (fee, lp_fee, _) = calculate_trading_fee(config, fee_bps, amount, false)?;
+        let net_amount = amount.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+
+        // Accumulate the pre-trade price before reserves move
+        self.update_price_accumulators(now)?;
+        self.roll_volatility_reference(now)?;
+
+        check_exposure_limits(config, self, position, outcome, net_amount)?;
+
+        let (shares, new_yes_reserve, new_no_reserve) = price(self, outcome, net_amount)?;
+
+        // The dynamic fee stays in the vault for the liquidity provider
+        let vault_amount = net_amount.checked_add(lp_fee).ok_or(ErrorCode::MathOverflow)?;
+
+        if outcome {
+            self.yes_pool = self.yes_pool.checked_add(net_amount).ok_or(ErrorCode::MathOverflow)?;
+            self.total_yes_shares = self.total_yes_shares.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
+            position.yes_shares = position.yes_shares.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
+            position.yes_stake = position.yes_stake.checked_add(net_amount).ok_or(ErrorCode::MathOverflow)?;
+        } else {
+            self.no_pool = self.no_pool.checked_add(net_amount).ok_or(ErrorCode::MathOverflow)?;
+            self.total_no_shares = self.total_no_shares.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
+            position.no_shares = position.no_shares.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
+            position.no_stake = position.no_stake.checked_add(net_amount).ok_or(ErrorCode::MathOverflow)?;
+        }
+        self.yes_reserve = new_yes_reserve;
+        self.no_reserve = new_no_reserve;
+        self.total_collateral = self.total_collateral.checked_add(vault_amount).ok_or(ErrorCode::MathOverflow)?;
+        self.lp_fees = self.lp_fees.checked_add(lp_fee).ok_or(ErrorCode::MathOverflow)?;
+        self.open_stake = self.open_stake.checked_add(net_amount).ok_or(ErrorCode::MathOverflow)?;
+        self.total_volume = self.total_volume.checked_add(net_amount).ok_or(ErrorCode::MathOverflow)?;
+        position.total_stake = position.total_stake.checked_add(net_amount).ok_or(ErrorCode::MathOverflow)?;
+        position.claimed = false;
+
+        let halted = self.trip_circuit_breaker(now)?;
+
+        Ok(BuyFill { fee_bps, fee, lp_fee, net_amount, vault_amount, shares, resumed, halted })
+    }
+
+    /// `sell_shares` without accounts. The caller pays `SellFill::proceeds`
+    /// and the protocol fee out of the vault and checks solvency.
+    pub fn apply_sell(
+        &mut self,
+        position: &mut Position,
+        config: &ProtocolConfig,
+        outcome: bool,
+        shares: u64,
+        now: i64,
+    ) -> Result<SellFill> {
+        self.apply_sell_priced(position, config, outcome, shares, now, Market::price_sell)
+    }
+
+    /// `apply_sell` with collateral priced by `price` instead of `Market::price_sell`
+    pub fn apply_sell_priced(
+        &mut self,
+        position: &mut Position,
+        config: &ProtocolConfig,
+        outcome: bool,
+        shares: u64,
+        now: i64,
+        price: impl FnOnce(&Market, bool, u64) -> Result<(u64, u64, u64)>,
+    ) -> Result<SellFill> {
+        require!(
+            self.status == MarketStatus::Open,
+            ErrorCode::MarketClosed
+        );
+
+        require!(
+            !self.legacy,
+            ErrorCode::LegacyMarket
+        );
+
+        require!(
+            now < self.resolution_time,
+            ErrorCode::MarketExpired
+        );
+
+        let resumed = self.check_circuit_breaker(now)?;
+
+        let user_shares = if outcome { position.yes_shares } else { position.no_shares };
+        require!(
+            user_shares >= shares,
+            ErrorCode::InsufficientShares
+        );
+
+        // Fee rate is set by the pre-trade state
+        let fee_bps = trading_fee_bps(config, self, now)?;
+
+        // Accumulate the pre-trade price before reserves move
+        self.update_price_accumulators(now)?;
+        self.roll_volatility_reference(now)?;
+
+        let (collateral_out, new_yes_reserve, new_no_reserve) = price(self, outcome, shares)?;
+
+        // Trading fee comes out of the proceeds; the dynamic part stays in the vault
+        let (fee, lp_fee, _) = calculate_trading_fee(config, fee_bps, collateral_out, false)?;
+        let proceeds = collateral_out.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+        let vault_amount = collateral_out - lp_fee;
+
+        // Pools are informational, so they floor at zero when a seller exits at a profit
+        if outcome {
+            self.yes_pool = self.yes_pool.saturating_sub(collateral_out);
+            self.total_yes_shares = self.total_yes_shares.checked_sub(shares).ok_or(ErrorCode::InsufficientShares)?;
+        } else {
+            self.no_pool = self.no_pool.saturating_sub(collateral_out);
+            self.total_no_shares = self.total_no_shares.checked_sub(shares).ok_or(ErrorCode::InsufficientShares)?;
+        }
+        self.yes_reserve = new_yes_reserve;
+        self.no_reserve = new_no_reserve;
+        self.total_collateral = self.total_collateral.checked_sub(vault_amount).ok_or(ErrorCode::InsolventMarket)?;
+        self.lp_fees = self.lp_fees.checked_add(lp_fee).ok_or(ErrorCode::MathOverflow)?;
+        self.total_volume = self.total_volume.checked_add(collateral_out).ok_or(ErrorCode::MathOverflow)?;
+
+        // Release cost basis in proportion to the shares sold
+        let released_stake = if outcome {
+            let released = proportional_stake(position.yes_stake, shares, position.yes_shares)?;
+            position.yes_shares -= shares;
+            position.yes_stake -= released;
+            released
+        } else {
+            let released = proportional_stake(position.no_stake, shares, position.no_shares)?;
+            position.no_shares -= shares;
+            position.no_stake -= released;
+            released
+        };
+        position.total_stake = position.total_stake.checked_sub(released_stake).ok_or(ErrorCode::MathOverflow)?;
+        self.open_stake = self.open_stake.saturating_sub(released_stake);
+
+        let halted = self.trip_circuit_breaker(now)?;
+
+        Ok(SellFill {
+            fee_bps,
+            fee,
+            lp_fee,
+            collateral_out,
+            proceeds,
+            vault_amount,
+            released_stake,
+            resumed,
+            halted,
+        })
+    }
+
+    /// `close_market` without accounts: stop trading once `resolution_time` has passed
+    pub fn close_trading(&mut self, now: i64) -> Result<()> {
+        require!(
+            self.status == MarketStatus::Open,
+            ErrorCode::MarketClosed
+        );
+
+        require!(
+            now >= self.resolution_time,
+            ErrorCode::MarketNotExpired
+        );
+
+        self.update_price_accumulators(now)?;
+        self.status = MarketStatus::Closed;
+        Ok(())
+    }
+
+    /// `resolve_market` without accounts
+    pub fn resolve(&mut self, outcome: bool, now: i64) -> Result<()> {
+        require!(
+            self.status.is_unresolved(),
+            ErrorCode::MarketAlreadyResolved
+        );
+
+        require!(
+            now >= self.resolution_time,
+            ErrorCode::MarketNotExpired
+        );
+
+        // Drift markets resolve through settle_drift_market / finalize_drift_market
+        require!(
+            self.drift_condition.is_none(),
+            ErrorCode::DriftMarketResolution
+        );
+
+        self.set_outcome(outcome, now)
+    }
+
+    /// Record the winning outcome. The pool's inventory of it belongs to the
+    /// liquidity provider.
+    fn set_outcome(&mut self, outcome: bool, now: i64) -> Result<()> {
+        self.status = MarketStatus::Resolved;
+        self.resolved_at = now;
+        self.winning_outcome = Some(outcome);
+        self.lp_claimable = self.lp_payout(outcome)?;
+        Ok(())
+    }
+
+    /// `cancel_market` without accounts: void the market and settle the
+    /// creator bond. Returns the bond to slash to the treasury.
+    pub fn cancel(&mut self, now: i64) -> Result<u64> {
+        require!(
+            self.status.is_unresolved(),
+            ErrorCode::MarketAlreadyResolved
+        );
+
+        let bond_slashed = if self.bond_settled { 0 } else { self.creator_bond };
+        self.void(now);
+        self.bond_settled = true;
+        Ok(bond_slashed)
+    }
+
+    /// Redeem a winning position in a resolved market. Returns (winning_shares, payout).
+    pub fn claim_winnings(&mut self, position: &mut Position) -> Result<(u64, u64)> {
+        require!(
+            self.status == MarketStatus::Resolved,
+            ErrorCode::MarketNotResolved
+        );
+
+        require!(
+            !position.claimed,
+            ErrorCode::AlreadyClaimed
+        );
+
+        let (winning_shares, payout) = calculate_payout(self, position)?;
+        require!(winning_shares > 0, ErrorCode::NoWinningStake);
+
+        position.claimed = true;
+        self.total_collateral = self.total_collateral.checked_sub(payout).ok_or(ErrorCode::InsolventMarket)?;
+        Ok((winning_shares, payout))
+    }
+
+    /// `claim_winnings` for one `claim_many` entry. Unresolved, claimed and losing
+    /// positions return None, so one stale entry doesn't fail the batch.
+    pub fn claim_in_batch(
+        &mut self,
+        market_key: Pubkey,
+        position: &mut Position,
+        user: Pubkey,
+    ) -> Result<Option<(u64, u64)>> {
+        require!(
+            position.market == market_key,
+            ErrorCode::InvalidClaimAccounts
+        );
+
+        require!(
+            position.user == user,
+            ErrorCode::Unauthorized
+        );
+
+        if self.status != MarketStatus::Resolved || position.claimed || calculate_payout(self, position)?.1 == 0 {
+            return Ok(None);
+        }
+        self.claim_winnings(position).map(Some)
+    }
+
+    /// Refund a position's share of the refund pool from a cancelled market
+    pub fn claim_refund(&mut self, position: &mut Position) -> Result<u64> {
+        require!(
+            self.status == MarketStatus::Cancelled,
+            ErrorCode::MarketNotCancelled
+        );
+
+        require!(
+            !position.claimed,
+            ErrorCode::AlreadyClaimed
+        );
+
+        let refund = self.refund_for(position.total_stake)?;
+
+        position.claimed = true;
+        self.total_collateral = self.total_collateral.checked_sub(refund).ok_or(ErrorCode::InsolventMarket)?;
+        Ok(refund)
+    }
+
+    /// Release the liquidity provider's share after resolution or cancellation
+    pub fn withdraw_liquidity(&mut self) -> Result<u64> {
+        require!(
+            !self.status.is_unresolved(),
+            ErrorCode::MarketNotResolved
+        );
+
+        let amount = self.lp_claimable;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+
+        self.lp_claimable = 0;
+        self.total_collateral = self.total_collateral.checked_sub(amount).ok_or(ErrorCode::InsolventMarket)?;
+        Ok(amount)
+    }
+}
+
+/// Market layout written before accounts were versioned (v0). It shares
+/// `Market`'s discriminator but not its layout, so `migrate_market` decodes
+/// it explicitly and translates it with `Market::from_v0`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MarketV0 {
+    pub creator: Pubkey,
+    pub model: Pubkey,
+    pub question: String,
+    pub yes_pool: u64,
+    pub no_pool: u64,
+    pub total_volume: u64,
+    pub status: MarketStatus,
+    pub resolution_time: i64,
+    pub resolved_at: i64,
+    pub winning_outcome: Option<bool>,
+    pub min_stake: u64,
+    pub created_at: i64,
+    pub bump: u8,
+    pub amm_enabled: bool,
+    pub virtual_yes_reserve: u64,
+    pub virtual_no_reserve: u64,
+    pub k_constant: u64,
+    pub total_yes_shares: u64,
+    pub total_no_shares: u64,
+}
+
+impl MarketV0 {
+    /// Allocated by v0 `create_market`
+    pub const SPACE: usize = 8 + 32 + 32 + 256 + 8 + 8 + 8 + 1 + 8 + 8 + 9 + 8 + 8 + 1 + 1 + 8 + 8 + 8 + 8 + 8;
+
+    /// Decode v0 account data, discriminator included
+    pub fn try_decode(data: &[u8]) -> Result<MarketV0> {
+        require!(
+            data.len() >= 8 && data[..8] == <Market as anchor_lang::Discriminator>::DISCRIMINATOR,
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+        );
+        MarketV0::deserialize(&mut &data[8..])
+            .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotDeserialize))
+    }
+}
+
+/// Point-in-time copy of a market's price accumulators. Consumers (e.g. the
+/// insurance program via the `cpi` feature) store two of these and call
+/// `get_twap` to read a manipulation-resistant price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct PriceObservation {
+    pub timestamp: i64,
+    pub yes_price_cumulative: u128,
+    pub no_price_cumulative: u128,
+}
+
+/// Time-weighted (yes_price, no_price) in basis points between two observations
+pub fn get_twap(older: &PriceObservation, newer: &PriceObservation) -> Result<(u64, u64)> {
+    let elapsed = newer.timestamp
+        .checked_sub(older.timestamp)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(elapsed > 0, ErrorCode::InvalidTwapWindow);
+
+    let twap = |newer: u128, older: u128| -> Result<u64> {
+        let sum = newer.checked_sub(older).ok_or(ErrorCode::InvalidTwapWindow)?;
+        let price = math::div(sum, elapsed as u128, Rounding::Down).ok_or(ErrorCode::MathOverflow)?;
+        u64::try_from(price).map_err(|_| error!(ErrorCode::MathOverflow))
+    };
+
+    Ok((
+        twap(newer.yes_price_cumulative, older.yes_price_cumulative)?,
+        twap(newer.no_price_cumulative, older.no_price_cumulative)?,
+    ))
+}
+
+#[account]
+#[derive(InitSpace, Default)]
+pub struct Position {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub yes_stake: u64,      // Total USDC bet on YES
+    pub no_stake: u64,       // Total USDC bet on NO
+    pub total_stake: u64,
+    pub yes_shares: u64,     // YES shares owned
+    pub no_shares: u64,      // NO shares owned
+    pub claimed: bool,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 32],         // Zeroed space for future fields
+}
+
+impl Versioned for Position {
+    const VERSION: u8 = 1;
+    // Unversioned accounts predate the version byte, which reads as zero
+    const APPEND_ONLY_SINCE: u8 = 0;
+
+    fn version(&self) -> u8 {
+        self.version
     }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,        // Token account receiving fees and slashed bonds
+    pub creation_bond: u64,      // Refundable bond per market
+    pub creation_fee: u64,       // Non-refundable fee per market
+    pub bump: u8,
+    pub trading_fee_bps: u16,    // Fee on every buy and sell
+    pub referral_fee_bps: u16,   // Referrer share of the trading fee
+    pub referral_vault: Pubkey,  // Token account holding unclaimed referral rewards (owned by config PDA)
+
+    // Exposure floors applied to every market (0 = disabled)
+    pub max_position_stake: u64,
+    pub max_wallet_oi_bps: u16,
+    pub max_price_impact_bps: u16,
+    pub wallet_oi_floor: u64,    // Wallet cap applies once open interest reaches this
+
+    // Drift settlement
+    pub drift_attester: Pubkey,  // Must co-sign settle_drift_market (default = settlement disabled)
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+
+    // Crank tips (0 = disabled)
+    pub crank_tip: u64,          // Paid to callers of the crank instructions (<= creation_fee)
+    pub crank_vault: Pubkey,     // Token account funding tips (owned by config PDA)
+    pub reserved: [u8; 24],      // Zeroed space for future fields
+}
+
+impl Versioned for ProtocolConfig {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl ProtocolConfig {
+    /// Referrer's slice of the protocol part of a trading fee, rounded down
+    pub fn referral_fee(&self, protocol_fee: u64) -> Result<u64> {
+        let fee = bps::apply(protocol_fee, self.referral_fee_bps as u64, Rounding::Down)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(fee)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ReferralAccount {
+    pub referrer: Pubkey,
+    pub referred_volume: u64,    // Total trade volume routed by this referrer
+    pub referred_trades: u64,
+    pub total_earned: u64,
+    pub unclaimed: u64,
+    pub total_claimed: u64,
+    pub created_at: i64,
+    pub bump: u8,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 32],         // Zeroed space for future fields
+}
+
+impl Versioned for ReferralAccount {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl ReferralAccount {
+    fn accrue(&mut self, volume: u64, reward: u64) -> Result<()> {
+        self.referred_volume = self.referred_volume.checked_add(volume).ok_or(ErrorCode::MathOverflow)?;
+        self.referred_trades = self.referred_trades.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        self.total_earned = self.total_earned.checked_add(reward).ok_or(ErrorCode::MathOverflow)?;
+        self.unclaimed = self.unclaimed.checked_add(reward).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Fixed-point scale of `RewardPool::reward_per_share`
+pub const REWARD_PRECISION: u128 = Fixed::SCALE;
+
+/// Sponsor-funded liquidity mining pool shared by one or more markets
+#[account]
+#[derive(InitSpace)]
+pub struct RewardPool {
+    pub sponsor: Pubkey,
+    pub pool_id: u64,
+    pub reward_vault: Pubkey,       // Token account owned by this PDA
+    pub emission_rate: u64,         // Reward tokens per second
+    pub start_time: i64,
+    pub end_time: i64,
+    pub last_update: i64,
+    pub reward_per_share: u128,     // Accumulated rewards per staked unit, scaled by REWARD_PRECISION
+    pub total_staked: u64,          // Sum of synced reward stakes
+    pub total_funded: u64,
+    pub unallocated: u64,           // Emitted while nothing was staked; returnable to the sponsor
+    pub bump: u8,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 64],         // Zeroed space for future fields
+}
+
+impl Versioned for RewardPool {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl RewardPool {
+    /// Advance the accumulator to `now` (capped at `end_time`)
+    pub fn update(&mut self, now: i64) -> Result<()> {
+        let until = now.min(self.end_time);
+        if until <= self.last_update {
+            return Ok(());
+        }
+
+        let emitted = (self.emission_rate as u128)
+            .checked_mul((until - self.last_update) as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if self.total_staked == 0 {
+            let emitted = u64::try_from(emitted).map_err(|_| error!(ErrorCode::MathOverflow))?;
+            self.unallocated = self.unallocated.checked_add(emitted).ok_or(ErrorCode::MathOverflow)?;
+        } else {
+            let per_share = Fixed::ratio(emitted, self.total_staked as u128, Rounding::Down)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.reward_per_share = Fixed::from_raw(self.reward_per_share)
+                .checked_add(per_share)
+                .ok_or(ErrorCode::MathOverflow)?
+                .raw();
+        }
+
+        self.last_update = until;
+        Ok(())
+    }
+}
+
+/// A wallet's stake in a reward pool, mirroring its position's open stake
+#[account]
+#[derive(InitSpace)]
+pub struct RewardStake {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub staked: u64,
+    pub reward_debt: u128,          // staked * reward_per_share at the last sync
+    pub pending: u64,               // Accrued, unclaimed rewards
+    pub total_claimed: u64,
+    pub bump: u8,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 32],         // Zeroed space for future fields
+}
+
+impl Versioned for RewardStake {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl RewardStake {
+    /// Accrue rewards at the pool's current accumulator and move the stake to `staked`.
+    /// Accruals round down and the new debt rounds up, so dust stays in the pool.
+    pub fn sync(&mut self, pool: &mut RewardPool, staked: u64) -> Result<()> {
+        let reward_per_share = Fixed::from_raw(pool.reward_per_share);
+        let accrued = reward_per_share
+            .mul_int(self.staked as u128, Rounding::Down)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let earned = accrued.saturating_sub(self.reward_debt);
+        let earned = u64::try_from(earned).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        self.pending = self.pending.checked_add(earned).ok_or(ErrorCode::MathOverflow)?;
+
+        pool.total_staked = pool.total_staked
+            .checked_sub(self.staked)
+            .and_then(|t| t.checked_add(staked))
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.staked = staked;
+        self.reward_debt = reward_per_share
+            .mul_int(staked as u128, Rounding::Up)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Point a market's reward stakes at `pool`. The sponsor must be the market's
+/// creator or the protocol admin, and the market must outlive the pool.
+fn attach_reward_pool(market: &mut Market, pool: &Account<RewardPool>, config: &ProtocolConfig, now: i64) -> Result<()> {
+    require!(
+        pool.sponsor == market.creator || pool.sponsor == config.admin,
+        ErrorCode::Unauthorized
+    );
+
+    require!(
+        market.status == MarketStatus::Open && now < market.resolution_time,
+        ErrorCode::MarketClosed
+    );
+
+    require!(
+        market.reward_pool.is_none(),
+        ErrorCode::RewardPoolExists
+    );
+
+    require!(
+        now < pool.end_time && pool.end_time <= market.resolution_time,
+        ErrorCode::InvalidRewardParams
+    );
+
+    market.reward_pool = Some(pool.key());
+    Ok(())
+}
+
+/// Keep a trader's reward stake in line with their open stake. Required on
+/// every trade once a market has a reward pool, so stakes can't go stale.
+fn sync_reward_stake(
+    market: &Market,
+    pool: Option<&mut Account<RewardPool>>,
+    stake: Option<&mut Account<RewardStake>>,
+    stake_bump: Option<u8>,
+    trader: Pubkey,
+    position_stake: u64,
+    now: i64,
+) -> Result<()> {
+    let pool_key = match market.reward_pool {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+
+    let pool = pool.ok_or(ErrorCode::MissingRewardAccounts)?;
+    let stake = stake.ok_or(ErrorCode::MissingRewardAccounts)?;
+    require_keys_eq!(pool.key(), pool_key, ErrorCode::MissingRewardAccounts);
+
+    // First sync for this wallet
+    if stake.pool == Pubkey::default() {
+        stake.pool = pool_key;
+        stake.user = trader;
+        stake.bump = stake_bump.ok_or(ErrorCode::MissingRewardAccounts)?;
+        stake.version = RewardStake::VERSION;
+    }
+
+    pool.update(now)?;
+    stake.sync(pool, position_stake)
+}
+
+/// Record a trade into the market's price history. Required on every trade
+/// once the history is initialized, so traders can't leave gaps in it.
+fn record_price_history(
+    market: &Market,
+    price_history: Option<&AccountLoader<PriceHistory>>,
+    now: i64,
+    volume: u64,
+) -> Result<()> {
+    let history_key = match market.price_history {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+
+    let price_history = price_history.ok_or(ErrorCode::MissingPriceHistory)?;
+    require_keys_eq!(price_history.key(), history_key, ErrorCode::MissingPriceHistory);
+
+    price_history.load_mut()?.record(market, now, volume)
+}
+
+/// Per-wallet trading record used as the leaderboard source.
+/// `realized_pnl` is before fees; subtract `fees_paid` for net PnL.
+#[account]
+#[derive(InitSpace)]
+pub struct UserStats {
+    pub user: Pubkey,
+    pub total_volume: u64,
+    pub trade_count: u64,
+    pub markets_traded: u64,
+    pub realized_pnl: i64,
+    pub winning_resolutions: u64,
+    pub losing_resolutions: u64,
+    pub fees_paid: u64,
+    pub last_trade_at: i64,
+    pub bump: u8,
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 32],         // Zeroed space for future fields
+}
+
+impl Versioned for UserStats {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl UserStats {
+    fn record_trade(&mut self, volume: u64, fee: u64, new_market: bool, now: i64) -> Result<()> {
+        self.total_volume = self.total_volume.checked_add(volume).ok_or(ErrorCode::MathOverflow)?;
+        self.trade_count = self.trade_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        if new_market {
+            self.markets_traded = self.markets_traded.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        }
+        self.fees_paid = self.fees_paid.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+        self.last_trade_at = now;
+        Ok(())
+    }
+
+    fn record_pnl(&mut self, pnl: i64) -> Result<()> {
+        self.realized_pnl = self.realized_pnl.checked_add(pnl).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    fn record_resolution(&mut self, pnl: i64, won: bool) -> Result<()> {
+        self.record_pnl(pnl)?;
+        if won {
+            self.winning_resolutions = self.winning_resolutions.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        } else {
+            self.losing_resolutions = self.losing_resolutions.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(())
+    }
+}
+
+/// Proceeds minus cost basis as a signed amount
+fn signed_pnl(proceeds: u64, cost: u64) -> Result<i64> {
+    i64::try_from(proceeds as i128 - cost as i128).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Max markets a session key can be restricted to
+pub const MAX_SESSION_MARKETS: usize = 8;
+
+/// Delegated trading authority for an ephemeral key
+#[account]
+#[derive(InitSpace)]
+pub struct SessionKey {
+    pub owner: Pubkey,
+    pub session_key: Pubkey,
+    pub spend_cap: u64,              // Max total stake the key can place
+    pub spent: u64,
+    #[max_len(8)]
+    pub allowed_markets: Vec<Pubkey>, // Empty = any market
+    pub expires_at: i64,
+    pub created_at: i64,
+    pub bump: u8,
+    pub vault: Pubkey,               // Token account holding the unspent cap (owned by the session PDA)
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 32],         // Zeroed space for future fields
+}
+
+impl Versioned for SessionKey {
+    const VERSION: u8 = 1;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl SessionKey {
+    /// Check a session trade against expiry, market allowlist and spend cap, and record the spend
+    pub fn authorize(&mut self, market: Pubkey, spend: u64, now: i64) -> Result<()> {
+        require!(now < self.expires_at, ErrorCode::SessionExpired);
+
+        require!(
+            self.allowed_markets.is_empty() || self.allowed_markets.contains(&market),
+            ErrorCode::SessionMarketNotAllowed
+        );
+
+        let spent = self.spent.checked_add(spend).ok_or(ErrorCode::MathOverflow)?;
+        require!(spent <= self.spend_cap, ErrorCode::SessionSpendCapExceeded);
+        self.spent = spent;
+
+        Ok(())
+    }
+}
+
+/// Wallet a trade acts for: the signer, or the owner of the session key it signs with
+fn trader_key(user: &Signer, session: &Option<Account<SessionKey>>) -> Pubkey {
+    session.as_ref().map_or(user.key(), |s| s.owner)
+}
+
+/// Source of a trader's payments: the signer's token account, or the
+/// session vault signed for by the session PDA
+struct TradePayer<'a, 'info> {
+    user: &'a Signer<'info>,
+    session: &'a Option<Account<'info, SessionKey>>,
+    token_account: &'a Account<'info, TokenAccount>,
+    token_program: &'a Program<'info, Token>,
+}
+
+impl<'a, 'info> TradePayer<'a, 'info> {
+    fn transfer(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+        match self.session {
+            Some(session) => {
+                let seeds = &[
+                    b"session",
+                    session.owner.as_ref(),
+                    session.session_key.as_ref(),
+                    &[session.bump],
+                ];
+                let signer = &[&seeds[..]];
+                let cpi_accounts = Transfer {
+                    from: self.token_account.to_account_info(),
+                    to,
+                    authority: session.to_account_info(),
+                };
+                token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer), amount)
+            }
+            None => {
+                let cpi_accounts = Transfer {
+                    from: self.token_account.to_account_info(),
+                    to,
+                    authority: self.user.to_account_info(),
+                };
+                token::transfer(CpiContext::new(cpi_program, cpi_accounts), amount)
+            }
+        }
+    }
+}
+
+/// Number of samples kept in a market's price history ring buffer
+pub const PRICE_HISTORY_CAPACITY: usize = 256;
+
+/// Zero-copy ring buffer of price samples, one per market.
+/// The whole history can be read with a single getAccountInfo call.
+#[account(zero_copy)]
+pub struct PriceHistory {
+    pub market: Pubkey,
+    pub sample_interval: i64,    // Minimum seconds between samples
+    pub last_sample_at: i64,
+    pub pending_volume: u64,     // Volume traded since the last sample
+    pub head: u32,               // Index of the next slot to write
+    pub len: u32,                // Number of valid samples (<= capacity)
+    pub samples: [PriceSample; PRICE_HISTORY_CAPACITY],
+
+    // Versioning
+    pub version: u8,                // Layout version, see Versioned::VERSION
+    pub reserved: [u8; 63],         // Zeroed space for future fields
+}
+
+#[zero_copy]
+pub struct PriceSample {
+    pub timestamp: i64,
+    pub yes_price: u64,          // Basis points
+    pub no_price: u64,           // Basis points
+    pub volume: u64,             // Volume since the previous sample
+}
+
+impl PriceHistory {
+    pub const VERSION: u8 = 1;
+
+    /// Add trade volume and write a sample if the interval has elapsed
+    pub fn record(&mut self, market: &Market, now: i64, volume: u64) -> Result<()> {
+        self.pending_volume = self.pending_volume.saturating_add(volume);
+
+        if self.len > 0 && now < self.last_sample_at.saturating_add(self.sample_interval) {
+            return Ok(());
+        }
+
+        self.samples[self.head as usize] = PriceSample {
+            timestamp: now,
+            yes_price: get_yes_price(market)?,
+            no_price: get_no_price(market)?,
+            volume: self.pending_volume,
+        };
+        self.head = (self.head + 1) % PRICE_HISTORY_CAPACITY as u32;
+        self.len = (self.len + 1).min(PRICE_HISTORY_CAPACITY as u32);
+        self.last_sample_at = now;
+        self.pending_volume = 0;
+        Ok(())
+    }
+}
+
+/// Parameters shared by every market creation path
+pub struct MarketParams {
+    pub model: Pubkey,
+    pub question: String,
+    pub resolution_time: i64,
+    pub min_stake: u64,
+    pub initial_liquidity: u64,
+    pub allowlist_root: Option<[u8; 32]>,
+}
+
+/// Max question length; the Market account reserves 256 bytes including the length prefix
+pub const MAX_QUESTION_LEN: usize = 252;
+
+/// Model metric a drift market is written on
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum DriftMetric {
+    Accuracy,
+    Precision,
+    Recall,
+    F1Score,
+    DriftScore,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum Comparator {
+    Below,
+    AtOrBelow,
+    Above,
+    AtOrAbove,
+}
+
+/// Structured drift condition: `metric comparator threshold`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct DriftCondition {
+    pub metric: DriftMetric,
+    pub comparator: Comparator,
+    pub threshold: u64,          // Basis points
+}
+
+impl DriftMetric {
+    fn label(&self) -> &'static str {
+        match self {
+            DriftMetric::Accuracy => "accuracy",
+            DriftMetric::Precision => "precision",
+            DriftMetric::Recall => "recall",
+            DriftMetric::F1Score => "F1 score",
+            DriftMetric::DriftScore => "drift score",
+        }
+    }
+
+    fn read(&self, receipt: &MonitoringReceipt) -> u64 {
+        match self {
+            DriftMetric::Accuracy => receipt.accuracy,
+            DriftMetric::Precision => receipt.precision,
+            DriftMetric::Recall => receipt.recall,
+            DriftMetric::F1Score => receipt.f1_score,
+            DriftMetric::DriftScore => receipt.drift_score,
+        }
+    }
+}
+
+impl Comparator {
+    fn label(&self) -> &'static str {
+        match self {
+            Comparator::Below => "below",
+            Comparator::AtOrBelow => "at or below",
+            Comparator::Above => "above",
+            Comparator::AtOrAbove => "at or above",
+        }
+    }
+
+    fn compare(&self, value: u64, threshold: u64) -> bool {
+        match self {
+            Comparator::Below => value < threshold,
+            Comparator::AtOrBelow => value <= threshold,
+            Comparator::Above => value > threshold,
+            Comparator::AtOrAbove => value >= threshold,
+        }
+    }
+}
+
+impl DriftCondition {
+    pub fn is_met(&self, receipt: &MonitoringReceipt) -> bool {
+        self.comparator.compare(self.metric.read(receipt), self.threshold)
+    }
+
+    /// Canonical question, e.g. "Will fraud-detector accuracy fall below 90.00% by 2026-11-30 UTC?".
+    /// Long model names are truncated so the question fits the account.
+    pub fn question(&self, model_name: &str, deadline: i64) -> String {
+        let verb = match self.comparator {
+            Comparator::Below | Comparator::AtOrBelow => "fall",
+            Comparator::Above | Comparator::AtOrAbove => "rise",
+        };
+        let suffix = format!(
+            " {} {} {} {}.{:02}% by {} UTC?",
+            self.metric.label(),
+            verb,
+            self.comparator.label(),
+            self.threshold / 100,
+            self.threshold % 100,
+            format_date(deadline),
+        );
+
+        let mut name_len = MAX_QUESTION_LEN.saturating_sub("Will".len() + 1 + suffix.len()).min(model_name.len());
+        while !model_name.is_char_boundary(name_len) {
+            name_len -= 1;
+        }
+        format!("Will {}{}", &model_name[..name_len], suffix)
+    }
+}
+
+/// Format a unix timestamp as YYYY-MM-DD (UTC)
+fn format_date(timestamp: i64) -> String {
+    // Civil-from-days (proleptic Gregorian calendar)
+    let z = timestamp.div_euclid(86400) + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Result of `quote_buy`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BuyQuote {
+    pub amount: u64,             // Gross amount paid
+    pub fee: u64,                // Trading fee included in amount
+    pub fee_bps: u16,            // Fee rate, including any dynamic fee
+    pub net_amount: u64,         // Amount added to the pool
+    pub shares: u64,             // Shares received
+    pub average_price: u64,      // Basis points per share
+    pub price_impact: u64,       // Move in the bought outcome's price (bps)
+    pub yes_price_after: u64,
+    pub no_price_after: u64,
+}
+
+/// Result of `quote_sell`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SellQuote {
+    pub shares: u64,             // Shares sold
+    pub gross_proceeds: u64,     // Amount released by the AMM
+    pub fee: u64,                // Trading fee withheld
+    pub fee_bps: u16,            // Fee rate, including any dynamic fee
+    pub proceeds: u64,           // Amount received
+    pub average_price: u64,      // Basis points per share
+    pub price_impact: u64,       // Move in the sold outcome's price (bps)
+    pub yes_price_after: u64,
+    pub no_price_after: u64,
+}
+
+/// Outcome of `Market::apply_buy`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BuyFill {
+    pub fee_bps: u16,
+    pub fee: u64,                // Trading fee included in the amount paid
+    pub lp_fee: u64,             // Dynamic part of the fee, kept for the LP
+    pub net_amount: u64,         // Stake added to the position
+    pub vault_amount: u64,       // Paid into the market vault (stake plus LP fee)
+    pub shares: u64,
+    pub resumed: bool,           // An expired circuit-breaker halt was cleared
+    pub halted: bool,            // The trade tripped the circuit breaker
+}
+
+/// Outcome of `Market::apply_sell`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SellFill {
+    pub fee_bps: u16,
+    pub fee: u64,                // Trading fee withheld from the proceeds
+    pub lp_fee: u64,             // Dynamic part of the fee, kept for the LP
+    pub collateral_out: u64,     // Released by the pool, before the fee
+    pub proceeds: u64,           // Paid to the seller
+    pub vault_amount: u64,       // Paid out of the market vault (proceeds plus protocol fee)
+    pub released_stake: u64,     // Cost basis of the shares sold
+    pub resumed: bool,
+    pub halted: bool,
+}
+
+/// Result of `quote_claim`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ClaimQuote {
+    pub winning_outcome: bool,
+    pub winning_shares: u64,
+    pub payout: u64,
+    pub already_claimed: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum MarketStatus {
+    Open,
+    Resolved,
+    Cancelled,
+    Closed,     // Past resolution_time, trading closed by close_market
+}
+
+impl MarketStatus {
+    /// Open or closed to trading, but not yet resolved or cancelled
+    pub fn is_unresolved(&self) -> bool {
+        matches!(self, MarketStatus::Open | MarketStatus::Closed)
+    }
+}
+
+// Context Structures
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = ProtocolConfig::SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub treasury: Account<'info, TokenAccount>,
+    #[account(
+        constraint = referral_vault.owner == config.key() @ ErrorCode::InvalidReferralVault,
+        constraint = referral_vault.mint == treasury.mint @ ErrorCode::InvalidReferralVault
+    )]
+    pub referral_vault: Account<'info, TokenAccount>,
+    // Only the upgrade authority can claim the config, so it can't be front-run
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized)]
+    pub program: Program<'info, crate::program::PredictionMarket>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    pub treasury: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct SetCrankTip<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    #[account(constraint = crank_vault.owner == config.key() @ ErrorCode::InvalidCrankVault)]
+    pub crank_vault: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct SetDriftAttester<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetExposureFloors<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(model_pubkey: Pubkey)]
+pub struct CreateMarket<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Market::SPACE,
+        seeds = [b"market", creator.key().as_ref(), model_pubkey.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(
+        mut,
+        constraint = market_vault.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_vault.mint == treasury.mint @ ErrorCode::InvalidMarketVault
+    )]
+    pub market_vault: Account<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = bond_vault.owner == market.key() @ ErrorCode::InvalidBondVault,
+        constraint = bond_vault.mint == treasury.mint @ ErrorCode::InvalidBondVault
+    )]
+    pub bond_vault: Account<'info, TokenAccount>,
+    #[account(mut, address = config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
-/// Calculate SOL out when selling shares (reverse of calculate_shares_out)
-/// When selling YES shares:
-/// - Add shares back to no_reserve
-/// - Calculate new yes_reserve to maintain k
-/// - SOL out = old_yes_reserve - new_yes_reserve
-fn calculate_sol_out(market: &Market, outcome: bool, shares: u64) -> Result<u64> {
-    if outcome {
-        // Selling YES shares
-        let new_no_reserve = market.virtual_no_reserve
-            .checked_add(shares)
-            .ok_or(ErrorCode::MathOverflow)?;
+#[derive(Accounts)]
+pub struct CreateDriftMarket<'info> {
+    pub model: Account<'info, ModelAccount>,
+    pub create: CreateMarket<'info>,
+}
 
-        let new_yes_reserve = (market.k_constant as u128)
-            .checked_div(new_no_reserve as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+impl<'info> CrankTip<'info> {
+    /// Pay the configured tip for cranking `target`, capped at what the vault
+    /// holds so an underfunded vault never blocks a crank. Returns the tip paid.
+    fn pay(&self, target: Pubkey, now: i64) -> Result<u64> {
+        let (vault, cranker_token_account) = match (&self.crank_vault, &self.cranker_token_account) {
+            (Some(vault), Some(account)) => (vault, account),
+            _ => return Ok(0),
+        };
 
-        let sol_out = market.virtual_yes_reserve
-            .checked_sub(new_yes_reserve)
-            .ok_or(ErrorCode::InsufficientLiquidity)?;
+        let tip = self.config.crank_tip.min(vault.amount);
+        if tip == 0 {
+            return Ok(0);
+        }
 
-        Ok(sol_out)
-    } else {
-        // Selling NO shares
-        let new_yes_reserve = market.virtual_yes_reserve
-            .checked_add(shares)
-            .ok_or(ErrorCode::MathOverflow)?;
+        let seeds = &[b"config".as_ref(), &[self.config.bump]];
+        let signer = &[&seeds[..]];
 
-        let new_no_reserve = (market.k_constant as u128)
-            .checked_div(new_yes_reserve as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+        let cpi_accounts = Transfer {
+            from: vault.to_account_info(),
+            to: cranker_token_account.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, tip)?;
 
-        let sol_out = market.virtual_no_reserve
-            .checked_sub(new_no_reserve)
-            .ok_or(ErrorCode::InsufficientLiquidity)?;
+        emit!(CrankTipPaid {
+            cranker: self.cranker.key(),
+            target,
+            tip,
+            timestamp: now,
+        });
 
-        Ok(sol_out)
+        Ok(tip)
     }
 }
 
-/// Get YES price (as basis points, e.g., 5000 = 50%)
-fn get_yes_price(market: &Market) -> u64 {
-    let total_reserve = market.virtual_yes_reserve
-        .saturating_add(market.virtual_no_reserve);
+impl<'info> CreateMarket<'info> {
+    /// Shared market initialization for `create_market` and `create_drift_market`
+    fn initialize(&mut self, bump: u8, params: MarketParams) -> Result<()> {
+        let market = &mut self.market;
+        let clock = Clock::get()?;
+        let model = params.model;
+        let initial_liquidity = params.initial_liquidity;
 
-    if total_reserve == 0 {
-        return 5000; // 50% default
+        market.open(params, clock.unix_timestamp)?;
+        market.creator = self.creator.key();
+        market.bump = bump;
+        market.vault = self.market_vault.key();
+
+        let cpi_accounts = Transfer {
+            from: self.creator_token_account.to_account_info(),
+            to: self.market_vault.to_account_info(),
+            authority: self.creator.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, initial_liquidity)?;
+
+        // Creation fee goes to the treasury, the bond is escrowed per market
+        let config = &self.config;
+        if config.creation_fee > 0 {
+            let cpi_accounts = Transfer {
+                from: self.creator_token_account.to_account_info(),
+                to: self.treasury.to_account_info(),
+                authority: self.creator.to_account_info(),
+            };
+            let cpi_program = self.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::transfer(cpi_ctx, config.creation_fee)?;
+        }
+        if config.creation_bond > 0 {
+            let cpi_accounts = Transfer {
+                from: self.creator_token_account.to_account_info(),
+                to: self.bond_vault.to_account_info(),
+                authority: self.creator.to_account_info(),
+            };
+            let cpi_program = self.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::transfer(cpi_ctx, config.creation_bond)?;
+        }
+        market.creator_bond = config.creation_bond;
+        market.bond_vault = self.bond_vault.key();
+        market.bond_settled = config.creation_bond == 0;
+
+        emit!(MarketCreated {
+            market_key: market.key(),
+            creator: market.creator,
+            model,
+            question: market.question.clone(),
+            initial_liquidity,
+            creation_bond: config.creation_bond,
+            creation_fee: config.creation_fee,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
     }
+}
 
-    // Price = (yes_reserve / total_reserve) * 10000
-    ((market.virtual_yes_reserve as u128 * 10000) / total_reserve as u128) as u64
+#[derive(Accounts)]
+pub struct PlaceBet<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = Position::SPACE,
+        seeds = [b"position", market.key().as_ref(), trader_key(&user, &session).as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut, seeds = [b"session", session.owner.as_ref(), user.key().as_ref()], bump = session.bump)]
+    pub session: Option<Account<'info, SessionKey>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserStats::SPACE,
+        seeds = [b"user_stats", trader_key(&user, &session).as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = market.vault @ ErrorCode::InvalidMarketVault)]
+    pub market_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"price_history", market.key().as_ref()], bump)]
+    pub price_history: Option<AccountLoader<'info, PriceHistory>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"referral", referral.referrer.as_ref()], bump = referral.bump)]
+    pub referral: Option<Account<'info, ReferralAccount>>,
+    #[account(mut, address = config.referral_vault @ ErrorCode::InvalidReferralVault)]
+    pub referral_vault: Option<Account<'info, TokenAccount>>,
+    pub parent_market: Option<Account<'info, Market>>,
+    #[account(mut)]
+    pub reward_pool: Option<Account<'info, RewardPool>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = RewardStake::SPACE,
+        seeds = [b"reward_stake", market.key().as_ref(), trader_key(&user, &session).as_ref()],
+        bump
+    )]
+    pub reward_stake: Option<Account<'info, RewardStake>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
-/// Get NO price (as basis points)
-fn get_no_price(market: &Market) -> u64 {
-    let total_reserve = market.virtual_yes_reserve
-        .saturating_add(market.virtual_no_reserve);
+#[derive(Accounts)]
+pub struct SellShares<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut, has_one = market)]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut, seeds = [b"session", session.owner.as_ref(), user.key().as_ref()], bump = session.bump)]
+    pub session: Option<Account<'info, SessionKey>>,
+    #[account(mut, seeds = [b"user_stats", trader_key(&user, &session).as_ref()], bump = user_stats.bump)]
+    pub user_stats: Account<'info, UserStats>,
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = market.vault @ ErrorCode::InvalidMarketVault)]
+    pub market_vault: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"price_history", market.key().as_ref()], bump)]
+    pub price_history: Option<AccountLoader<'info, PriceHistory>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"referral", referral.referrer.as_ref()], bump = referral.bump)]
+    pub referral: Option<Account<'info, ReferralAccount>>,
+    #[account(mut, address = config.referral_vault @ ErrorCode::InvalidReferralVault)]
+    pub referral_vault: Option<Account<'info, TokenAccount>>,
+    pub parent_market: Option<Account<'info, Market>>,
+    #[account(mut)]
+    pub reward_pool: Option<Account<'info, RewardPool>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = RewardStake::SPACE,
+        seeds = [b"reward_stake", market.key().as_ref(), trader_key(&user, &session).as_ref()],
+        bump
+    )]
+    pub reward_stake: Option<Account<'info, RewardStake>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
 
-    if total_reserve == 0 {
-        return 5000; // 50% default
-    }
+#[derive(Accounts)]
+pub struct ResolveMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
+    pub parent_market: Option<Account<'info, Market>>,
+}
 
-    ((market.virtual_no_reserve as u128 * 10000) / total_reserve as u128) as u64
+#[derive(Accounts)]
+pub struct SettleDriftMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub receipt: Account<'info, MonitoringReceipt>,
+    pub parent_market: Option<Account<'info, Market>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(address = config.drift_attester @ ErrorCode::Unauthorized)]
+    pub attester: Signer<'info>,
 }
 
-// Account Structures
+/// Tip accounts shared by the crank instructions. The tip is skipped when
+/// the vault or the caller's token account is omitted.
+#[derive(Accounts)]
+pub struct CrankTip<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub cranker: Signer<'info>,
+    #[account(mut, address = config.crank_vault @ ErrorCode::InvalidCrankVault)]
+    pub crank_vault: Option<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = cranker_token_account.owner == cranker.key() @ ErrorCode::InvalidTokenOwner)]
+    pub cranker_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
 
-#[account]
-pub struct Market {
-    pub creator: Pubkey,
-    pub model: Pubkey,           // Reference to model in registry
-    pub question: String,         // Max 256 chars
-    pub yes_pool: u64,           // Total USDC bet on YES
-    pub no_pool: u64,            // Total USDC bet on NO
-    pub total_volume: u64,
-    pub status: MarketStatus,
-    pub resolution_time: i64,
-    pub resolved_at: i64,
-    pub winning_outcome: Option<bool>,
-    pub min_stake: u64,
-    pub created_at: i64,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub tip: CrankTip<'info>,
+}
 
-    // AMM fields
-    pub amm_enabled: bool,
-    pub virtual_yes_reserve: u64,  // Virtual YES tokens for pricing
-    pub virtual_no_reserve: u64,   // Virtual NO tokens for pricing
-    pub k_constant: u64,           // Constant product (x * y = k)
-    pub total_yes_shares: u64,     // Total YES shares issued
-    pub total_no_shares: u64,      // Total NO shares issued
+#[derive(Accounts)]
+pub struct FinalizeDriftMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub parent_market: Option<Account<'info, Market>>,
+    pub tip: CrankTip<'info>,
 }
 
-#[account]
-pub struct Position {
-    pub market: Pubkey,
-    pub user: Pubkey,
-    pub yes_stake: u64,      // Total USDC bet on YES
-    pub no_stake: u64,       // Total USDC bet on NO
-    pub total_stake: u64,
-    pub yes_shares: u64,     // YES shares owned
-    pub no_shares: u64,      // NO shares owned
-    pub claimed: bool,
+#[derive(Accounts)]
+pub struct VoidAbandonedMarket<'info> {
+    #[account(mut, seeds = [b"market", market.creator.as_ref(), market.model.as_ref()], bump = market.bump)]
+    pub market: Account<'info, Market>,
+    pub tip: CrankTip<'info>,
+    #[account(mut, address = market.bond_vault @ ErrorCode::InvalidBondVault)]
+    pub bond_vault: Account<'info, TokenAccount>,
+    #[account(mut, address = tip.config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Account<'info, TokenAccount>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum MarketStatus {
-    Open,
-    Resolved,
-    Cancelled,
+#[derive(Accounts)]
+pub struct ClaimWinnings<'info> {
+    #[account(mut, seeds = [b"market", market.creator.as_ref(), market.model.as_ref()], bump)]
+    pub market: Account<'info, Market>,
+    #[account(mut, has_one = market)]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut, seeds = [b"session", session.owner.as_ref(), user.key().as_ref()], bump = session.bump)]
+    pub session: Option<Account<'info, SessionKey>>,
+    // Positions in markets migrated from v0 predate user stats
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserStats::SPACE,
+        seeds = [b"user_stats", trader_key(&user, &session).as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = market.vault @ ErrorCode::InvalidMarketVault)]
+    pub market_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
-// Context Structures
+#[derive(Accounts)]
+pub struct SettleLosingPosition<'info> {
+    pub market: Account<'info, Market>,
+    #[account(mut, has_one = market)]
+    pub position: Account<'info, Position>,
+    #[account(mut, seeds = [b"user_stats", position.user.as_ref()], bump = user_stats.bump)]
+    pub user_stats: Account<'info, UserStats>,
+}
 
 #[derive(Accounts)]
-#[instruction(model_pubkey: Pubkey)]
-pub struct CreateMarket<'info> {
+pub struct ClaimMany<'info> {
+    pub user: Signer<'info>,
+    #[account(mut, seeds = [b"user_stats", user.key().as_ref()], bump = user_stats.bump)]
+    pub user_stats: Account<'info, UserStats>,
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct CreateSessionKey<'info> {
     #[account(
         init,
-        payer = creator,
-        space = 8 + 32 + 32 + 256 + 8 + 8 + 8 + 1 + 8 + 8 + 9 + 8 + 8 + 1 + 1 + 8 + 8 + 8 + 8 + 8,
-        seeds = [b"market", creator.key().as_ref(), model_pubkey.as_ref()],
+        payer = owner,
+        space = SessionKey::SPACE,
+        seeds = [b"session", owner.key().as_ref(), session_key.as_ref()],
+        bump
+    )]
+    pub session: Account<'info, SessionKey>,
+    #[account(
+        mut,
+        constraint = session_vault.owner == session.key() @ ErrorCode::InvalidSessionVault,
+        constraint = session_vault.mint == owner_token_account.mint @ ErrorCode::InvalidSessionVault
+    )]
+    pub session_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, constraint = owner_token_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner)]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSessionKey<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        close = owner,
+        seeds = [b"session", owner.key().as_ref(), session.session_key.as_ref()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, SessionKey>,
+    #[account(mut, address = session.vault @ ErrorCode::InvalidSessionVault)]
+    pub session_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, constraint = owner_token_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner)]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct CreateRewardPool<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        init,
+        payer = sponsor,
+        space = RewardPool::SPACE,
+        seeds = [b"reward_pool", sponsor.key().as_ref(), pool_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+    #[account(mut, constraint = reward_vault.owner == reward_pool.key() @ ErrorCode::InvalidRewardVault)]
+    pub reward_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    #[account(mut)]
+    pub sponsor_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddRewardMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(has_one = sponsor @ ErrorCode::Unauthorized)]
+    pub reward_pool: Account<'info, RewardPool>,
+    pub sponsor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(constraint = market.reward_pool == Some(reward_pool.key()) @ ErrorCode::InvalidRewardPool)]
+    pub market: Account<'info, Market>,
+    #[account(mut)]
+    pub reward_pool: Account<'info, RewardPool>,
+    #[account(
+        mut,
+        seeds = [b"reward_stake", market.key().as_ref(), user.key().as_ref()],
+        bump = reward_stake.bump,
+        constraint = reward_stake.pool == reward_pool.key() @ ErrorCode::InvalidRewardPool
+    )]
+    pub reward_stake: Account<'info, RewardStake>,
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = reward_pool.reward_vault @ ErrorCode::InvalidRewardVault)]
+    pub reward_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUnallocatedRewards<'info> {
+    #[account(mut, has_one = sponsor @ ErrorCode::Unauthorized)]
+    pub reward_pool: Account<'info, RewardPool>,
+    pub sponsor: Signer<'info>,
+    #[account(mut)]
+    pub sponsor_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = reward_pool.reward_vault @ ErrorCode::InvalidRewardVault)]
+    pub reward_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(
+        init,
+        payer = referrer,
+        space = ReferralAccount::SPACE,
+        seeds = [b"referral", referrer.key().as_ref()],
         bump
     )]
+    pub referral: Account<'info, ReferralAccount>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(mut, seeds = [b"referral", referrer.key().as_ref()], bump = referral.bump)]
+    pub referral: Account<'info, ReferralAccount>,
+    pub referrer: Signer<'info>,
+    #[account(mut)]
+    pub referrer_token_account: Account<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = config.referral_vault @ ErrorCode::InvalidReferralVault)]
+    pub referral_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimCreatorBond<'info> {
+    #[account(mut, seeds = [b"market", market.creator.as_ref(), market.model.as_ref()], bump = market.bump)]
+    pub market: Account<'info, Market>,
+    pub creator: Signer<'info>,
+    #[account(mut)]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = market.bond_vault @ ErrorCode::InvalidBondVault)]
+    pub bond_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelMarket<'info> {
+    #[account(mut, seeds = [b"market", market.creator.as_ref(), market.model.as_ref()], bump = market.bump)]
     pub market: Account<'info, Market>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    #[account(mut, address = market.bond_vault @ ErrorCode::InvalidBondVault)]
+    pub bond_vault: Account<'info, TokenAccount>,
+    #[account(mut, address = config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct LinkParentMarket<'info> {
     #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub parent_market: Account<'info, Market>,
     pub creator: Signer<'info>,
-    /// CHECK: Market vault for holding USDC
-    pub market_vault: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct PlaceBet<'info> {
+pub struct VoidConditionalMarket<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1,
-        seeds = [b"position", market.key().as_ref(), user.key().as_ref()],
-        bump
-    )]
+    pub parent_market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(mut, seeds = [b"market", market.creator.as_ref(), market.model.as_ref()], bump = market.bump)]
+    pub market: Account<'info, Market>,
+    #[account(mut, has_one = market)]
     pub position: Account<'info, Position>,
-    #[account(mut)]
     pub user: Signer<'info>,
     #[account(mut)]
     pub user_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = market.vault @ ErrorCode::InvalidMarketVault)]
+    pub market_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLiquidity<'info> {
+    #[account(mut, seeds = [b"market", market.creator.as_ref(), market.model.as_ref()], bump = market.bump)]
+    pub market: Account<'info, Market>,
+    pub creator: Signer<'info>,
     #[account(mut)]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    #[account(mut, address = market.vault @ ErrorCode::InvalidMarketVault)]
     pub market_vault: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SellShares<'info> {
+pub struct SetAllowlistRoot<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetExposureLimits<'info> {
     #[account(mut)]
-    pub position: Account<'info, Position>,
-    #[account(mut)]
-    pub user: Signer<'info>,
+    pub market: Account<'info, Market>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetDynamicFees<'info> {
     #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub market: Account<'info, Market>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCircuitBreaker<'info> {
     #[account(mut)]
-    pub market_vault: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub market: Account<'info, Market>,
+    pub creator: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ResolveMarket<'info> {
+pub struct ResumeMarket<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimWinnings<'info> {
-    #[account(mut, seeds = [b"market", market.creator.as_ref(), market.model.as_ref()], bump)]
-    pub market: Account<'info, Market>,
-    #[account(mut)]
-    pub position: Account<'info, Position>,
-    pub user: Signer<'info>,
+pub struct InitializePriceHistory<'info> {
     #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + std::mem::size_of::<PriceHistory>(),
+        seeds = [b"price_history", market.key().as_ref()],
+        bump
+    )]
+    pub price_history: AccountLoader<'info, PriceHistory>,
     #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPriceHistoryInterval<'info> {
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"price_history", market.key().as_ref()],
+        bump
+    )]
+    pub price_history: AccountLoader<'info, PriceHistory>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMarket<'info> {
+    /// CHECK: Still in the v0 layout; decoded by the handler
+    #[account(mut, owner = ID)]
+    pub market: UncheckedAccount<'info>,
+    pub creator: Signer<'info>,
+    #[account(
+        constraint = market_vault.owner == market.key() @ ErrorCode::InvalidMarketVault,
+        constraint = market_vault.mint == treasury.mint @ ErrorCode::InvalidMarketVault
+    )]
     pub market_vault: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(address = config.treasury @ ErrorCode::InvalidTreasury)]
+    pub treasury: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -622,6 +4652,24 @@ pub struct GetPrices<'info> {
     pub market: Account<'info, Market>,
 }
 
+#[derive(Accounts)]
+pub struct QuoteTrade<'info> {
+    pub market: Account<'info, Market>,
+    #[account(has_one = market)]
+    pub position: Option<Account<'info, Position>>,   // The trader's position, if any
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct QuoteClaim<'info> {
+    pub market: Account<'info, Market>,
+    #[account(has_one = market)]
+    pub position: Account<'info, Position>,
+}
+
+migrate_account_context!();
+
 // Events
 
 #[event]
@@ -630,7 +4678,20 @@ pub struct MarketCreated {
     pub creator: Pubkey,
     pub model: Pubkey,
     pub question: String,
-    pub virtual_liquidity: u64,
+    pub initial_liquidity: u64,
+    pub creation_bond: u64,
+    pub creation_fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DriftMarketCreated {
+    pub market_key: Pubkey,
+    pub model: Pubkey,
+    pub metric: DriftMetric,
+    pub comparator: Comparator,
+    pub threshold: u64,
+    pub resolution_time: i64,
     pub timestamp: i64,
 }
 
@@ -641,6 +4702,9 @@ pub struct BetPlaced {
     pub outcome: bool,
     pub amount: u64,
     pub shares: u64,
+    pub fee: u64,        // Trading fee included in amount
+    pub fee_bps: u16,    // Fee rate charged, including any dynamic fee
+    pub referrer: Option<Pubkey>,
     pub yes_price: u64,  // Basis points (5000 = 50%)
     pub no_price: u64,
     pub timestamp: i64,
@@ -653,6 +4717,9 @@ pub struct SharesSold {
     pub outcome: bool,
     pub shares: u64,
     pub sol_received: u64,
+    pub fee: u64,        // Trading fee withheld from proceeds
+    pub fee_bps: u16,    // Fee rate charged, including any dynamic fee
+    pub referrer: Option<Pubkey>,
     pub yes_price: u64,
     pub no_price: u64,
     pub timestamp: i64,
@@ -669,6 +4736,268 @@ pub struct MarketResolved {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct LosingPositionSettled {
+    pub market_key: Pubkey,
+    pub user: Pubkey,
+    pub stake_lost: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BatchClaimed {
+    pub user: Pubkey,
+    pub claimed_count: u32,
+    pub skipped_count: u32,
+    pub total_payout: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityWithdrawn {
+    pub market_key: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub creation_bond: u64,
+    pub creation_fee: u64,
+    pub trading_fee_bps: u16,
+    pub referral_fee_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CrankTipUpdated {
+    pub crank_tip: u64,
+    pub crank_vault: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DriftAttesterUpdated {
+    pub drift_attester: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CrankTipPaid {
+    pub cranker: Pubkey,
+    pub target: Pubkey,  // Account the crank acted on
+    pub tip: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketClosed {
+    pub market_key: Pubkey,
+    pub yes_price: u64,  // Final spot prices (basis points)
+    pub no_price: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SessionKeyCreated {
+    pub owner: Pubkey,
+    pub session_key: Pubkey,
+    pub vault: Pubkey,
+    pub spend_cap: u64,
+    pub allowed_markets: Vec<Pubkey>,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ExposureFloorsUpdated {
+    pub max_position_stake: u64,
+    pub max_wallet_oi_bps: u16,
+    pub max_price_impact_bps: u16,
+    pub wallet_oi_floor: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SessionKeyRevoked {
+    pub owner: Pubkey,
+    pub session_key: Pubkey,
+    pub spent: u64,
+    pub refund: u64,             // Unspent escrow returned to the owner
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardPoolCreated {
+    pub market_key: Pubkey,
+    pub reward_pool: Pubkey,
+    pub sponsor: Pubkey,
+    pub emission_rate: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub total_rewards: u64,
+}
+
+#[event]
+pub struct RewardMarketAdded {
+    pub reward_pool: Pubkey,
+    pub market_key: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsClaimed {
+    pub market_key: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnallocatedRewardsWithdrawn {
+    pub reward_pool: Pubkey,
+    pub sponsor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferrerRegistered {
+    pub referrer: Pubkey,
+    pub referral_key: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralRewardAccrued {
+    pub referrer: Pubkey,
+    pub market_key: Pubkey,
+    pub user: Pubkey,
+    pub volume: u64,
+    pub reward: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralRewardsClaimed {
+    pub referrer: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CreatorBondReturned {
+    pub market_key: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketCancelled {
+    pub market_key: Pubkey,
+    pub bond_slashed: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AbandonedMarketVoided {
+    pub market_key: Pubkey,
+    pub bond_slashed: u64,
+    pub refund_pool: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ParentMarketLinked {
+    pub market_key: Pubkey,
+    pub parent_market: Pubkey,
+    pub parent_outcome: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConditionalMarketVoided {
+    pub market_key: Pubkey,
+    pub parent_market: Pubkey,
+    pub refund_pool: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub market_key: Pubkey,
+    pub user: Pubkey,
+    pub refund: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AllowlistRootUpdated {
+    pub market_key: Pubkey,
+    pub previous_root: Option<[u8; 32]>,
+    pub new_root: Option<[u8; 32]>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DynamicFeesUpdated {
+    pub market_key: Pubkey,
+    pub fee_ramp_bps: u16,
+    pub fee_ramp_window: i64,
+    pub volatility_fee_multiplier_bps: u16,
+    pub volatility_window: i64,
+    pub max_dynamic_fee_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CircuitBreakerUpdated {
+    pub market_key: Pubkey,
+    pub move_bps: u16,
+    pub window: i64,
+    pub cooldown: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketHalted {
+    pub market_key: Pubkey,
+    pub reference_price: u64,    // YES price at the start of the window
+    pub yes_price: u64,          // YES price after the tripping trade
+    pub halted_until: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketResumed {
+    pub market_key: Pubkey,
+    pub authority: Option<Pubkey>, // None when the cooldown simply expired
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ExposureLimitsUpdated {
+    pub market_key: Pubkey,
+    pub max_position_stake: u64,
+    pub max_wallet_oi_bps: u16,
+    pub max_price_impact_bps: u16,
+    pub wallet_oi_floor: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PriceHistoryInitialized {
+    pub market_key: Pubkey,
+    pub price_history: Pubkey,
+    pub sample_interval: i64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct WinningsClaimed {
     pub market_key: Pubkey,
@@ -710,10 +5039,120 @@ pub enum ErrorCode {
     InsufficientLiquidity,
     #[msg("Invalid liquidity parameter")]
     InvalidLiquidity,
-    #[msg("Liquidity too low (min: 10)")]
+    #[msg("Initial liquidity too low")]
     LiquidityTooLow,
-    #[msg("Liquidity too high (max: 10000)")]
+    #[msg("Initial liquidity too high")]
     LiquidityTooHigh,
     #[msg("Insufficient shares to sell")]
     InsufficientShares,
+    #[msg("Invalid TWAP observation window")]
+    InvalidTwapWindow,
+    #[msg("Price history sample interval must be positive")]
+    InvalidSampleInterval,
+    #[msg("Price history account is required for this market")]
+    MissingPriceHistory,
+    #[msg("Invalid exposure limit (bps must be <= 10000)")]
+    InvalidExposureLimit,
+    #[msg("Position stake limit exceeded")]
+    PositionStakeLimitExceeded,
+    #[msg("Wallet share of open interest limit exceeded")]
+    WalletOpenInterestLimitExceeded,
+    #[msg("Trade price impact too high")]
+    PriceImpactTooHigh,
+    #[msg("Wallet is not on this market's allowlist")]
+    NotAllowlisted,
+    #[msg("Creator bond already returned or slashed")]
+    BondAlreadySettled,
+    #[msg("Invalid bond vault")]
+    InvalidBondVault,
+    #[msg("Invalid treasury account")]
+    InvalidTreasury,
+    #[msg("Market is not cancelled")]
+    MarketNotCancelled,
+    #[msg("Invalid fee parameter")]
+    InvalidFeeParameter,
+    #[msg("Cannot refer yourself")]
+    SelfReferral,
+    #[msg("Referral vault account missing")]
+    MissingReferralVault,
+    #[msg("Invalid referral vault")]
+    InvalidReferralVault,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Market vault cannot cover the maximum payout owed")]
+    InsolventMarket,
+    #[msg("Invalid market vault")]
+    InvalidMarketVault,
+    #[msg("Claim accounts must be (market, position, vault) triples")]
+    InvalidClaimAccounts,
+    #[msg("Parent market account missing")]
+    MissingParentMarket,
+    #[msg("Invalid parent market")]
+    InvalidParentMarket,
+    #[msg("Parent market not resolved yet")]
+    ParentNotResolved,
+    #[msg("Parent market did not resolve to the required outcome")]
+    ParentConditionFailed,
+    #[msg("Parent market has not failed the condition")]
+    ParentConditionNotFailed,
+    #[msg("Market already has trades")]
+    MarketHasTrades,
+    #[msg("Question too long")]
+    QuestionTooLong,
+    #[msg("Model account does not match")]
+    InvalidModel,
+    #[msg("Drift threshold must be <= 10000 bps")]
+    InvalidDriftThreshold,
+    #[msg("Market has no drift condition")]
+    NotDriftMarket,
+    #[msg("Drift markets resolve through settle_drift_market or finalize_drift_market")]
+    DriftMarketResolution,
+    #[msg("Receipt is outside the market window")]
+    ReceiptOutsideWindow,
+    #[msg("Receipt does not meet the drift condition")]
+    DriftConditionNotMet,
+    #[msg("Invalid session key")]
+    InvalidSessionKey,
+    #[msg("Invalid session vault")]
+    InvalidSessionVault,
+    #[msg("Too many markets for a session key")]
+    TooManySessionMarkets,
+    #[msg("Session key has expired")]
+    SessionExpired,
+    #[msg("Session key is not allowed to trade this market")]
+    SessionMarketNotAllowed,
+    #[msg("Session key spend cap exceeded")]
+    SessionSpendCapExceeded,
+    #[msg("Token account is not owned by the trader")]
+    InvalidTokenOwner,
+    #[msg("Position is not a losing position")]
+    NotALosingPosition,
+    #[msg("Market already has a reward pool")]
+    RewardPoolExists,
+    #[msg("Reward pool funding is below the minimum")]
+    RewardPoolUnderfunded,
+    #[msg("Reward pool does not match the market")]
+    InvalidRewardPool,
+    #[msg("Invalid reward pool parameters")]
+    InvalidRewardParams,
+    #[msg("Invalid reward vault")]
+    InvalidRewardVault,
+    #[msg("Reward pool and reward stake accounts are required for this market")]
+    MissingRewardAccounts,
+    #[msg("Invalid circuit breaker parameters")]
+    InvalidCircuitBreaker,
+    #[msg("Trading is halted by the circuit breaker")]
+    TradingHalted,
+    #[msg("Market is not halted")]
+    MarketNotHalted,
+    #[msg("Invalid crank vault")]
+    InvalidCrankVault,
+    #[msg("Drift settlement grace period has not passed")]
+    SettlementGraceActive,
+    #[msg("Crank tip cannot exceed the market creation fee")]
+    InvalidCrankTip,
+    #[msg("Abandoned market grace period has not passed")]
+    AbandonGraceActive,
+    #[msg("Markets migrated from v0 cannot be traded")]
+    LegacyMarket,
 }
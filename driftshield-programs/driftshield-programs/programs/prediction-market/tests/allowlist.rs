@@ -0,0 +1,70 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::keccak;
+use prediction_market::verify_allowlist_proof;
+
+fn leaf(wallet: &Pubkey) -> [u8; 32] {
+    keccak::hashv(&[&[0u8], wallet.as_ref()]).to_bytes()
+}
+
+fn node(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (min, max) = if a <= b { (a, b) } else { (b, a) };
+    keccak::hashv(&[&[1u8], &min, &max]).to_bytes()
+}
+
+/// Four wallets and the root of the tree ((w0, w1), (w2, w3))
+fn tree() -> ([Pubkey; 4], [u8; 32]) {
+    let wallets = [(); 4].map(|_| Pubkey::new_unique());
+    let [a, b, c, d] = wallets.map(|w| leaf(&w));
+    (wallets, node(node(a, b), node(c, d)))
+}
+
+#[test]
+fn accepts_every_member() {
+    let (wallets, root) = tree();
+    let leaves = wallets.map(|w| leaf(&w));
+    let (left, right) = (node(leaves[0], leaves[1]), node(leaves[2], leaves[3]));
+
+    assert!(verify_allowlist_proof(&[leaves[1], right], root, &wallets[0]));
+    assert!(verify_allowlist_proof(&[leaves[0], right], root, &wallets[1]));
+    assert!(verify_allowlist_proof(&[leaves[3], left], root, &wallets[2]));
+    assert!(verify_allowlist_proof(&[leaves[2], left], root, &wallets[3]));
+}
+
+#[test]
+fn rejects_invalid_proofs() {
+    let (wallets, root) = tree();
+    let leaves = wallets.map(|w| leaf(&w));
+    let right = node(leaves[2], leaves[3]);
+
+    // Outsider borrowing a member's proof
+    assert!(!verify_allowlist_proof(&[leaves[1], right], root, &Pubkey::new_unique()));
+    // Wrong sibling, truncated and overlong proofs
+    assert!(!verify_allowlist_proof(&[leaves[2], right], root, &wallets[0]));
+    assert!(!verify_allowlist_proof(&[leaves[1]], root, &wallets[0]));
+    assert!(!verify_allowlist_proof(&[leaves[1], right, right], root, &wallets[0]));
+    // Right proof, other tree
+    assert!(!verify_allowlist_proof(&[leaves[1], right], tree().1, &wallets[0]));
+}
+
+#[test]
+fn empty_proof_only_matches_a_single_wallet_tree() {
+    let (wallets, root) = tree();
+    assert!(!verify_allowlist_proof(&[], root, &wallets[0]));
+    assert!(verify_allowlist_proof(&[], leaf(&wallets[0]), &wallets[0]));
+    assert!(!verify_allowlist_proof(&[], leaf(&wallets[0]), &wallets[1]));
+}
+
+#[test]
+fn children_are_sorted_and_leaves_cannot_pose_as_nodes() {
+    let (wallets, root) = tree();
+    let leaves = wallets.map(|w| leaf(&w));
+
+    // Sorted children make the root independent of the order the tree was built in
+    let swapped = node(node(leaves[3], leaves[2]), node(leaves[1], leaves[0]));
+    assert_eq!(swapped, root);
+
+    // An inner node presented as a wallet hashes with the leaf prefix, so it misses the root
+    let left = node(leaves[0], leaves[1]);
+    let right = node(leaves[2], leaves[3]);
+    assert!(!verify_allowlist_proof(&[right], root, &Pubkey::new_from_array(left)));
+}
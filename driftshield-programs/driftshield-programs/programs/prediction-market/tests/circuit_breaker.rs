@@ -0,0 +1,98 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use anchor_lang::prelude::*;
+use common::NOW;
+use prediction_market::{get_yes_price, ErrorCode, Market, Position};
+
+const WINDOW: i64 = 3_600;
+const COOLDOWN: i64 = 600;
+
+/// A market halting on a 5% move within an hour, and a trader
+fn market() -> (Market, Position) {
+    let mut market = common::market(1_000_000);
+    market.set_circuit_breaker(500, WINDOW, COOLDOWN, NOW).unwrap();
+    let position = common::position(Pubkey::new_unique(), Pubkey::new_unique());
+    (market, position)
+}
+
+#[test]
+fn trips_on_the_cumulative_move_within_the_window() {
+    let config = common::config();
+    let (mut market, mut position) = market();
+
+    // Each buy moves YES by less than 5%; together they move it by more
+    let fill = market.apply_buy(&mut position, &config, true, 100_000, NOW + 10).unwrap();
+    assert!(!fill.halted && market.halted_until == 0);
+    assert!(get_yes_price(&market).unwrap() - market.breaker_ref_price <= 500);
+
+    // The tripping trade stands
+    let fill = market.apply_buy(&mut position, &config, true, 100_000, NOW + 20).unwrap();
+    assert!(fill.halted);
+    assert_eq!(market.halted_until, NOW + 20 + COOLDOWN);
+    assert!(get_yes_price(&market).unwrap() - market.breaker_ref_price > 500);
+    assert_eq!(position.yes_stake, 2 * fill.net_amount);
+}
+
+#[test]
+fn halts_until_the_cooldown_ends() {
+    let config = common::config();
+    let (mut market, mut position) = market();
+    market.apply_buy(&mut position, &config, true, 300_000, NOW + 10).unwrap();
+    let halted_until = market.halted_until;
+    assert_eq!(halted_until, NOW + 10 + COOLDOWN);
+
+    let err = market.apply_buy(&mut position, &config, false, 1_000, halted_until - 1).err().unwrap();
+    assert_eq!(err, ErrorCode::TradingHalted.into());
+    let err = market.apply_sell(&mut position, &config, true, 1_000, halted_until - 1).err().unwrap();
+    assert_eq!(err, ErrorCode::TradingHalted.into());
+
+    // The first trade after the cooldown clears the halt against the current price
+    let price = get_yes_price(&market).unwrap();
+    let fill = market.apply_buy(&mut position, &config, false, 1_000, halted_until).unwrap();
+    assert!(fill.resumed && !fill.halted);
+    assert_eq!((market.halted_until, market.breaker_ref_time), (0, halted_until));
+    assert_eq!(market.breaker_ref_price, price);
+}
+
+#[test]
+fn reference_rolls_after_the_window() {
+    let config = common::config();
+    let (mut market, mut position) = market();
+
+    market.apply_buy(&mut position, &config, true, 100_000, NOW + 10).unwrap();
+    let price = get_yes_price(&market).unwrap();
+
+    // A window later the reference is the current price, so the same buy doesn't trip
+    assert!(!market.check_circuit_breaker(NOW + WINDOW).unwrap());
+    assert_eq!((market.breaker_ref_price, market.breaker_ref_time), (price, NOW + WINDOW));
+    let fill = market.apply_buy(&mut position, &config, true, 100_000, NOW + WINDOW).unwrap();
+    assert!(!fill.halted);
+}
+
+#[test]
+fn resume_lifts_a_halt_early() {
+    let config = common::config();
+    let (mut market, mut position) = market();
+
+    let err = market.resume(NOW).unwrap_err();
+    assert_eq!(err, ErrorCode::MarketNotHalted.into());
+
+    market.apply_buy(&mut position, &config, true, 300_000, NOW + 10).unwrap();
+    market.resume(NOW + 20).unwrap();
+    assert_eq!(market.halted_until, 0);
+    assert_eq!(market.breaker_ref_price, get_yes_price(&market).unwrap());
+    market.apply_buy(&mut position, &config, false, 1_000, NOW + 30).unwrap();
+}
+
+#[test]
+fn disabled_breaker_never_trips() {
+    let config = common::config();
+    let (mut market, mut position) = market();
+    market.set_circuit_breaker(0, 0, 0, NOW).unwrap();
+
+    let fill = market.apply_buy(&mut position, &config, true, 900_000, NOW + 10).unwrap();
+    assert!(!fill.halted && !market.trip_circuit_breaker(NOW + 10).unwrap());
+    market.apply_buy(&mut position, &config, true, 1_000, NOW + 20).unwrap();
+}
@@ -0,0 +1,84 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{DAY, NOW};
+use prediction_market::{ErrorCode, Market, Position};
+
+/// A resolved market where `alice` backed the winner and `bob` the loser
+fn resolved() -> (Market, Pubkey, Position, Position) {
+    let config = common::config();
+    let mut market = common::market(1_000_000);
+    let key = Pubkey::new_unique();
+    let mut alice = common::position(key, Pubkey::new_unique());
+    let mut bob = common::position(key, Pubkey::new_unique());
+
+    market.apply_buy(&mut alice, &config, true, 200_000, NOW).unwrap();
+    market.apply_buy(&mut bob, &config, false, 100_000, NOW + 60).unwrap();
+    market.close_trading(market.resolution_time).unwrap();
+    market.resolve(true, market.resolution_time + DAY).unwrap();
+    (market, key, alice, bob)
+}
+
+/// `claim_many`'s handling of one entry, claimed by the position's owner
+fn claim(market: &mut Market, key: Pubkey, position: &mut Position) -> Result<Option<(u64, u64)>> {
+    let user = position.user;
+    market.claim_in_batch(key, position, user)
+}
+
+#[test]
+fn claims_winners_and_skips_losers() {
+    let (mut market, key, mut alice, mut bob) = resolved();
+    let collateral = market.total_collateral;
+
+    let (shares, payout) = claim(&mut market, key, &mut alice).unwrap().unwrap();
+    assert_eq!((shares, payout), (alice.yes_shares, alice.yes_shares));
+    assert!(alice.claimed);
+    assert_eq!(market.total_collateral, collateral - payout);
+
+    assert!(claim(&mut market, key, &mut bob).unwrap().is_none());
+    assert!(!bob.claimed);
+    assert_eq!(market.total_collateral, collateral - payout);
+}
+
+#[test]
+fn duplicate_entries_pay_once() {
+    let (mut market, key, mut alice, _) = resolved();
+    let collateral = market.total_collateral;
+
+    let (_, payout) = claim(&mut market, key, &mut alice).unwrap().unwrap();
+    // claim_many persists each entry before the next, so a repeat sees the claim
+    assert!(claim(&mut market, key, &mut alice).unwrap().is_none());
+    assert_eq!(market.total_collateral, collateral - payout);
+}
+
+#[test]
+fn rejects_foreign_positions() {
+    let (mut market, key, mut alice, _) = resolved();
+
+    // A position from another market
+    let mut foreign = alice.clone();
+    foreign.market = Pubkey::new_unique();
+    let err = claim(&mut market, key, &mut foreign).unwrap_err();
+    assert_eq!(err, ErrorCode::InvalidClaimAccounts.into());
+
+    // Someone else's position in this market
+    let err = market.claim_in_batch(key, &mut alice, Pubkey::new_unique()).unwrap_err();
+    assert_eq!(err, ErrorCode::Unauthorized.into());
+    assert!(!alice.claimed);
+}
+
+#[test]
+fn skips_unresolved_markets() {
+    let config = common::config();
+    let mut market = common::market(1_000_000);
+    let key = Pubkey::new_unique();
+    let mut alice = common::position(key, Pubkey::new_unique());
+    market.apply_buy(&mut alice, &config, true, 200_000, NOW).unwrap();
+
+    assert!(claim(&mut market, key, &mut alice).unwrap().is_none());
+    market.close_trading(market.resolution_time).unwrap();
+    assert!(claim(&mut market, key, &mut alice).unwrap().is_none());
+    assert!(!alice.claimed);
+}
@@ -0,0 +1,47 @@
+#![allow(dead_code)]
+
+use anchor_lang::prelude::*;
+use prediction_market::{Market, MarketParams, Position, ProtocolConfig, Versioned};
+
+pub const NOW: i64 = 1_700_000_000;
+pub const DAY: i64 = 86_400;
+
+/// A freshly allocated account body: every field zero, as `init` leaves it
+pub fn zeroed<T: Versioned + AnchorDeserialize>() -> T {
+    let data = vec![0u8; T::SPACE - 8];
+    T::deserialize(&mut data.as_slice()).unwrap()
+}
+
+pub fn config() -> ProtocolConfig {
+    let mut config: ProtocolConfig = zeroed();
+    config.admin = Pubkey::new_unique();
+    config.trading_fee_bps = 100;
+    config.version = ProtocolConfig::VERSION;
+    config
+}
+
+/// A market as `create_market` leaves it at `NOW`, resolving a week later
+pub fn market(initial_liquidity: u64) -> Market {
+    let mut market: Market = zeroed();
+    market.creator = Pubkey::new_unique();
+    market.vault = Pubkey::new_unique();
+    let params = MarketParams {
+        model: Pubkey::new_unique(),
+        question: "Will the fraud model's accuracy drop below 90% this month?".to_string(),
+        resolution_time: NOW + 7 * DAY,
+        min_stake: 1,
+        initial_liquidity,
+        allowlist_root: None,
+    };
+    market.open(params, NOW).unwrap();
+    market
+}
+
+/// An empty position, as `place_bet` first creates it
+pub fn position(market: Pubkey, user: Pubkey) -> Position {
+    let mut position: Position = zeroed();
+    position.market = market;
+    position.user = user;
+    position.version = Position::VERSION;
+    position
+}
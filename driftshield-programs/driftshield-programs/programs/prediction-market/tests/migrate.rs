@@ -0,0 +1,238 @@
+#![allow(clippy::result_large_err)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use driftshield_versioning::{upgrade_account_data, VersioningError};
+use prediction_market::{
+    get_yes_price, ErrorCode, Market, MarketStatus, MarketV0, Position, ProtocolConfig, SessionKey, Versioned,
+};
+
+const NOW: i64 = 1_700_000_000;
+/// Allocated by v0 `place_bet`
+const POSITION_V0_SPACE: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1;
+
+/// Position layout written by the unversioned program
+#[derive(AnchorSerialize)]
+struct PositionV0 {
+    market: Pubkey,
+    user: Pubkey,
+    yes_stake: u64,
+    no_stake: u64,
+    total_stake: u64,
+    yes_shares: u64,
+    no_shares: u64,
+    claimed: bool,
+}
+
+/// Account data as the unversioned program left it: discriminator, body, then zeroes up to `space`
+fn v0_data<T: AnchorSerialize>(discriminator: [u8; 8], account: &T, space: usize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    account.serialize(&mut data).unwrap();
+    assert!(data.len() <= space, "v0 account overflows its allocation");
+    data.resize(space, 0);
+    data
+}
+
+/// Run the generic migration over `data`, grown to `T::SPACE` as `migrate_account` does
+fn migrate<T: Versioned>(mut data: Vec<u8>) -> Result<T> {
+    data.resize(data.len().max(T::SPACE), 0);
+    upgrade_account_data::<T>(&mut data)?;
+    T::try_deserialize(&mut data.as_slice())
+}
+
+/// A v0 market after alice bet 600 on YES and bob 400 on NO. v0 priced off
+/// virtual reserves and never moved them on a buy.
+fn v0_market() -> MarketV0 {
+    MarketV0 {
+        creator: Pubkey::new_unique(),
+        model: Pubkey::new_unique(),
+        question: "Will the fraud model's accuracy drop below 90% this month?".to_string(),
+        yes_pool: 600,
+        no_pool: 400,
+        total_volume: 1_000,
+        status: MarketStatus::Open,
+        resolution_time: NOW + 86_400,
+        resolved_at: 0,
+        winning_outcome: None,
+        min_stake: 10,
+        created_at: NOW - 86_400,
+        bump: 253,
+        amm_enabled: true,
+        virtual_yes_reserve: 3_000,
+        virtual_no_reserve: 1_000,
+        k_constant: 3_000_000,
+        total_yes_shares: 900,
+        total_no_shares: 350,
+    }
+}
+
+fn v0_position(market: Pubkey, yes_stake: u64, yes_shares: u64, no_stake: u64, no_shares: u64) -> PositionV0 {
+    PositionV0 {
+        market,
+        user: Pubkey::new_unique(),
+        yes_stake,
+        no_stake,
+        total_stake: yes_stake + no_stake,
+        yes_shares,
+        no_shares,
+        claimed: false,
+    }
+}
+
+fn migrated_position(v0: PositionV0) -> Position {
+    migrate(v0_data(Position::DISCRIMINATOR, &v0, POSITION_V0_SPACE)).unwrap()
+}
+
+fn translate(v0: &MarketV0, vault: Pubkey, vault_balance: u64) -> Result<Market> {
+    let data = v0_data(Market::DISCRIMINATOR, v0, MarketV0::SPACE);
+    let decoded = MarketV0::try_decode(&data)?;
+    let market = Market::from_v0(decoded, vault, vault_balance, NOW)?;
+
+    // Written back the way migrate_market writes it
+    let mut data = vec![0u8; Market::SPACE];
+    market.try_serialize(&mut &mut data[..])?;
+    Market::try_deserialize(&mut data.as_slice())
+}
+
+#[test]
+fn translates_v0_market() {
+    let v0 = v0_market();
+    let vault = Pubkey::new_unique();
+    let market = translate(&v0, vault, 1_000).unwrap();
+
+    assert_eq!(market.creator, v0.creator);
+    assert_eq!(market.question, v0.question);
+    assert_eq!((market.yes_pool, market.no_pool, market.total_volume), (600, 400, 1_000));
+    assert!(market.status == MarketStatus::Open);
+    assert_eq!((market.total_yes_shares, market.total_no_shares), (900, 350));
+    assert_eq!((market.min_stake, market.bump), (10, 253));
+
+    // v0 priced YES at yes / (yes + no) = 75%; the swapped reserves keep that price
+    assert_eq!((market.yes_reserve, market.no_reserve), (1_000, 3_000));
+    assert_eq!(market.k_constant, 3_000_000u128);
+    assert_eq!(get_yes_price(&market).unwrap(), 7_500);
+    assert_eq!(market.breaker_ref_price, 7_500);
+
+    assert_eq!((market.vault, market.bond_vault), (vault, vault));
+    assert_eq!((market.total_collateral, market.open_stake), (1_000, 1_000));
+    assert!(market.bond_settled && market.creator_bond == 0);
+    assert_eq!(market.last_price_update, NOW);
+    assert!(market.legacy);
+    assert_eq!(market.version, Market::VERSION);
+    market.assert_solvent(1_000).unwrap();
+}
+
+#[test]
+fn migrated_v0_market_pays_winners_pari_mutuel() {
+    let mut market = translate(&v0_market(), Pubkey::new_unique(), 1_000).unwrap();
+    let market_key = Pubkey::new_unique();
+
+    let mut alice = migrated_position(v0_position(market_key, 600, 900, 0, 0));
+    let mut bob = migrated_position(v0_position(market_key, 0, 0, 400, 350));
+
+    market.resolve(true, market.resolution_time).unwrap();
+    assert_eq!(market.lp_claimable, 0);
+
+    // v0 paid shares * (yes_pool + no_pool) / total_winning_shares
+    let (shares, payout) = market.claim_winnings(&mut alice).unwrap();
+    assert_eq!((shares, payout), (900, 900 * 1_000 / 900));
+    assert!(alice.claimed);
+    assert_eq!(market.total_collateral, 0);
+    market.assert_solvent(0).unwrap();
+
+    assert_eq!(market.claim_winnings(&mut alice).unwrap_err(), ErrorCode::AlreadyClaimed.into());
+    assert_eq!(market.claim_winnings(&mut bob).unwrap_err(), ErrorCode::NoWinningStake.into());
+}
+
+#[test]
+fn splits_pot_across_winning_shares() {
+    let mut v0 = v0_market();
+    v0.status = MarketStatus::Resolved;
+    v0.resolved_at = v0.resolution_time;
+    v0.winning_outcome = Some(false);
+    v0.no_pool = 500;
+    v0.total_no_shares = 700;
+    let mut market = translate(&v0, Pubkey::new_unique(), 1_100).unwrap();
+    assert!(market.status == MarketStatus::Resolved);
+
+    let market_key = Pubkey::new_unique();
+    let mut small = migrated_position(v0_position(market_key, 0, 0, 100, 300));
+    let mut large = migrated_position(v0_position(market_key, 0, 0, 400, 400));
+
+    assert_eq!(market.claim_winnings(&mut small).unwrap().1, 300 * 1_100 / 700);
+    assert_eq!(market.claim_winnings(&mut large).unwrap().1, 400 * 1_100 / 700);
+    // Rounding leaves dust in the vault, never a shortfall
+    assert_eq!(market.total_collateral, 1_100 - 471 - 628);
+}
+
+#[test]
+fn migrated_v0_market_cannot_trade() {
+    let mut market = translate(&v0_market(), Pubkey::new_unique(), 1_000).unwrap();
+    let mut position = migrated_position(v0_position(Pubkey::new_unique(), 600, 900, 0, 0));
+    let config = ProtocolConfig::deserialize(&mut &vec![0u8; ProtocolConfig::SPACE - 8][..]).unwrap();
+
+    let err = market.apply_buy(&mut position, &config, true, 100, NOW).unwrap_err();
+    assert_eq!(err, ErrorCode::LegacyMarket.into());
+    let err = market.apply_sell(&mut position, &config, true, 100, NOW).unwrap_err();
+    assert_eq!(err, ErrorCode::LegacyMarket.into());
+}
+
+#[test]
+fn cancelled_v0_market_refunds_stakes() {
+    let mut v0 = v0_market();
+    v0.status = MarketStatus::Cancelled;
+    v0.resolved_at = NOW - 60;
+    let mut market = translate(&v0, Pubkey::new_unique(), 1_000).unwrap();
+
+    assert_eq!((market.refund_pool, market.lp_claimable), (1_000, 0));
+    let mut position = migrated_position(v0_position(Pubkey::new_unique(), 600, 900, 0, 0));
+    assert_eq!(market.claim_refund(&mut position).unwrap(), 600);
+}
+
+#[test]
+fn rejects_underfunded_vault_and_foreign_data() {
+    let err = translate(&v0_market(), Pubkey::new_unique(), 999).err().unwrap();
+    assert_eq!(err, ErrorCode::InsolventMarket.into());
+
+    let data = v0_data(Position::DISCRIMINATOR, &v0_market(), MarketV0::SPACE);
+    assert!(MarketV0::try_decode(&data).is_err());
+}
+
+#[test]
+fn migrates_v0_position() {
+    let v0 = v0_position(Pubkey::new_unique(), 250, 400, 50, 80);
+    let data = v0_data(Position::DISCRIMINATOR, &v0, POSITION_V0_SPACE);
+
+    let position: Position = migrate(data.clone()).unwrap();
+    assert_eq!((position.market, position.user), (v0.market, v0.user));
+    assert_eq!((position.yes_stake, position.no_stake, position.total_stake), (250, 50, 300));
+    assert_eq!((position.yes_shares, position.no_shares), (400, 80));
+    assert!(!position.claimed);
+    assert_eq!(position.version, Position::VERSION);
+
+    // A second migration finds the account current
+    let mut data = data;
+    data.resize(Position::SPACE, 0);
+    upgrade_account_data::<Position>(&mut data).unwrap();
+    let err = upgrade_account_data::<Position>(&mut data).unwrap_err();
+    assert_eq!(err, VersioningError::AlreadyMigrated.into());
+}
+
+#[test]
+fn generic_migration_rejects_layouts_without_a_translation() {
+    // A v0 market is not a prefix of the current layout
+    let data = v0_data(Market::DISCRIMINATOR, &v0_market(), MarketV0::SPACE);
+    assert!(migrate::<Market>(data).is_err());
+
+    let mut market = translate(&v0_market(), Pubkey::new_unique(), 1_000).unwrap();
+    market.version = 0;
+    let mut data = vec![0u8; Market::SPACE];
+    market.try_serialize(&mut &mut data[..]).unwrap();
+    let err = upgrade_account_data::<Market>(&mut data).unwrap_err();
+    assert_eq!(err, VersioningError::UnsupportedVersion.into());
+
+    let mut data = vec![0u8; SessionKey::SPACE];
+    data[..8].copy_from_slice(&SessionKey::DISCRIMINATOR);
+    let err = upgrade_account_data::<SessionKey>(&mut data).unwrap_err();
+    assert_eq!(err, VersioningError::UnsupportedVersion.into());
+}
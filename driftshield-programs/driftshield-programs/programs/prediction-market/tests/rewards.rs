@@ -0,0 +1,104 @@
+#![allow(clippy::result_large_err)]
+
+mod common;
+
+use anchor_lang::prelude::*;
+use common::NOW;
+use prediction_market::{RewardPool, RewardStake, REWARD_PRECISION};
+
+/// A pool emitting `emission_rate` per second for 1,000 seconds from `NOW`
+fn pool(emission_rate: u64) -> RewardPool {
+    let mut pool: RewardPool = common::zeroed();
+    pool.sponsor = Pubkey::new_unique();
+    pool.emission_rate = emission_rate;
+    pool.start_time = NOW;
+    pool.end_time = NOW + 1_000;
+    pool.last_update = NOW;
+    pool.total_funded = emission_rate * 1_000;
+    pool
+}
+
+/// What a trade does to a wallet's reward stake: advance the pool, then move the stake
+fn restake(pool: &mut RewardPool, stake: &mut RewardStake, staked: u64, now: i64) -> Result<()> {
+    pool.update(now)?;
+    stake.sync(pool, staked)
+}
+
+#[test]
+fn splits_emissions_by_stake_over_time() {
+    let mut pool = pool(10);
+    let (mut alice, mut bob): (RewardStake, RewardStake) = (common::zeroed(), common::zeroed());
+
+    restake(&mut pool, &mut alice, 100, NOW).unwrap();
+    restake(&mut pool, &mut bob, 300, NOW + 100).unwrap();
+    // Alice earned every token of the first 100 seconds
+    assert_eq!(pool.reward_per_share, 1_000 * REWARD_PRECISION / 100);
+    assert_eq!(pool.total_staked, 400);
+
+    restake(&mut pool, &mut alice, 100, NOW + 200).unwrap();
+    restake(&mut pool, &mut bob, 300, NOW + 200).unwrap();
+    // Then a quarter and three quarters of the next 100
+    assert_eq!((alice.pending, bob.pending), (1_000 + 250, 750));
+}
+
+#[test]
+fn stake_changes_settle_at_the_old_stake() {
+    let mut pool = pool(10);
+    let (mut alice, mut bob): (RewardStake, RewardStake) = (common::zeroed(), common::zeroed());
+    restake(&mut pool, &mut alice, 100, NOW).unwrap();
+    restake(&mut pool, &mut bob, 100, NOW).unwrap();
+
+    // Alice triples her stake halfway; the first half still splits evenly
+    restake(&mut pool, &mut alice, 300, NOW + 500).unwrap();
+    assert_eq!((alice.pending, alice.staked, pool.total_staked), (2_500, 300, 400));
+
+    // Bob exits at 750: 2,500 plus a quarter of 250 seconds
+    restake(&mut pool, &mut bob, 0, NOW + 750).unwrap();
+    assert_eq!((bob.pending, pool.total_staked), (2_500 + 625, 300));
+
+    // Emissions stop at end_time and the rest goes to alice. 2,500 / 300 per share
+    // rounds down, leaving a token of dust in the pool.
+    restake(&mut pool, &mut alice, 300, NOW + 5_000).unwrap();
+    assert_eq!(alice.pending, 2_500 + 1_875 + 2_500 - 1);
+    assert_eq!(alice.pending + bob.pending, 10_000 - 1);
+}
+
+#[test]
+fn unstaked_time_is_unallocated() {
+    let mut pool = pool(10);
+    let mut alice: RewardStake = common::zeroed();
+
+    pool.update(NOW + 100).unwrap();
+    assert_eq!((pool.unallocated, pool.reward_per_share), (1_000, 0));
+
+    restake(&mut pool, &mut alice, 50, NOW + 100).unwrap();
+    restake(&mut pool, &mut alice, 0, NOW + 300).unwrap();
+    pool.update(NOW + 1_000).unwrap();
+    assert_eq!((alice.pending, pool.unallocated), (2_000, 1_000 + 7_000));
+
+    // A late or repeated update changes nothing
+    let before = pool.clone();
+    pool.update(NOW + 999).unwrap();
+    pool.update(NOW + 2_000).unwrap();
+    assert_eq!((pool.last_update, pool.unallocated), (before.last_update, before.unallocated));
+}
+
+#[test]
+fn rounding_never_pays_out_more_than_emitted() {
+    let mut pool = pool(7);
+    let mut stakes: Vec<RewardStake> = (0..3).map(|_| common::zeroed()).collect();
+
+    for (i, stake) in stakes.iter_mut().enumerate() {
+        restake(&mut pool, stake, 3 + i as u64, NOW + i as i64).unwrap();
+    }
+    for t in [NOW + 333, NOW + 667, NOW + 1_000] {
+        for (i, stake) in stakes.iter_mut().enumerate() {
+            restake(&mut pool, stake, 3 + i as u64 + (t - NOW) as u64 % 5, t).unwrap();
+        }
+    }
+
+    let paid: u64 = stakes.iter().map(|s| s.pending).sum();
+    assert!(paid + pool.unallocated <= 7_000);
+    // Dust is at most a unit per sync
+    assert!(paid + pool.unallocated + 12 >= 7_000, "{}", paid + pool.unallocated);
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{DAY, NOW};
+use prediction_market::{ErrorCode, SessionKey};
+
+/// A session allowed `spend_cap` across `allowed_markets` for a day
+fn session(spend_cap: u64, allowed_markets: Vec<Pubkey>) -> SessionKey {
+    let mut session: SessionKey = common::zeroed();
+    session.owner = Pubkey::new_unique();
+    session.session_key = Pubkey::new_unique();
+    session.spend_cap = spend_cap;
+    session.allowed_markets = allowed_markets;
+    session.created_at = NOW;
+    session.expires_at = NOW + DAY;
+    session
+}
+
+#[test]
+fn spend_cap_covers_every_trade() {
+    let market = Pubkey::new_unique();
+    let mut session = session(1_000, Vec::new());
+
+    session.authorize(market, 600, NOW).unwrap();
+    session.authorize(Pubkey::new_unique(), 400, NOW + 60).unwrap();
+    assert_eq!(session.spent, 1_000);
+
+    let err = session.authorize(market, 1, NOW + 120).unwrap_err();
+    assert_eq!(err, ErrorCode::SessionSpendCapExceeded.into());
+    assert_eq!(session.spent, 1_000);
+
+    // Sells and claims spend nothing and stay allowed at the cap
+    session.authorize(market, 0, NOW + 180).unwrap();
+}
+
+#[test]
+fn rejected_trades_spend_nothing() {
+    let mut session = session(1_000, Vec::new());
+    let err = session.authorize(Pubkey::new_unique(), 1_001, NOW).unwrap_err();
+    assert_eq!(err, ErrorCode::SessionSpendCapExceeded.into());
+    assert_eq!(session.spent, 0);
+
+    session.spent = u64::MAX - 1;
+    let err = session.authorize(Pubkey::new_unique(), 2, NOW).unwrap_err();
+    assert_eq!(err, ErrorCode::MathOverflow.into());
+}
+
+#[test]
+fn allowed_markets_restrict_the_key() {
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut session = session(1_000, vec![a, b]);
+
+    session.authorize(a, 100, NOW).unwrap();
+    session.authorize(b, 100, NOW).unwrap();
+    let err = session.authorize(Pubkey::new_unique(), 100, NOW).unwrap_err();
+    assert_eq!(err, ErrorCode::SessionMarketNotAllowed.into());
+    assert_eq!(session.spent, 200);
+}
+
+#[test]
+fn expired_sessions_cannot_trade() {
+    let market = Pubkey::new_unique();
+    let mut session = session(1_000, vec![market]);
+
+    session.authorize(market, 100, NOW + DAY - 1).unwrap();
+    let err = session.authorize(market, 100, NOW + DAY).unwrap_err();
+    assert_eq!(err, ErrorCode::SessionExpired.into());
+    let err = session.authorize(market, 0, NOW + 2 * DAY).unwrap_err();
+    assert_eq!(err, ErrorCode::SessionExpired.into());
+    assert_eq!(session.spent, 100);
+}